@@ -0,0 +1,641 @@
+//! Compute-unit benchmarks for the settlement hot path.
+//!
+//! Build the program first, then point Mollusk at the output:
+//!
+//! ```sh
+//! cargo build-sbf
+//! SBF_OUT_DIR=../../target/deploy cargo bench --bench compute_units
+//! ```
+//!
+//! Results are written to `target/benches/compute_units.md`; compare them
+//! against the previous run to catch regressions.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::{InstructionData, Space, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token::state::{Account as TokenState, AccountState, Mint as MintState};
+use mollusk_svm::program::{create_program_account_loader_v3, keyed_account_for_system_program};
+use mollusk_svm::Mollusk;
+use mollusk_svm_bencher::MolluskComputeUnitBencher;
+use mollusk_svm_programs_token::{associated_token, token};
+use solana_account::Account;
+use tossr_engine::{
+    accounts, instruction, Bet, BettingMode, CommunityEntry, Config, JackpotPot, Market,
+    MarketType, OutcomeType, ParamValues, Round, RoundStatus, Selection, SelectionKind,
+    SettlementMode, Streak, StreakStatus, Treasury, Versioned,
+};
+
+const MARKET_INDEX: u16 = 0;
+const ROUND_NUMBER: u64 = 1;
+const OUTCOME: u16 = 42;
+const STAKE: u64 = 1_000_000;
+const ODDS_BPS: u32 = 20_000;
+const BATCH_SIZE: usize = 4;
+const STREAK_TARGET: u16 = 5;
+const COMMUNITY_BYTE: u8 = 7;
+const JACKPOT: u64 = 50_000_000;
+
+fn pda(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &tossr_engine::ID)
+}
+
+fn rent_exempt(len: usize) -> u64 {
+    Rent::default().minimum_balance(len)
+}
+
+fn program_account<T: AccountSerialize + Space>(value: &T) -> Account {
+    let mut data = Vec::with_capacity(8 + T::INIT_SPACE);
+    value.try_serialize(&mut data).unwrap();
+    data.resize(8 + T::INIT_SPACE, 0);
+    Account {
+        lamports: rent_exempt(data.len()),
+        data,
+        owner: tossr_engine::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn packed_account<T: Pack>(value: T) -> Account {
+    let mut data = vec![0; T::LEN];
+    T::pack(value, &mut data).unwrap();
+    Account {
+        lamports: rent_exempt(T::LEN),
+        data,
+        owner: anchor_spl::token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    packed_account(TokenState {
+        mint,
+        owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenState::default()
+    })
+}
+
+/// A market with one revealed, base-settled round and everything needed to
+/// settle bets against it.
+struct Fixture {
+    operator: Pubkey,
+    config: Pubkey,
+    market: Pubkey,
+    round: Pubkey,
+    round_bump: u8,
+    vault_authority: Pubkey,
+    vault_bump: u8,
+    vault_token: Pubkey,
+    mint: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let (config, _) = pda(&[b"config"]);
+        let (market, _) = pda(&[b"market", &MARKET_INDEX.to_le_bytes()]);
+        let (round, round_bump) = pda(&[b"round", market.as_ref(), &ROUND_NUMBER.to_le_bytes()]);
+        let (vault_authority, vault_bump) = pda(&[b"vault", market.as_ref()]);
+        let mint = Pubkey::new_unique();
+        Self {
+            operator: Pubkey::new_unique(),
+            config,
+            market,
+            round,
+            round_bump,
+            vault_authority,
+            vault_bump,
+            vault_token: get_associated_token_address(&vault_authority, &mint),
+            mint,
+        }
+    }
+
+    fn config_account(&self) -> Account {
+        program_account(&Config {
+            version: Config::VERSION,
+            owner: self.operator,
+            pending_owner: Pubkey::default(),
+            operator: self.operator,
+            oracle: self.operator,
+            treasurer: self.operator,
+            guardian: self.operator,
+            pause_flags: 0,
+        })
+    }
+
+    fn market_account(&self) -> Account {
+        program_account(&Market {
+            version: Market::VERSION,
+            admin: self.config,
+            name: "bench".to_string(),
+            is_active: true,
+            last_round: ROUND_NUMBER,
+            house_edge_bps: 200,
+            mint: self.mint,
+            market_type: MarketType::PickRange,
+            index: MARKET_INDEX,
+            vault_bump: self.vault_bump,
+            balance_vault_bump: pda(&[b"balance_vault", self.mint.as_ref()]).1,
+            betting_mode: BettingMode::FixedOdds as u8,
+        })
+    }
+
+    fn round_account(&self, status: RoundStatus, bets: u32) -> Account {
+        program_account(&self.round_state(status, bets))
+    }
+
+    fn round_state(&self, status: RoundStatus, bets: u32) -> Round {
+        let revealed = status == RoundStatus::Locked;
+        Round {
+            version: Round::VERSION,
+            market: self.market,
+            number: ROUND_NUMBER,
+            status: status as u8,
+            inputs_hash: [0; 32],
+            outcome: if revealed {
+                OutcomeType::Numeric { value: OUTCOME }
+            } else {
+                OutcomeType::Pending
+            },
+            unsettled_bets: bets,
+            opened_at: 1,
+            lock_scheduled_at: 0,
+            locked_at: if revealed { 2 } else { 0 },
+            commitment_hash: None,
+            revealed_at: if revealed { 3 } else { 0 },
+            player_entropy: [0; 32],
+            entropy_contributions: 0,
+            vrf_seed: [0; 32],
+            vrf_requested_at: 0,
+            vrf_fulfilled: revealed,
+            delegation_status: 0,
+            settlement_mode: SettlementMode::Base as u8,
+            balance_staked: 0,
+            balance_credited: 0,
+            balances_synced: false,
+            open_liability: u64::from(bets) * STAKE * 2,
+            params: ParamValues::default(),
+            params_revision: 0,
+            open_bets: bets,
+            rent_payer: self.operator,
+            bump: self.round_bump,
+            betting_mode: BettingMode::FixedOdds as u8,
+            pools: Vec::new(),
+            exposures: Vec::new(),
+        }
+    }
+
+    /// The fixture round revealed as a community round landing on
+    /// `COMMUNITY_BYTE`.
+    fn community_round_account(&self) -> Account {
+        program_account(&Round {
+            outcome: OutcomeType::Community {
+                final_byte: COMMUNITY_BYTE,
+                seed_hash: [0; 32],
+            },
+            ..self.round_state(RoundStatus::Locked, 0)
+        })
+    }
+
+    /// A bet by `user` that wins when `winning` and is already settled and
+    /// paid out when `closed`.
+    fn bet(&self, user: Pubkey, winning: bool, closed: bool) -> (Pubkey, Account) {
+        let (key, bump) = pda(&[b"bet", self.round.as_ref(), user.as_ref()]);
+        let selection = if winning {
+            Selection {
+                kind: SelectionKind::Range as u8,
+                a: 1,
+                b: 50,
+                c: 0,
+            }
+        } else {
+            Selection {
+                kind: SelectionKind::Single as u8,
+                a: OUTCOME + 1,
+                b: 0,
+                c: 0,
+            }
+        };
+        let account = program_account(&Bet {
+            version: Bet::VERSION,
+            user,
+            round: self.round,
+            stake: STAKE,
+            selection,
+            odds_bps: ODDS_BPS,
+            settled: closed,
+            won: closed && winning,
+            payout: 0,
+            placed_at: 1,
+            paid_out: closed,
+            funded_from_balance: false,
+            rent_payer: user,
+            bump,
+        });
+        (key, account)
+    }
+
+    fn mint_account(&self) -> Account {
+        packed_account(MintState {
+            supply: u64::MAX / 2,
+            decimals: 6,
+            is_initialized: true,
+            ..MintState::default()
+        })
+    }
+
+    fn vault_accounts(&self) -> Vec<(Pubkey, Account)> {
+        vec![
+            (self.vault_authority, Account::default()),
+            (
+                self.vault_token,
+                token_account(self.mint, self.vault_authority, u64::MAX / 4),
+            ),
+            (self.mint, self.mint_account()),
+            token::keyed_account(),
+        ]
+    }
+
+    fn settle_bet(&self, user: Pubkey, winning: bool) -> (Instruction, Vec<(Pubkey, Account)>) {
+        let (bet, bet_account) = self.bet(user, winning, false);
+        let user_token = get_associated_token_address(&user, &self.mint);
+        let ix = Instruction {
+            program_id: tossr_engine::ID,
+            accounts: accounts::SettleBet {
+                admin: self.operator,
+                market: self.market,
+                config: self.config,
+                round: self.round,
+                bet,
+                vault_authority: self.vault_authority,
+                vault_token: self.vault_token,
+                user_token,
+                user,
+                mint: self.mint,
+                token_program: anchor_spl::token::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::SettleBet {}.data(),
+        };
+        let mut keyed = vec![
+            (
+                self.operator,
+                Account::new(10_000_000_000, 0, &anchor_lang::system_program::ID),
+            ),
+            (self.market, self.market_account()),
+            (self.config, self.config_account()),
+            (self.round, self.round_account(RoundStatus::Locked, 1)),
+            (bet, bet_account),
+            (user_token, token_account(self.mint, user, 0)),
+            (user, Account::default()),
+            associated_token::keyed_account(),
+            keyed_account_for_system_program(),
+        ];
+        keyed.extend(self.vault_accounts());
+        (ix, keyed)
+    }
+
+    fn claim_bet(&self, user: Pubkey) -> (Instruction, Vec<(Pubkey, Account)>) {
+        let (bet, bet_account) = self.bet(user, true, false);
+        let user_token = get_associated_token_address(&user, &self.mint);
+        let ix = Instruction {
+            program_id: tossr_engine::ID,
+            accounts: accounts::ClaimBet {
+                claimer: user,
+                market: self.market,
+                config: self.config,
+                round: self.round,
+                bet,
+                vault_authority: self.vault_authority,
+                vault_token: self.vault_token,
+                user_token,
+                user,
+                mint: self.mint,
+                token_program: anchor_spl::token::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::ClaimBet {}.data(),
+        };
+        let mut keyed = vec![
+            (
+                user,
+                Account::new(10_000_000_000, 0, &anchor_lang::system_program::ID),
+            ),
+            (self.market, self.market_account()),
+            (self.config, self.config_account()),
+            (self.round, self.round_account(RoundStatus::Locked, 1)),
+            (bet, bet_account),
+            (user_token, token_account(self.mint, user, 0)),
+            associated_token::keyed_account(),
+            keyed_account_for_system_program(),
+        ];
+        keyed.extend(self.vault_accounts());
+        (ix, keyed)
+    }
+
+    fn settle_bets_batch(&self) -> (Instruction, Vec<(Pubkey, Account)>) {
+        let mut metas = accounts::SettleBetsBatch {
+            admin: self.operator,
+            market: self.market,
+            config: self.config,
+            round: self.round,
+            vault_authority: self.vault_authority,
+            vault_token: self.vault_token,
+            mint: self.mint,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(None);
+        let mut keyed = vec![
+            (
+                self.operator,
+                Account::new(10_000_000_000, 0, &anchor_lang::system_program::ID),
+            ),
+            (self.market, self.market_account()),
+            (self.config, self.config_account()),
+            (
+                self.round,
+                self.round_account(RoundStatus::Locked, BATCH_SIZE as u32),
+            ),
+        ];
+        keyed.extend(self.vault_accounts());
+        for i in 0..BATCH_SIZE {
+            let user = Pubkey::new_unique();
+            let (bet, bet_account) = self.bet(user, i % 2 == 0, false);
+            let user_token = get_associated_token_address(&user, &self.mint);
+            metas.push(AccountMeta::new(bet, false));
+            metas.push(AccountMeta::new(user_token, false));
+            keyed.push((bet, bet_account));
+            keyed.push((user_token, token_account(self.mint, user, 0)));
+        }
+        let ix = Instruction {
+            program_id: tossr_engine::ID,
+            accounts: metas,
+            data: instruction::SettleBetsBatch {}.data(),
+        };
+        (ix, keyed)
+    }
+
+    fn lock_round(&self) -> (Instruction, Vec<(Pubkey, Account)>) {
+        let ix = Instruction {
+            program_id: tossr_engine::ID,
+            accounts: accounts::LockRound {
+                admin: self.operator,
+                market: self.market,
+                config: self.config,
+                round: self.round,
+                treasury: None,
+                vault_authority: None,
+                vault_token: None,
+                cranker_token: None,
+                mint: None,
+                token_program: None,
+            }
+            .to_account_metas(None),
+            data: instruction::LockRound {}.data(),
+        };
+        let keyed = vec![
+            (
+                self.operator,
+                Account::new(10_000_000_000, 0, &anchor_lang::system_program::ID),
+            ),
+            (self.market, self.market_account()),
+            (self.config, self.config_account()),
+            (self.round, self.round_account(RoundStatus::Predicting, 0)),
+            (
+                tossr_engine::ID,
+                create_program_account_loader_v3(&tossr_engine::ID),
+            ),
+        ];
+        (ix, keyed)
+    }
+
+    fn claim_streak_reward(&self, user: Pubkey) -> (Instruction, Vec<(Pubkey, Account)>) {
+        let (streak, _) = pda(&[b"streak", user.as_ref(), self.market.as_ref()]);
+        let (treasury, _) = pda(&[b"treasury", self.market.as_ref()]);
+        let user_token = get_associated_token_address(&user, &self.mint);
+        let ix = Instruction {
+            program_id: tossr_engine::ID,
+            accounts: accounts::ClaimStreakReward {
+                user,
+                market: self.market,
+                config: self.config,
+                streak,
+                treasury,
+                vault_authority: self.vault_authority,
+                vault_token: self.vault_token,
+                user_token,
+                mint: self.mint,
+                token_program: anchor_spl::token::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::ClaimStreakReward {}.data(),
+        };
+        let mut keyed = vec![
+            (
+                user,
+                Account::new(10_000_000_000, 0, &anchor_lang::system_program::ID),
+            ),
+            (self.market, self.market_account()),
+            (self.config, self.config_account()),
+            (
+                streak,
+                program_account(&Streak {
+                    version: Streak::VERSION,
+                    user,
+                    market: self.market,
+                    target: STREAK_TARGET,
+                    current_streak: STREAK_TARGET,
+                    status: StreakStatus::Completed as u8,
+                    last_round: self.round,
+                    rent_payer: user,
+                }),
+            ),
+            (
+                treasury,
+                program_account(&Treasury {
+                    version: Treasury::VERSION,
+                    market: self.market,
+                    settled_through: 0,
+                    streak_liability: u64::MAX / 8,
+                    total_withdrawn: 0,
+                    lp_enabled: false,
+                    crank_reward: 0,
+                    parlay_liability: 0,
+                }),
+            ),
+            (user_token, token_account(self.mint, user, 0)),
+            associated_token::keyed_account(),
+            keyed_account_for_system_program(),
+        ];
+        keyed.extend(self.vault_accounts());
+        (ix, keyed)
+    }
+
+    fn settle_community_entry(&self, user: Pubkey) -> (Instruction, Vec<(Pubkey, Account)>) {
+        let (community_entry, _) = pda(&[b"community", self.round.as_ref(), user.as_ref()]);
+        let user_token = get_associated_token_address(&user, &self.mint);
+        let ix = Instruction {
+            program_id: tossr_engine::ID,
+            accounts: accounts::SettleCommunityEntry {
+                admin: self.operator,
+                market: self.market,
+                config: self.config,
+                round: self.round,
+                community_entry,
+                vault_authority: self.vault_authority,
+                vault_token: self.vault_token,
+                user_token,
+                user,
+                mint: self.mint,
+                token_program: anchor_spl::token::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::SettleCommunityEntry {}.data(),
+        };
+        let mut keyed = vec![
+            (
+                self.operator,
+                Account::new(10_000_000_000, 0, &anchor_lang::system_program::ID),
+            ),
+            (self.market, self.market_account()),
+            (self.config, self.config_account()),
+            (self.round, self.community_round_account()),
+            (
+                community_entry,
+                program_account(&CommunityEntry {
+                    version: CommunityEntry::VERSION,
+                    user,
+                    round: self.round,
+                    seed_byte: COMMUNITY_BYTE,
+                    distance: None,
+                    won: false,
+                    rent_payer: user,
+                }),
+            ),
+            (user_token, token_account(self.mint, user, 0)),
+            (user, Account::default()),
+            associated_token::keyed_account(),
+            keyed_account_for_system_program(),
+        ];
+        keyed.extend(self.vault_accounts());
+        (ix, keyed)
+    }
+
+    fn claim_jackpot(&self, user: Pubkey) -> (Instruction, Vec<(Pubkey, Account)>) {
+        let (bet, bet_account) = self.bet(user, true, true);
+        let (jackpot_pot, _) = pda(&[b"jackpot_pot", self.market.as_ref()]);
+        let user_token = get_associated_token_address(&user, &self.mint);
+        let ix = Instruction {
+            program_id: tossr_engine::ID,
+            accounts: accounts::ClaimJackpot {
+                user,
+                market: self.market,
+                config: self.config,
+                round: self.round,
+                bet,
+                jackpot_pot,
+                vault_authority: self.vault_authority,
+                vault_token: self.vault_token,
+                user_token,
+                mint: self.mint,
+                token_program: anchor_spl::token::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::ClaimJackpot {}.data(),
+        };
+        let mut keyed = vec![
+            (
+                user,
+                Account::new(1_000_000_000, 0, &anchor_lang::system_program::ID),
+            ),
+            (self.market, self.market_account()),
+            (self.config, self.config_account()),
+            (self.round, self.round_account(RoundStatus::Settled, 0)),
+            (bet, bet_account),
+            (
+                jackpot_pot,
+                program_account(&JackpotPot {
+                    version: JackpotPot::VERSION,
+                    market: self.market,
+                    current_pot: JACKPOT,
+                    last_winner: None,
+                    total_contributed: JACKPOT,
+                }),
+            ),
+            (user_token, token_account(self.mint, user, 0)),
+        ];
+        keyed.extend(self.vault_accounts());
+        (ix, keyed)
+    }
+
+    fn close_bet(&self, user: Pubkey) -> (Instruction, Vec<(Pubkey, Account)>) {
+        let (bet, bet_account) = self.bet(user, false, true);
+        let ix = Instruction {
+            program_id: tossr_engine::ID,
+            accounts: accounts::CloseBet {
+                closer: user,
+                market: self.market,
+                config: self.config,
+                round: self.round,
+                bet,
+                rent_payer: user,
+            }
+            .to_account_metas(None),
+            data: instruction::CloseBet {}.data(),
+        };
+        let keyed = vec![
+            (
+                user,
+                Account::new(1_000_000_000, 0, &anchor_lang::system_program::ID),
+            ),
+            (self.market, self.market_account()),
+            (self.config, self.config_account()),
+            (self.round, self.round_account(RoundStatus::Settled, 1)),
+            (bet, bet_account),
+        ];
+        (ix, keyed)
+    }
+}
+
+fn main() {
+    let mut mollusk = Mollusk::new(&tossr_engine::ID, "tossr_engine");
+    token::add_program(&mut mollusk);
+    associated_token::add_program(&mut mollusk);
+
+    let fixture = Fixture::new();
+    let (lock_ix, lock_accounts) = fixture.lock_round();
+    let (win_ix, win_accounts) = fixture.settle_bet(Pubkey::new_unique(), true);
+    let (loss_ix, loss_accounts) = fixture.settle_bet(Pubkey::new_unique(), false);
+    let (claim_ix, claim_accounts) = fixture.claim_bet(Pubkey::new_unique());
+    let (batch_ix, batch_accounts) = fixture.settle_bets_batch();
+    let (close_ix, close_accounts) = fixture.close_bet(Pubkey::new_unique());
+    let (streak_ix, streak_accounts) = fixture.claim_streak_reward(Pubkey::new_unique());
+    let (community_ix, community_accounts) = fixture.settle_community_entry(Pubkey::new_unique());
+    let (jackpot_ix, jackpot_accounts) = fixture.claim_jackpot(Pubkey::new_unique());
+
+    MolluskComputeUnitBencher::new(mollusk)
+        .bench(("lock_round", &lock_ix, &lock_accounts))
+        .bench(("settle_bet_win", &win_ix, &win_accounts))
+        .bench(("settle_bet_loss", &loss_ix, &loss_accounts))
+        .bench(("claim_bet", &claim_ix, &claim_accounts))
+        .bench(("settle_bets_batch_4", &batch_ix, &batch_accounts))
+        .bench(("close_bet", &close_ix, &close_accounts))
+        .bench(("claim_streak_reward", &streak_ix, &streak_accounts))
+        .bench(("settle_community_entry", &community_ix, &community_accounts))
+        .bench(("claim_jackpot", &jackpot_ix, &jackpot_accounts))
+        .must_pass(true)
+        .out_dir("../../target/benches")
+        .execute();
+}
@@ -1,8 +1,11 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::secp256k1_recover::secp256k1_recover;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, TransferChecked};
+use anchor_spl::token::{
+    self, Approve, Burn, Mint, MintTo, Revoke, Token, TokenAccount, TransferChecked,
+};
 use ephemeral_rollups_sdk::anchor::{commit, delegate, ephemeral};
+use ephemeral_rollups_sdk::consts::DELEGATION_PROGRAM_ID;
 use ephemeral_rollups_sdk::cpi::DelegateConfig;
 use ephemeral_rollups_sdk::ephem::{commit_accounts, commit_and_undelegate_accounts};
 use ephemeral_vrf_sdk::anchor::vrf;
@@ -12,6 +15,7 @@ use sha2::{Digest, Sha256};
 
 declare_id!("5xmSvdzDsFY4bx5nyFiMpmq881Epcm7v3Dxsxw54gGcX");
 
+const CONFIG_SEED: &[u8] = b"config";
 const MARKET_SEED: &[u8] = b"market";
 const ROUND_SEED: &[u8] = b"round";
 const VAULT_SEED: &[u8] = b"vault";
@@ -21,9 +25,59 @@ const COMMUNITY_SEED: &[u8] = b"community";
 const PATTERN_SEED: &[u8] = b"pattern";
 const JACKPOT_POT_SEED: &[u8] = b"jackpot_pot";
 const PERMISSION_GROUP_SEED: &[u8] = b"permission_group";
+const LEDGER_SEED: &[u8] = b"ledger";
+const PLAYER_BALANCE_SEED: &[u8] = b"player_balance";
+const BALANCE_VAULT_SEED: &[u8] = b"balance_vault";
+const SESSION_SEED: &[u8] = b"session";
+const TREASURY_SEED: &[u8] = b"treasury";
+const PARAMS_SEED: &[u8] = b"params";
+const POOL_SEED: &[u8] = b"pool";
+const LP_MINT_SEED: &[u8] = b"lp_mint";
+const LP_WITHDRAWAL_SEED: &[u8] = b"lp_withdrawal";
+const PARLAY_SEED: &[u8] = b"parlay";
+const MAX_LP_COOLDOWN: i64 = 7 * 24 * 60 * 60;
+const MAX_SESSION_DURATION: i64 = 7 * 24 * 60 * 60;
 
 const MIN_LOCK_DURATION: i64 = 5;
 const MAX_PREDICTING_DURATION: i64 = 300;
+const PARAM_CHANGE_DELAY: i64 = 24 * 60 * 60;
+const PAUSE_BETTING: u8 = 1 << 0;
+const PAUSE_REVEAL: u8 = 1 << 1;
+const PAUSE_PAYOUT: u8 = 1 << 2;
+const PAUSE_DELEGATION: u8 = 1 << 3;
+const PAUSE_ALL: u8 = PAUSE_BETTING | PAUSE_REVEAL | PAUSE_PAYOUT | PAUSE_DELEGATION;
+const MAX_HOUSE_EDGE_BPS: u16 = 7_500;
+/// Highest share of the stake `cash_out` may keep.
+const MAX_CASH_OUT_FEE_BPS: u16 = 5_000;
+const DEFAULT_CASH_OUT_FEE_BPS: u16 = 500;
+const MAX_PREDICTING_DURATION_CAP: i64 = 3_600;
+const MAX_MIN_LOCK_DURATION: i64 = 120;
+const VRF_RETRY_TIMEOUT: i64 = 30;
+/// Odds are stored in basis points of the stake: `ODDS_ONE` is 1x.
+const ODDS_ONE: u32 = 10_000;
+/// Allocated sizes of the accounts deployed before the version byte, when
+/// odds were `u16` percent. Each `migrate_*` instruction accepts exactly this
+/// size as the v0 layout.
+const LEGACY_MARKET_SPACE: usize = 8 + 32 + 4 + 64 + 1 + 8 + 2 + 32 + 16 + 2;
+const LEGACY_ROUND_SPACE: usize = 8 + 32 + 8 + 1 + 32 + 256 + 4 + 8 + 8 + 8 + 33 + 8;
+const LEGACY_BET_SPACE: usize = 8 + 32 + 32 + 8 + 8 + 2 + 1 + 1 + 8 + 8;
+const LEGACY_STREAK_SPACE: usize = 8 + 32 + 32 + 2 + 2 + 1 + 32;
+const LEGACY_COMMUNITY_ENTRY_SPACE: usize = 8 + 32 + 32 + 1 + 2 + 1;
+const LEGACY_JACKPOT_POT_SPACE: usize = 8 + 32 + 8 + 33 + 8;
+const LEGACY_PATTERN_CONFIG_SPACE: usize = 8 + 32 + 1 + 16 + 1;
+const LEGACY_PERMISSION_GROUP_SPACE: usize = 8 + 32 + 4 + (32 * 50) + 1;
+/// `Round.open_bets` of a round migrated from before bets were counted. Such
+/// rounds can never be closed.
+const ROUND_BETS_UNTRACKED: u32 = u32::MAX;
+const MAX_MARKET_NAME_LEN: usize = 64;
+const MAX_VIEWERS: usize = 50;
+/// Distinct selections a pari-mutuel or dynamic-odds round can track.
+const MAX_POOL_BUCKETS: usize = 32;
+const MIN_PARLAY_LEGS: usize = 2;
+const MAX_PARLAY_LEGS: usize = 8;
+/// Widest band dynamic odds may move away from the fixed-odds quote.
+const MAX_DYNAMIC_ODDS_BPS: u32 = 2 * ODDS_ONE;
+const STREAK_BASE_STAKE: u64 = 100_000_000;
 
 const TEE_PUBKEY: [u8; 65] = [
     0x04, 0x5d, 0x46, 0xd0, 0x70, 0x9c, 0x22, 0xee, 0x95, 0x23, 0x9e, 0x90, 0x3e, 0xc5, 0xfe, 0x49,
@@ -38,6 +92,151 @@ const TEE_PUBKEY: [u8; 65] = [
 pub mod tossr_engine {
     use super::*;
 
+    /// Create the program-wide role config. Only the program's upgrade
+    /// authority can do this; every role starts out as `owner`.
+    pub fn init_config(ctx: Context<InitConfig>, owner: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.version = Config::VERSION;
+        config.owner = owner;
+        config.pending_owner = Pubkey::default();
+        config.operator = owner;
+        config.oracle = owner;
+        config.treasurer = owner;
+        config.guardian = owner;
+        config.pause_flags = 0;
+        Ok(())
+    }
+
+    /// Replace the program-wide pause flags (`PAUSE_*` bits). The guardian
+    /// or the owner can pause and unpause.
+    pub fn set_pause_flags(ctx: Context<SetPauseFlags>, flags: u8) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == config.guardian || authority == config.owner,
+            ErrorCode::Unauthorized
+        );
+        require!(flags & !PAUSE_ALL == 0, ErrorCode::InvalidPauseFlags);
+        config.pause_flags = flags;
+        emit!(PauseFlagsUpdated {
+            authority,
+            flags,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn set_role(ctx: Context<UpdateConfig>, role: Role, key: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require_keys_eq!(config.owner, ctx.accounts.owner.key(), ErrorCode::Unauthorized);
+        match role {
+            Role::Owner => return Err(ErrorCode::InvalidRole.into()),
+            Role::Operator => config.operator = key,
+            Role::Oracle => config.oracle = key,
+            Role::Treasurer => config.treasurer = key,
+            Role::Guardian => config.guardian = key,
+        }
+        Ok(())
+    }
+
+    /// First step of an ownership transfer; `accept_ownership` completes it.
+    /// Proposing `Pubkey::default()` cancels a pending transfer.
+    pub fn propose_owner(ctx: Context<UpdateConfig>, new_owner: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require_keys_eq!(config.owner, ctx.accounts.owner.key(), ErrorCode::Unauthorized);
+        config.pending_owner = new_owner;
+        Ok(())
+    }
+
+    pub fn accept_ownership(ctx: Context<AcceptOwnership>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            config.pending_owner != Pubkey::default(),
+            ErrorCode::NoPendingOwner
+        );
+        require_keys_eq!(
+            config.pending_owner,
+            ctx.accounts.new_owner.key(),
+            ErrorCode::Unauthorized
+        );
+        config.owner = config.pending_owner;
+        config.pending_owner = Pubkey::default();
+        Ok(())
+    }
+
+    /// Hand a market created under `[MARKET_SEED, admin, index]` over to the
+    /// config roles. The market keeps its address, rounds and vault; both the
+    /// legacy admin and the config owner must sign.
+    pub fn migrate_market(ctx: Context<MigrateMarket>) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.config.owner,
+            ctx.accounts.owner.key(),
+            ErrorCode::Unauthorized
+        );
+        let config_key = ctx.accounts.config.key();
+        let market = &mut ctx.accounts.market;
+        require_keys_eq!(
+            market.admin,
+            ctx.accounts.legacy_admin.key(),
+            ErrorCode::Unauthorized
+        );
+        market.admin = config_key;
+        Ok(())
+    }
+
+    /// Rewrite a percent-odds Bet into the current layout. Permissionless;
+    /// the payer covers any extra rent.
+    pub fn migrate_bet(ctx: Context<MigrateAccount>) -> Result<()> {
+        let info = ctx.accounts.account.to_account_info();
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::InvalidBetAccount);
+
+        let migrated = {
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == *Bet::DISCRIMINATOR,
+                ErrorCode::InvalidBetAccount
+            );
+            require!(data.len() != 8 + Bet::INIT_SPACE, ErrorCode::AlreadyMigrated);
+            require!(data.len() == LEGACY_BET_SPACE, ErrorCode::InvalidBetAccount);
+            LegacyBet::deserialize(&mut &data[8..])?.upgrade(&info.key())
+        };
+        write_migrated(
+            &info,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            &migrated,
+        )
+    }
+
+    /// Layout migration; `migrate_market` moves a market's authority.
+    pub fn migrate_market_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_layout::<Market>(ctx.accounts, &[LEGACY_MARKET_SPACE])
+    }
+
+    pub fn migrate_round(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_layout::<Round>(ctx.accounts, &[LEGACY_ROUND_SPACE])
+    }
+
+    pub fn migrate_streak(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_layout::<Streak>(ctx.accounts, &[LEGACY_STREAK_SPACE])
+    }
+
+    pub fn migrate_community_entry(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_layout::<CommunityEntry>(ctx.accounts, &[LEGACY_COMMUNITY_ENTRY_SPACE])
+    }
+
+    pub fn migrate_jackpot_pot(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_layout::<JackpotPot>(ctx.accounts, &[LEGACY_JACKPOT_POT_SPACE])
+    }
+
+    pub fn migrate_pattern_config(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_layout::<PatternConfig>(ctx.accounts, &[LEGACY_PATTERN_CONFIG_SPACE])
+    }
+
+    pub fn migrate_permission_group(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_layout::<PermissionGroup>(ctx.accounts, &[LEGACY_PERMISSION_GROUP_SPACE])
+    }
+
     pub fn initialize_market(
         ctx: Context<InitializeMarket>,
         market_index: u16,
@@ -45,8 +244,15 @@ pub mod tossr_engine {
         house_edge_bps: u16,
         market_type: MarketType,
     ) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.config.owner,
+            ctx.accounts.admin.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(name.len() <= MAX_MARKET_NAME_LEN, ErrorCode::NameTooLong);
         let market = &mut ctx.accounts.market;
-        market.admin = ctx.accounts.admin.key();
+        market.version = Market::VERSION;
+        market.admin = ctx.accounts.config.key();
         market.name = name;
         market.is_active = true;
         market.last_round = 0;
@@ -54,17 +260,61 @@ pub mod tossr_engine {
         market.mint = ctx.accounts.mint.key();
         market.market_type = market_type;
         market.index = market_index;
+        market.vault_bump = Pubkey::find_program_address(
+            &[VAULT_SEED, market.key().as_ref()],
+            ctx.program_id,
+        )
+        .1;
+        market.balance_vault_bump = Pubkey::find_program_address(
+            &[BALANCE_VAULT_SEED, market.mint.as_ref()],
+            ctx.program_id,
+        )
+        .1;
+
+        let values = ParamValues::with_house_edge(house_edge_bps);
+        values.validate()?;
+        init_params(&mut ctx.accounts.params, ctx.accounts.market.key(), values)?;
         Ok(())
     }
 
     /// Request VRF randomness inside ER; callback will set outcome.
-    pub fn request_randomness(ctx: Context<VrfRequestCtx>, client_seed: u8) -> Result<()> {
+    /// The caller seed is the round's accumulated player entropy.
+    /// One request per round; a retry is only allowed once the pending
+    /// request has gone unanswered for `VRF_RETRY_TIMEOUT` seconds.
+    pub fn request_randomness(ctx: Context<VrfRequestCtx>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.payer.key(),
+        )?;
+
+        let clock = Clock::get()?;
+        let caller_seed = {
+            let round = &mut ctx.accounts.round;
+            require!(
+                round.status == RoundStatus::Locked as u8,
+                ErrorCode::InvalidState
+            );
+            require!(!round.vrf_fulfilled, ErrorCode::VrfAlreadyFulfilled);
+            if round.vrf_requested_at > 0 {
+                require!(
+                    clock.unix_timestamp >= round.vrf_requested_at + VRF_RETRY_TIMEOUT,
+                    ErrorCode::VrfRequestPending
+                );
+            }
+            round.vrf_seed = round.player_entropy;
+            round.vrf_requested_at = clock.unix_timestamp;
+            round.vrf_seed
+        };
+
         let ix = create_request_randomness_ix(RequestRandomnessParams {
             payer: ctx.accounts.payer.key(),
             oracle_queue: ctx.accounts.oracle_queue.key(),
         callback_program_id: ID,
         callback_discriminator: instruction::VrfCallback::DISCRIMINATOR.to_vec(),
-        caller_seed: [client_seed; 32],
+        caller_seed,
         accounts_metas: Some(vec![
             SerializableAccountMeta {
                 pubkey: ctx.accounts.market.key(),
@@ -91,43 +341,148 @@ pub mod tossr_engine {
             round.status == RoundStatus::Locked as u8,
             ErrorCode::InvalidState
         );
+        require!(round.vrf_requested_at > 0, ErrorCode::VrfNotRequested);
+        require!(!round.vrf_fulfilled, ErrorCode::VrfAlreadyFulfilled);
         round.inputs_hash = randomness;
+        round.vrf_fulfilled = true;
         Ok(())
     }
 
+    /// The owner can toggle a market either way; the guardian can only
+    /// deactivate one.
     pub fn toggle_market(ctx: Context<ToggleMarket>, is_active: bool) -> Result<()> {
-        require_keys_eq!(
-            ctx.accounts.market.admin,
-            ctx.accounts.admin.key(),
-            ErrorCode::Unauthorized
-        );
+        let role = if !is_active && ctx.accounts.config.guardian == ctx.accounts.admin.key() {
+            Role::Guardian
+        } else {
+            Role::Owner
+        };
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            role,
+            &ctx.accounts.admin.key(),
+        )?;
         ctx.accounts.market.is_active = is_active;
         Ok(())
     }
 
-    pub fn set_house_edge_bps(ctx: Context<SetHouseEdge>, house_edge_bps: u16) -> Result<()> {
-        require_keys_eq!(
-            ctx.accounts.market.admin,
-            ctx.accounts.admin.key(),
-            ErrorCode::Unauthorized
-        );
-        ctx.accounts.market.house_edge_bps = house_edge_bps;
+    /// Switch the market between fixed odds and pari-mutuel pools. Rounds
+    /// keep the mode they opened with, so this applies from the next round.
+    pub fn set_betting_mode(ctx: Context<SetBettingMode>, mode: BettingMode) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Owner,
+            &ctx.accounts.admin.key(),
+        )?;
+        let market = &mut ctx.accounts.market;
+        if mode == BettingMode::PariMutuel {
+            // Streaks take no bets and jackpot wins are paid from their own pot.
+            require!(
+                !matches!(
+                    market.market_type,
+                    MarketType::StreakMeter | MarketType::Jackpot
+                ),
+                ErrorCode::BettingModeNotSupported
+            );
+        }
+        if mode == BettingMode::DynamicOdds {
+            ctx.accounts.params.current.dynamic_odds.validate()?;
+        }
+        market.betting_mode = mode as u8;
+        Ok(())
+    }
+
+    /// Create the parameter record for a market that predates it, seeded
+    /// from the market's current house edge and the default limits.
+    pub fn init_market_params(ctx: Context<InitMarketParams>) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Owner,
+            &ctx.accounts.admin.key(),
+        )?;
+        let edge = ctx.accounts.market.house_edge_bps.min(MAX_HOUSE_EDGE_BPS);
+        init_params(
+            &mut ctx.accounts.params,
+            ctx.accounts.market.key(),
+            ParamValues::with_house_edge(edge),
+        )
+    }
+
+    /// Queue a parameter change. Fields left as `None` keep their current
+    /// value; the change can be executed after `PARAM_CHANGE_DELAY` and
+    /// applies from the next opened round.
+    pub fn propose_market_params(
+        ctx: Context<UpdateMarketParams>,
+        change: ParamChange,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Owner,
+            &ctx.accounts.admin.key(),
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        let params = &mut ctx.accounts.params;
+        let pending = change.apply_to(&params.current);
+        pending.validate()?;
+
+        params.pending = pending;
+        params.has_pending = true;
+        params.pending_eta = now.saturating_add(PARAM_CHANGE_DELAY);
+        params.proposed_at = now;
+        Ok(())
+    }
+
+    pub fn cancel_market_params(ctx: Context<UpdateMarketParams>) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Owner,
+            &ctx.accounts.admin.key(),
+        )?;
+        let params = &mut ctx.accounts.params;
+        require!(params.has_pending, ErrorCode::NoPendingParams);
+        params.has_pending = false;
+        params.pending_eta = 0;
+        Ok(())
+    }
+
+    /// Apply a matured parameter change. Permissionless once the delay has
+    /// passed; rounds that are already open keep their snapshot.
+    pub fn execute_market_params(ctx: Context<ExecuteMarketParams>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let params = &mut ctx.accounts.params;
+        require!(params.has_pending, ErrorCode::NoPendingParams);
+        require!(now >= params.pending_eta, ErrorCode::TimelockNotElapsed);
+
+        params.current = params.pending;
+        params.has_pending = false;
+        params.pending_eta = 0;
+        params.revision = params.revision.saturating_add(1);
+        params.updated_at = now;
+
+        ctx.accounts.market.house_edge_bps = params.current.house_edge_bps;
         Ok(())
     }
 
     pub fn open_round(ctx: Context<OpenRound>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_BETTING)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
         let market = &mut ctx.accounts.market;
         require!(market.is_active, ErrorCode::MarketInactive);
-        require_keys_eq!(
-            market.admin,
-            ctx.accounts.admin.key(),
-            ErrorCode::Unauthorized
-        );
 
         let clock = Clock::get()?;
         let round = &mut ctx.accounts.round;
 
         market.last_round = market.last_round.saturating_add(1);
+        round.version = Round::VERSION;
         round.market = market.key();
         round.number = market.last_round;
         round.status = RoundStatus::Predicting as u8;
@@ -139,15 +494,38 @@ pub mod tossr_engine {
         round.locked_at = 0;
         round.commitment_hash = None;
         round.revealed_at = 0;
+        round.player_entropy = initial_player_entropy(&round.market, round.number);
+        round.entropy_contributions = 0;
+        round.vrf_seed = [0u8; 32];
+        round.vrf_requested_at = 0;
+        round.vrf_fulfilled = false;
+        round.delegation_status = DelegationStatus::Undelegated as u8;
+        round.settlement_mode = SettlementMode::Base as u8;
+        round.balance_staked = 0;
+        round.balance_credited = 0;
+        round.balances_synced = false;
+        round.open_liability = 0;
+        round.params = ctx.accounts.params.current;
+        round.params_revision = ctx.accounts.params.revision;
+        if market.betting_mode == BettingMode::DynamicOdds as u8 {
+            round.params.dynamic_odds.validate()?;
+        }
+        round.open_bets = 0;
+        round.rent_payer = ctx.accounts.admin.key();
+        round.bump = ctx.bumps.round;
+        round.betting_mode = market.betting_mode;
+        round.pools = Vec::new();
+        round.exposures = Vec::new();
         Ok(())
     }
 
     pub fn schedule_lock(ctx: Context<ScheduleLock>, lock_at: i64) -> Result<()> {
-        require_keys_eq!(
-            ctx.accounts.market.admin,
-            ctx.accounts.admin.key(),
-            ErrorCode::Unauthorized
-        );
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Predicting as u8,
@@ -157,7 +535,7 @@ pub mod tossr_engine {
         let clock = Clock::get()?;
         require!(lock_at > clock.unix_timestamp, ErrorCode::InvalidLockTime);
         require!(
-            lock_at <= round.opened_at + MAX_PREDICTING_DURATION,
+            lock_at <= round.opened_at + round.params.max_predicting_duration,
             ErrorCode::LockTimeTooLate
         );
 
@@ -165,17 +543,47 @@ pub mod tossr_engine {
         Ok(())
     }
 
+    /// Call off a round whose outcome was never revealed. Its bets settle as
+    /// refunds of their stake and parlay legs on it are voided.
+    pub fn cancel_round(ctx: Context<CancelRound>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        let round = &mut ctx.accounts.round;
+        check_cancellable(round, now)?;
+        round.status = RoundStatus::Cancelled as u8;
+        emit!(RoundCancelled {
+            round: round.key(),
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Lock betting on a round. The operator can lock at any time unless a
+    /// lock is scheduled; once `lock_scheduled_at` has passed anyone can,
+    /// and a non-operator caller collects the treasury's crank reward when
+    /// the reward accounts are supplied.
     pub fn lock_round(ctx: Context<LockRound>) -> Result<()> {
-        require_keys_eq!(
-            ctx.accounts.market.admin,
-            ctx.accounts.admin.key(),
-            ErrorCode::Unauthorized
-        );
+        let cranked = require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )
+        .is_err();
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Predicting as u8,
             ErrorCode::InvalidState
         );
+        if cranked {
+            require!(round.lock_scheduled_at > 0, ErrorCode::Unauthorized);
+        }
 
         let clock = Clock::get()?;
 
@@ -188,6 +596,30 @@ pub mod tossr_engine {
 
         round.status = RoundStatus::Locked as u8;
         round.locked_at = clock.unix_timestamp;
+
+        if cranked {
+            let reward = pay_crank_reward(&ctx)?;
+            emit!(RoundLockCranked {
+                round: ctx.accounts.round.key(),
+                cranker: ctx.accounts.admin.key(),
+                reward,
+                timestamp: clock.unix_timestamp,
+            });
+        }
+        Ok(())
+    }
+
+    /// Set the reward paid for permissionless round locks. Bounded by the
+    /// market's minimum stake so the crank never pays more than a bet.
+    pub fn set_crank_reward(ctx: Context<SetCrankReward>, amount: u64) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Owner,
+            &ctx.accounts.admin.key(),
+        )?;
+        check_crank_reward(amount, &ctx.accounts.params.current)?;
+        ctx.accounts.treasury.crank_reward = amount;
         Ok(())
     }
 
@@ -196,6 +628,13 @@ pub mod tossr_engine {
         commitment_hash: [u8; 32],
         attestation_sig: [u8; 64],
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -204,7 +643,7 @@ pub mod tossr_engine {
 
         let clock = Clock::get()?;
         require!(
-            clock.unix_timestamp >= round.locked_at + MIN_LOCK_DURATION,
+            clock.unix_timestamp >= round.locked_at + round.params.min_lock_duration,
             ErrorCode::MinLockDurationNotMet
         );
 
@@ -221,6 +660,13 @@ pub mod tossr_engine {
         inputs_hash: [u8; 32],
         attestation_sig: [u8; 64],
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -244,6 +690,13 @@ pub mod tossr_engine {
 
     /// ER-only: Reveal numeric outcome inside Ephemeral Rollup
     pub fn er_reveal_outcome_numeric(ctx: Context<RevealOutcome>, value: u16) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -264,6 +717,13 @@ pub mod tossr_engine {
         inputs_hash: [u8; 32],
         attestation_sig: [u8; 64],
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -290,6 +750,13 @@ pub mod tossr_engine {
         color: u8,
         size: u8,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -309,6 +776,13 @@ pub mod tossr_engine {
         inputs_hash: [u8; 32],
         attestation_sig: [u8; 64],
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -338,6 +812,13 @@ pub mod tossr_engine {
         pattern_id: u8,
         matched_value: u16,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -361,6 +842,13 @@ pub mod tossr_engine {
         inputs_hash: [u8; 32],
         attestation_sig: [u8; 64],
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -396,6 +884,13 @@ pub mod tossr_engine {
         chain_score: u16,
         sensor_score: u16,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -421,6 +916,13 @@ pub mod tossr_engine {
         inputs_hash: [u8; 32],
         attestation_sig: [u8; 64],
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -450,6 +952,13 @@ pub mod tossr_engine {
         final_byte: u8,
         seed_hash: [u8; 32],
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Oracle,
+            &ctx.accounts.payer.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -464,24 +973,21 @@ pub mod tossr_engine {
         Ok(())
     }
 
-    pub fn place_bet(ctx: Context<PlaceBet>, selection: Selection, stake: u64) -> Result<()> {
-        let round = &ctx.accounts.round;
-        require!(
-            round.status == RoundStatus::Predicting as u8,
-            ErrorCode::InvalidState
-        );
-
+    pub fn place_bet(
+        ctx: Context<PlaceBet>,
+        selection: Selection,
+        stake: u64,
+        player_seed: Option<[u8; 32]>,
+        min_odds_bps: u32,
+        max_stake: Option<u64>,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_BETTING)?;
         let clock = Clock::get()?;
-        if round.lock_scheduled_at > 0 {
-            require!(
-                clock.unix_timestamp < round.lock_scheduled_at,
-                ErrorCode::BettingClosed
-            );
-        }
+        check_betting_open(&ctx.accounts.round, clock.unix_timestamp)?;
+        check_stake(&ctx.accounts.round.params, stake)?;
 
-        require!(stake > 0, ErrorCode::InvalidStake);
-
-        let odds_bps = compute_odds_bps(&selection, &ctx.accounts.market)?;
+        let odds_bps = quote_round_odds(&selection, &ctx.accounts.market, &ctx.accounts.round)?;
+        check_slippage(odds_bps, min_odds_bps, stake, max_stake)?;
 
         require_keys_eq!(
             ctx.accounts.market.mint,
@@ -489,33 +995,201 @@ pub mod tossr_engine {
             ErrorCode::Unauthorized
         );
 
+        let user_key = ctx.accounts.user.key();
         let decimals = ctx.accounts.mint.decimals;
-        let cpi_accounts = TransferChecked {
-            from: ctx.accounts.user_token.to_account_info(),
-            to: ctx.accounts.vault_token.to_account_info(),
-            mint: ctx.accounts.mint.to_account_info(),
-            authority: ctx.accounts.payer.to_account_info(),
+
+        if ctx.accounts.payer.key() == user_key {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.user_token.to_account_info(),
+                to: ctx.accounts.vault_token.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+            token::transfer_checked(cpi_ctx, stake, decimals)?;
+        } else {
+            // A session signer bets on the owner's behalf; the SessionToken PDA
+            // is the SPL delegate on the owner's token account.
+            let session = ctx
+                .accounts
+                .session_token
+                .as_mut()
+                .ok_or(ErrorCode::Unauthorized)?;
+            authorize_session_spend(
+                session,
+                ctx.accounts.payer.key(),
+                stake,
+                clock.unix_timestamp,
+            )?;
+
+            let mint_key = ctx.accounts.mint.key();
+            let bump = session.bump;
+            let signer_slice: &[&[u8]] =
+                &[SESSION_SEED, user_key.as_ref(), mint_key.as_ref(), &[bump]];
+            let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
+
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.user_token.to_account_info(),
+                to: ctx.accounts.vault_token.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: session.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            token::transfer_checked(cpi_ctx, stake, decimals)?;
+        }
+
+        let round_key = ctx.accounts.round.key();
+        record_bet(
+            &mut ctx.accounts.bet,
+            &mut ctx.accounts.round,
+            round_key,
+            user_key,
+            ctx.accounts.payer.key(),
+            ctx.bumps.bet,
+            selection,
+            stake,
+            odds_bps,
+            clock.unix_timestamp,
+            false,
+            player_seed,
+        )?;
+        Ok(())
+    }
+
+    /// Authorize `session_signer` to place bets for the caller until
+    /// `expires_at`, spending at most `spend_cap` of `mint`.
+    pub fn create_session(
+        ctx: Context<CreateSession>,
+        session_signer: Pubkey,
+        spend_cap: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(spend_cap > 0, ErrorCode::InvalidStake);
+        require!(
+            expires_at > now && expires_at <= now.saturating_add(MAX_SESSION_DURATION),
+            ErrorCode::InvalidSession
+        );
+        require_keys_neq!(
+            session_signer,
+            ctx.accounts.owner.key(),
+            ErrorCode::InvalidSession
+        );
+
+        let session = &mut ctx.accounts.session_token;
+        session.version = SessionToken::VERSION;
+        session.owner = ctx.accounts.owner.key();
+        session.session_signer = session_signer;
+        session.mint = ctx.accounts.mint.key();
+        session.spend_cap = spend_cap;
+        session.spent = 0;
+        session.expires_at = expires_at;
+        session.created_at = now;
+        session.bump = ctx.bumps.session_token;
+
+        let cpi_accounts = Approve {
+            to: ctx.accounts.user_token.to_account_info(),
+            delegate: ctx.accounts.session_token.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
         };
         let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer_checked(cpi_ctx, stake, decimals)?;
+        token::approve(cpi_ctx, spend_cap)?;
+        Ok(())
+    }
 
-        let bet = &mut ctx.accounts.bet;
-        bet.user = ctx.accounts.payer.key();
-        bet.round = ctx.accounts.round.key();
-        bet.stake = stake;
-        bet.selection = selection;
-        bet.odds_bps = odds_bps;
-        bet.settled = false;
-        bet.won = false;
-        bet.payout = 0;
-        bet.placed_at = clock.unix_timestamp;
+    /// Revoke the caller's session for `mint`: drops the SPL delegation and
+    /// closes the SessionToken back to the owner.
+    pub fn revoke_session(ctx: Context<RevokeSession>) -> Result<()> {
+        let cpi_accounts = Revoke {
+            source: ctx.accounts.user_token.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::revoke(cpi_ctx)?;
+        Ok(())
+    }
+
+    /// Place a bet funded from the player's internal balance. No token CPI, so
+    /// this also runs inside the ER when the round and balance are delegated.
+    pub fn place_bet_from_balance(
+        ctx: Context<PlaceBetFromBalance>,
+        selection: Selection,
+        stake: u64,
+        player_seed: Option<[u8; 32]>,
+        min_odds_bps: u32,
+        max_stake: Option<u64>,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_BETTING)?;
+        let clock = Clock::get()?;
+        check_betting_open(&ctx.accounts.round, clock.unix_timestamp)?;
+        check_stake(&ctx.accounts.round.params, stake)?;
 
+        let odds_bps = quote_round_odds(&selection, &ctx.accounts.market, &ctx.accounts.round)?;
+        check_slippage(odds_bps, min_odds_bps, stake, max_stake)?;
+
+        let player_balance = &mut ctx.accounts.player_balance;
+        require!(
+            player_balance.balance >= stake,
+            ErrorCode::InsufficientBalance
+        );
+        player_balance.balance -= stake;
+
+        let round_key = ctx.accounts.round.key();
+        record_bet(
+            &mut ctx.accounts.bet,
+            &mut ctx.accounts.round,
+            round_key,
+            ctx.accounts.payer.key(),
+            ctx.accounts.payer.key(),
+            ctx.bumps.bet,
+            selection,
+            stake,
+            odds_bps,
+            clock.unix_timestamp,
+            true,
+            player_seed,
+        )?;
         let round_mut = &mut ctx.accounts.round;
-        round_mut.unsettled_bets = round_mut.unsettled_bets.saturating_add(1);
+        round_mut.balance_staked = round_mut
+            .balance_staked
+            .checked_add(stake)
+            .ok_or(ErrorCode::Overflow)?;
         Ok(())
     }
 
+    /// Delegate the round to the ER. Only allowed while the round is still
+    /// accepting bets or waiting on its outcome.
     pub fn delegate_round(ctx: Context<DelegateRound>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_DELEGATION)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.payer.key(),
+        )?;
+        require_keys_eq!(
+            ctx.accounts.pda.key(),
+            ctx.accounts.round.key(),
+            ErrorCode::InvalidDelegatedAccount
+        );
+
+        let round = &mut ctx.accounts.round;
+        require!(
+            round.status == RoundStatus::Predicting as u8 || round.status == RoundStatus::Locked as u8,
+            ErrorCode::InvalidState
+        );
+        require!(
+            round.delegation_status == DelegationStatus::Undelegated as u8,
+            ErrorCode::AlreadyDelegated
+        );
+        // Persist the status before the delegation program takes ownership.
+        round.delegation_status = DelegationStatus::Delegated as u8;
+        round.exit(&crate::ID)?;
+
         let validator = ctx.remaining_accounts.first().map(|acc| acc.key());
 
         ctx.accounts.delegate_pda(
@@ -534,6 +1208,17 @@ pub mod tossr_engine {
     }
 
     pub fn commit_round(ctx: Context<RoundCommitCtx>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_DELEGATION)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.payer.key(),
+        )?;
+        require!(
+            ctx.accounts.round.delegation_status == DelegationStatus::Delegated as u8,
+            ErrorCode::NotDelegated
+        );
         commit_accounts(
             &ctx.accounts.payer,
             vec![&ctx.accounts.round.to_account_info()],
@@ -544,6 +1229,22 @@ pub mod tossr_engine {
     }
 
     pub fn commit_and_undelegate_round(ctx: Context<RoundCommitCtx>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_DELEGATION)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.payer.key(),
+        )?;
+        let round = &mut ctx.accounts.round;
+        require!(
+            round.delegation_status == DelegationStatus::Delegated as u8,
+            ErrorCode::NotDelegated
+        );
+        require!(can_undelegate_round(round), ErrorCode::RoundWorkOutstanding);
+        round.delegation_status = DelegationStatus::Undelegated as u8;
+        round.exit(&crate::ID)?;
+
         commit_and_undelegate_accounts(
             &ctx.accounts.payer,
             vec![&ctx.accounts.round.to_account_info()],
@@ -553,9 +1254,16 @@ pub mod tossr_engine {
         Ok(())
     }
 
+    /// Undelegation callback invoked by the delegation program, which signs
+    /// with the delegation buffer.
     pub fn undelegate(ctx: Context<UndelegateRound>, pda_seeds: Vec<Vec<u8>>) -> Result<()> {
         use ephemeral_rollups_sdk::cpi::undelegate_account;
 
+        require!(
+            ctx.accounts.delegation_buffer.is_signer,
+            ErrorCode::Unauthorized
+        );
+
         undelegate_account(
             &ctx.accounts.pda,
             ctx.program_id,
@@ -567,12 +1275,16 @@ pub mod tossr_engine {
         Ok(())
     }
 
-    pub fn settle_round(ctx: Context<SettleRound>) -> Result<()> {
-        require_keys_eq!(
-            ctx.accounts.market.admin,
-            ctx.accounts.admin.key(),
-            ErrorCode::Unauthorized
-        );
+    /// Settle the round. A delegated round is committed and undelegated in the
+    /// same instruction; pass `[magic_context, magic_program]` as remaining accounts.
+    pub fn settle_round<'info>(ctx: Context<'_, '_, '_, 'info, SettleRound<'info>>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
         let round = &mut ctx.accounts.round;
         require!(
             round.status == RoundStatus::Locked as u8,
@@ -581,231 +1293,650 @@ pub mod tossr_engine {
         require!(round.revealed_at > 0, ErrorCode::OutcomeNotRevealed);
         require!(round.unsettled_bets == 0, ErrorCode::UnsettledBetsRemain);
         round.status = RoundStatus::Settled as u8;
+        // Every bet is paid; what is left of a pari-mutuel pool is house money.
+        round.open_liability = 0;
+
+        if round.delegation_status == DelegationStatus::Delegated as u8 {
+            let [magic_context, magic_program] = ctx.remaining_accounts else {
+                return Err(ErrorCode::MissingMagicAccounts.into());
+            };
+            require_keys_eq!(
+                magic_context.key(),
+                ephemeral_rollups_sdk::consts::MAGIC_CONTEXT_ID,
+                ErrorCode::MissingMagicAccounts
+            );
+            require_keys_eq!(
+                magic_program.key(),
+                ephemeral_rollups_sdk::consts::MAGIC_PROGRAM_ID,
+                ErrorCode::MissingMagicAccounts
+            );
+
+            round.delegation_status = DelegationStatus::Undelegated as u8;
+            round.exit(&crate::ID)?;
+            commit_and_undelegate_accounts(
+                &ctx.accounts.admin,
+                vec![&ctx.accounts.round.to_account_info()],
+                magic_context,
+                magic_program,
+            )?;
+        }
         Ok(())
     }
 
     pub fn settle_bet(ctx: Context<SettleBet>) -> Result<()> {
-        let round = &ctx.accounts.round;
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
         require_keys_eq!(
-            ctx.accounts.market.admin,
-            ctx.accounts.admin.key(),
+            ctx.accounts.market.mint,
+            ctx.accounts.mint.key(),
             ErrorCode::Unauthorized
         );
+        let market_key = ctx.accounts.market.key();
+        settle_bet_from_vault(
+            &mut ctx.accounts.round,
+            &mut ctx.accounts.bet,
+            &market_key,
+            &ctx.accounts.vault_authority,
+            &ctx.accounts.vault_token,
+            &ctx.accounts.user_token,
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+            ctx.accounts.market.vault_bump,
+        )?;
+        Ok(())
+    }
+
+    /// Operator-only: settle many vault-funded bets of one base-layer round.
+    /// Pass `(bet, user_token)` pairs as remaining accounts; `user_token` must
+    /// be the bettor's existing token account for the market mint. Bets that
+    /// were already settled (e.g. claimed) are skipped.
+    pub fn settle_bets_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleBetsBatch<'info>>,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
         require_keys_eq!(
             ctx.accounts.market.mint,
             ctx.accounts.mint.key(),
             ErrorCode::Unauthorized
         );
+        require_base_settlement(&ctx.accounts.round)?;
         require!(
-            round.status == RoundStatus::Locked as u8,
-            ErrorCode::InvalidState
+            !ctx.remaining_accounts.is_empty() && ctx.remaining_accounts.len().is_multiple_of(2),
+            ErrorCode::InvalidBatch
         );
-        require!(round.revealed_at > 0, ErrorCode::OutcomeNotRevealed);
-
-        let bet = &mut ctx.accounts.bet;
-        require!(!bet.settled, ErrorCode::AlreadySettled);
 
-        let won = evaluate_winner(&bet.selection, &round.outcome)?;
+        let market_key = ctx.accounts.market.key();
+        let round_key = ctx.accounts.round.key();
+        let vault_bump = ctx.accounts.market.vault_bump;
+        let mut settled: u32 = 0;
+        let mut total_payout: u64 = 0;
+        let mut released: u64 = 0;
+
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let mut bet: Account<'info, Bet> = Account::try_from(&pair[0])?;
+            let expected = Pubkey::create_program_address(
+                &[BET_SEED, round_key.as_ref(), bet.user.as_ref(), &[bet.bump]],
+                ctx.program_id,
+            )
+            .map_err(|_| ErrorCode::InvalidBetAccount)?;
+            require_keys_eq!(expected, bet.key(), ErrorCode::InvalidBetAccount);
+            if bet.settled {
+                continue;
+            }
 
-        let mut payout_amount: u64 = 0;
-        if won {
-            payout_amount = bet
-                .stake
-                .checked_mul(bet.odds_bps as u64)
-                .ok_or(ErrorCode::Overflow)?
-                / 100u64;
+            let payout = resolve_vault_bet(&mut bet, &ctx.accounts.round)?;
+            if payout > 0 {
+                let user_token: Account<'info, TokenAccount> = Account::try_from(&pair[1])?;
+                require_keys_eq!(user_token.owner, bet.user, ErrorCode::Unauthorized);
+                require_keys_eq!(
+                    user_token.mint,
+                    ctx.accounts.market.mint,
+                    ErrorCode::Unauthorized
+                );
+                pay_from_vault(
+                    &market_key,
+                    vault_bump,
+                    &ctx.accounts.vault_authority,
+                    &ctx.accounts.vault_token,
+                    &user_token,
+                    &ctx.accounts.mint,
+                    &ctx.accounts.token_program,
+                    payout,
+                )?;
+            }
+            released = released
+                .checked_add(settled_liability(&ctx.accounts.round, &bet)?)
+                .ok_or(ErrorCode::Overflow)?;
+            total_payout = total_payout
+                .checked_add(payout)
+                .ok_or(ErrorCode::Overflow)?;
+            settled += 1;
+            // Persist now so a duplicate later in the batch sees it settled.
+            bet.exit(ctx.program_id)?;
         }
 
-        if payout_amount > 0 {
-            let decimals = ctx.accounts.mint.decimals;
-            let market_key = ctx.accounts.market.key();
-            let seeds = &[VAULT_SEED, market_key.as_ref()];
-            let (_vault_pda, bump) = Pubkey::find_program_address(seeds, ctx.program_id);
-            let signer_slice: &[&[u8]] = &[VAULT_SEED, market_key.as_ref(), &[bump]];
-            let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
+        let round = &mut ctx.accounts.round;
+        round.unsettled_bets = round.unsettled_bets.saturating_sub(settled);
+        round.open_liability = round.open_liability.saturating_sub(released);
+
+        emit!(BetsBatchSettled {
+            round: round_key,
+            settled,
+            total_payout,
+        });
+        Ok(())
+    }
 
-            let cpi_accounts = TransferChecked {
-                from: ctx.accounts.vault_token.to_account_info(),
-                to: ctx.accounts.user_token.to_account_info(),
-                mint: ctx.accounts.mint.to_account_info(),
-                authority: ctx.accounts.vault_authority.to_account_info(),
-            };
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                cpi_accounts,
-                signer_seeds,
-            );
-            token::transfer_checked(cpi_ctx, payout_amount, decimals)?;
-        }
+    /// Settle a vault-funded base-layer bet once its round's outcome is
+    /// revealed. Permissionless: winnings always go to the bettor's token
+    /// account, whoever sends it. `settle_bet` remains for operator batches.
+    pub fn claim_bet(ctx: Context<ClaimBet>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_keys_eq!(
+            ctx.accounts.market.mint,
+            ctx.accounts.mint.key(),
+            ErrorCode::Unauthorized
+        );
+        let market_key = ctx.accounts.market.key();
+        let payout = settle_bet_from_vault(
+            &mut ctx.accounts.round,
+            &mut ctx.accounts.bet,
+            &market_key,
+            &ctx.accounts.vault_authority,
+            &ctx.accounts.vault_token,
+            &ctx.accounts.user_token,
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+            ctx.accounts.market.vault_bump,
+        )?;
+        emit!(BetClaimed {
+            bet: ctx.accounts.bet.key(),
+            user: ctx.accounts.bet.user,
+            claimer: ctx.accounts.claimer.key(),
+            won: ctx.accounts.bet.won,
+            payout,
+        });
+        Ok(())
+    }
 
-        bet.settled = true;
-        bet.won = won;
-        bet.payout = payout_amount;
+    pub fn init_market_ledger(ctx: Context<InitMarketLedger>) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Owner,
+            &ctx.accounts.admin.key(),
+        )?;
+        let ledger = &mut ctx.accounts.ledger;
+        ledger.version = MarketLedger::VERSION;
+        ledger.market = ctx.accounts.market.key();
+        ledger.total_credited = 0;
+        ledger.settled_bets = 0;
+        ledger.delegation_status = DelegationStatus::Undelegated as u8;
+        Ok(())
+    }
 
-        let round_mut = &mut ctx.accounts.round;
-        round_mut.unsettled_bets = round_mut.unsettled_bets.saturating_sub(1);
+    /// Switch a round to ER settlement: its bets are settled inside the rollup
+    /// and their winnings reach the bettors' balances via `sync_round_balances`
+    /// and `collect_payout` on base.
+    pub fn enable_er_settlement(ctx: Context<EnableErSettlement>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_DELEGATION)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+        let round = &mut ctx.accounts.round;
+        require!(
+            round.status == RoundStatus::Predicting as u8 || round.status == RoundStatus::Locked as u8,
+            ErrorCode::InvalidState
+        );
+        require!(
+            round.delegation_status == DelegationStatus::Undelegated as u8,
+            ErrorCode::AlreadyDelegated
+        );
+        round.settlement_mode = SettlementMode::Rollup as u8;
         Ok(())
     }
 
-    pub fn init_vault(_ctx: Context<InitVault>) -> Result<()> {
+    /// Delegate a bet to the ER alongside its round. Bets are immutable on base
+    /// once the round is locked, so delegation waits for the lock.
+    pub fn delegate_bet(ctx: Context<DelegateBet>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_DELEGATION)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.payer.key(),
+        )?;
+        require_keys_eq!(
+            ctx.accounts.pda.key(),
+            ctx.accounts.bet.key(),
+            ErrorCode::InvalidDelegatedAccount
+        );
+        let round = &ctx.accounts.round;
+        require!(
+            round.status == RoundStatus::Locked as u8,
+            ErrorCode::InvalidState
+        );
+        require!(
+            round.settlement_mode == SettlementMode::Rollup as u8,
+            ErrorCode::WrongSettlementMode
+        );
+        require!(!ctx.accounts.bet.settled, ErrorCode::AlreadySettled);
+
+        let validator = ctx.remaining_accounts.first().map(|acc| acc.key());
+        ctx.accounts.delegate_pda(
+            &ctx.accounts.payer,
+            &[
+                BET_SEED,
+                ctx.accounts.round.key().as_ref(),
+                ctx.accounts.bet.user.as_ref(),
+            ],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
         Ok(())
     }
 
-    pub fn init_streak(ctx: Context<InitStreak>, target: u16) -> Result<()> {
-        require!(target >= 2 && target <= 10, ErrorCode::InvalidStreakTarget);
+    pub fn delegate_market_ledger(ctx: Context<DelegateMarketLedger>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_DELEGATION)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.payer.key(),
+        )?;
+        require_keys_eq!(
+            ctx.accounts.pda.key(),
+            ctx.accounts.ledger.key(),
+            ErrorCode::InvalidDelegatedAccount
+        );
+        let ledger = &mut ctx.accounts.ledger;
+        require!(
+            ledger.delegation_status == DelegationStatus::Undelegated as u8,
+            ErrorCode::AlreadyDelegated
+        );
+        ledger.delegation_status = DelegationStatus::Delegated as u8;
+        ledger.exit(&crate::ID)?;
 
-        let streak = &mut ctx.accounts.streak;
-        streak.user = ctx.accounts.user.key();
-        streak.market = ctx.accounts.market.key();
-        streak.target = target;
-        streak.current_streak = 0;
-        streak.status = StreakStatus::Active as u8;
-        streak.last_round = Pubkey::default();
+        let validator = ctx.remaining_accounts.first().map(|acc| acc.key());
+        ctx.accounts.delegate_pda(
+            &ctx.accounts.payer,
+            &[LEDGER_SEED, ctx.accounts.market.key().as_ref()],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
         Ok(())
     }
 
-    pub fn update_streak(ctx: Context<UpdateStreak>, won: bool) -> Result<()> {
-        let streak = &mut ctx.accounts.streak;
+    /// ER-only: Settle a delegated bet; no token CPI. Its payout is owed to the
+    /// balance vault and moves there with the round's other results in
+    /// `sync_round_balances`.
+    pub fn er_settle_bet(ctx: Context<ErSettleBet>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+        let round = &ctx.accounts.round;
         require!(
-            streak.status == StreakStatus::Active as u8,
-            ErrorCode::StreakNotActive
+            round.status == RoundStatus::Locked as u8,
+            ErrorCode::InvalidState
+        );
+        require!(round.revealed_at > 0, ErrorCode::OutcomeNotRevealed);
+        require!(
+            round.settlement_mode == SettlementMode::Rollup as u8,
+            ErrorCode::WrongSettlementMode
         );
 
-        if won {
-            streak.current_streak = streak.current_streak.saturating_add(1);
-            streak.last_round = ctx.accounts.round.key();
+        let accounts = &mut *ctx.accounts;
+        credit_er_bet(&mut accounts.round, &mut accounts.bet, &mut accounts.ledger)?;
+        Ok(())
+    }
 
-            if streak.current_streak >= streak.target {
-                streak.status = StreakStatus::Completed as u8;
-            }
-        } else {
-            streak.status = StreakStatus::Failed as u8;
-        }
+    /// ER-only: Settle the round and write it back to base with its settled
+    /// bets, passed as remaining accounts. The market ledger is committed but
+    /// stays delegated for the market's other ER rounds.
+    pub fn commit_er_settlement<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ErSettlementCommitCtx<'info>>,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.payer.key(),
+        )?;
+        let round_key = ctx.accounts.round.key();
+        let round = &mut ctx.accounts.round;
+        require!(
+            round.status == RoundStatus::Locked as u8,
+            ErrorCode::InvalidState
+        );
+        require!(round.revealed_at > 0, ErrorCode::OutcomeNotRevealed);
+        require!(round.unsettled_bets == 0, ErrorCode::UnsettledBetsRemain);
+        require!(
+            round.settlement_mode == SettlementMode::Rollup as u8,
+            ErrorCode::WrongSettlementMode
+        );
+        round.status = RoundStatus::Settled as u8;
+        round.open_liability = 0;
+        round.delegation_status = DelegationStatus::Undelegated as u8;
+        round.exit(&crate::ID)?;
+
+        let mut accounts = vec![ctx.accounts.round.to_account_info()];
+        accounts.extend(settled_round_bets(&round_key, ctx.remaining_accounts)?);
+        commit_accounts(
+            &ctx.accounts.payer,
+            vec![&ctx.accounts.ledger.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+        commit_and_undelegate_accounts(
+            &ctx.accounts.payer,
+            accounts.iter().collect(),
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+        Ok(())
+    }
 
+    /// ER-only: Write back settled bets that were left out of
+    /// `commit_er_settlement`. Permissionless; pass the bet PDAs as remaining
+    /// accounts.
+    pub fn commit_er_bets<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ErBetsCommitCtx<'info>>,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require!(
+            !ctx.remaining_accounts.is_empty(),
+            ErrorCode::InvalidDelegatedAccount
+        );
+        let accounts = settled_round_bets(&ctx.accounts.round.key(), ctx.remaining_accounts)?;
+        commit_and_undelegate_accounts(
+            &ctx.accounts.payer,
+            accounts.iter().collect(),
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
         Ok(())
     }
 
-    pub fn claim_streak_reward(ctx: Context<ClaimStreakReward>) -> Result<()> {
-        let streak = &ctx.accounts.streak;
+    /// ER-only: Commit the market ledger back to base and undelegate it. Only
+    /// safe once none of the market's rounds are settling in the ER.
+    pub fn commit_and_undelegate_market_ledger(ctx: Context<MarketLedgerCommitCtx>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_DELEGATION)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.payer.key(),
+        )?;
+        let ledger = &mut ctx.accounts.ledger;
         require!(
-            streak.status == StreakStatus::Completed as u8,
-            ErrorCode::StreakNotCompleted
+            ledger.delegation_status == DelegationStatus::Delegated as u8,
+            ErrorCode::NotDelegated
         );
-        require_keys_eq!(
-            streak.user,
-            ctx.accounts.user.key(),
-            ErrorCode::Unauthorized
+        ledger.delegation_status = DelegationStatus::Undelegated as u8;
+        ledger.exit(&crate::ID)?;
+
+        commit_and_undelegate_accounts(
+            &ctx.accounts.payer,
+            vec![&ctx.accounts.ledger.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+        Ok(())
+    }
+
+    /// Credit the winnings of a bet settled in the ER, or of a balance-funded
+    /// bet, to the bettor's balance. Callable by anyone once
+    /// `sync_round_balances` has moved the round's payouts into the balance
+    /// vault; the caller pays rent for a new balance.
+    pub fn collect_payout(ctx: Context<CollectPayout>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require!(
+            ctx.accounts.round.balances_synced,
+            ErrorCode::BalancesNotSynced
+        );
+        let bet = &mut ctx.accounts.bet;
+        require!(bet.settled, ErrorCode::BetNotSettled);
+        require!(!bet.paid_out, ErrorCode::AlreadyPaidOut);
+
+        let player_balance = &mut ctx.accounts.player_balance;
+        if player_balance.owner == Pubkey::default() {
+            player_balance.version = PlayerBalance::VERSION;
+            player_balance.owner = bet.user;
+            player_balance.mint = ctx.accounts.market.mint;
+            player_balance.delegation_status = DelegationStatus::Undelegated as u8;
+            player_balance.bump = ctx.bumps.player_balance;
+            player_balance.vault_bump = ctx.accounts.market.balance_vault_bump;
+        }
+        require!(
+            player_balance.delegation_status == DelegationStatus::Undelegated as u8,
+            ErrorCode::AlreadyDelegated
         );
+        player_balance.balance = player_balance
+            .balance
+            .checked_add(bet.payout)
+            .ok_or(ErrorCode::Overflow)?;
+        bet.paid_out = true;
+        Ok(())
+    }
+
+    /// Deposit tokens into the caller's internal balance for `mint`.
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidStake);
 
-        let odds_bps = compute_streak_odds(streak.target)?;
-        let base_stake = 100_000_000u64;
-        let payout = base_stake
-            .checked_mul(odds_bps as u64)
-            .ok_or(ErrorCode::Overflow)?
-            / 100u64;
+        let player_balance = &mut ctx.accounts.player_balance;
+        if player_balance.owner == Pubkey::default() {
+            player_balance.version = PlayerBalance::VERSION;
+            player_balance.owner = ctx.accounts.user.key();
+            player_balance.mint = ctx.accounts.mint.key();
+            player_balance.delegation_status = DelegationStatus::Undelegated as u8;
+            player_balance.bump = ctx.bumps.player_balance;
+            player_balance.vault_bump = ctx.bumps.balance_vault_authority;
+        }
+        require!(
+            player_balance.delegation_status == DelegationStatus::Undelegated as u8,
+            ErrorCode::AlreadyDelegated
+        );
 
         let decimals = ctx.accounts.mint.decimals;
-        let market_key = ctx.accounts.market.key();
-        let seeds = &[VAULT_SEED, market_key.as_ref()];
-        let (_vault_pda, bump) = Pubkey::find_program_address(seeds, ctx.program_id);
-        let signer_slice: &[&[u8]] = &[VAULT_SEED, market_key.as_ref(), &[bump]];
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_token.to_account_info(),
+            to: ctx.accounts.balance_vault_token.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let player_balance = &mut ctx.accounts.player_balance;
+        player_balance.balance = player_balance
+            .balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        player_balance.total_deposited = player_balance.total_deposited.saturating_add(amount);
+        Ok(())
+    }
+
+    /// Withdraw tokens from the caller's internal balance. Winnings only reach
+    /// the balance through `collect_payout`, after `sync_round_balances` has
+    /// moved them into the balance vault.
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require!(amount > 0, ErrorCode::InvalidStake);
+
+        let player_balance = &mut ctx.accounts.player_balance;
+        require!(
+            player_balance.delegation_status == DelegationStatus::Undelegated as u8,
+            ErrorCode::AlreadyDelegated
+        );
+        require!(
+            player_balance.balance >= amount,
+            ErrorCode::InsufficientBalance
+        );
+        player_balance.balance -= amount;
+        player_balance.total_withdrawn = player_balance.total_withdrawn.saturating_add(amount);
+
+        let decimals = ctx.accounts.mint.decimals;
+        let mint_key = ctx.accounts.mint.key();
+        let bump = ctx.accounts.player_balance.vault_bump;
+        let signer_slice: &[&[u8]] = &[BALANCE_VAULT_SEED, mint_key.as_ref(), &[bump]];
         let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
 
         let cpi_accounts = TransferChecked {
-            from: ctx.accounts.vault_token.to_account_info(),
+            from: ctx.accounts.balance_vault_token.to_account_info(),
             to: ctx.accounts.user_token.to_account_info(),
             mint: ctx.accounts.mint.to_account_info(),
-            authority: ctx.accounts.vault_authority.to_account_info(),
+            authority: ctx.accounts.balance_vault_authority.to_account_info(),
         };
         let cpi_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             cpi_accounts,
             signer_seeds,
         );
-        token::transfer_checked(cpi_ctx, payout, decimals)?;
-
-        let streak_mut = &mut ctx.accounts.streak;
-        streak_mut.status = StreakStatus::Claimed as u8;
-
+        token::transfer_checked(cpi_ctx, amount, decimals)?;
         Ok(())
     }
 
-    pub fn join_community_round(ctx: Context<JoinCommunityRound>, seed_byte: u8) -> Result<()> {
-        let round = &ctx.accounts.round;
+    pub fn delegate_player_balance(ctx: Context<DelegatePlayerBalance>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_DELEGATION)?;
+        require_keys_eq!(
+            ctx.accounts.pda.key(),
+            ctx.accounts.player_balance.key(),
+            ErrorCode::InvalidDelegatedAccount
+        );
+        let player_balance = &mut ctx.accounts.player_balance;
         require!(
-            round.status == RoundStatus::Predicting as u8,
-            ErrorCode::RoundNotPredicting
+            player_balance.delegation_status == DelegationStatus::Undelegated as u8,
+            ErrorCode::AlreadyDelegated
         );
+        player_balance.delegation_status = DelegationStatus::Delegated as u8;
+        player_balance.exit(&crate::ID)?;
 
-        let clock = Clock::get()?;
-        if round.lock_scheduled_at > 0 {
-            require!(
-                clock.unix_timestamp < round.lock_scheduled_at,
-                ErrorCode::BettingClosed
-            );
-        }
-
-        let community = &mut ctx.accounts.community_entry;
-        community.user = ctx.accounts.user.key();
-        community.round = round.key();
-        community.seed_byte = seed_byte;
-        community.distance = None;
-        community.won = false;
-
+        let validator = ctx.remaining_accounts.first().map(|acc| acc.key());
+        ctx.accounts.delegate_pda(
+            &ctx.accounts.payer,
+            &[
+                PLAYER_BALANCE_SEED,
+                ctx.accounts.payer.key().as_ref(),
+                ctx.accounts.player_balance.mint.as_ref(),
+            ],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
         Ok(())
     }
 
-    pub fn finalize_community_round(
-        ctx: Context<FinalizeCommunityRound>,
-        all_seeds: Vec<u8>,
+    /// ER-only: Commit the player's balance back to base and undelegate it.
+    pub fn commit_and_undelegate_player_balance(
+        ctx: Context<PlayerBalanceCommitCtx>,
     ) -> Result<()> {
-        require!(all_seeds.len() > 0, ErrorCode::NoCommunitySeedsProvided);
-
-        let mut hasher = Sha256::new();
-        hasher.update(&all_seeds);
-        let hash_result = hasher.finalize();
+        require_not_paused(&ctx.accounts.config, PAUSE_DELEGATION)?;
+        let player_balance = &mut ctx.accounts.player_balance;
+        require!(
+            player_balance.delegation_status == DelegationStatus::Delegated as u8,
+            ErrorCode::NotDelegated
+        );
+        player_balance.delegation_status = DelegationStatus::Undelegated as u8;
+        player_balance.exit(&crate::ID)?;
 
-        let mut seed_hash = [0u8; 32];
-        seed_hash.copy_from_slice(&hash_result);
-
-        let final_byte = seed_hash[31];
-
-        let round = &mut ctx.accounts.round;
-        round.outcome = OutcomeType::Community {
-            final_byte,
-            seed_hash,
-        };
+        commit_and_undelegate_accounts(
+            &ctx.accounts.payer,
+            vec![&ctx.accounts.player_balance.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+        Ok(())
+    }
 
+    /// Settle a balance-funded bet. Its winnings join the round's balance
+    /// credits and reach the player's balance through `collect_payout` once
+    /// the round is synced. Runs on base or inside the ER.
+    pub fn settle_bet_to_balance(ctx: Context<SettleBetToBalance>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+        require_settleable(&ctx.accounts.round)?;
+        let accounts = &mut *ctx.accounts;
+        credit_balance_bet(&mut accounts.round, &mut accounts.bet)?;
         Ok(())
     }
 
-    pub fn settle_community_entry(ctx: Context<SettleCommunityEntry>) -> Result<()> {
+    /// Move the round's net balance-funded result between the balance vault
+    /// and the market vault: stakes taken from balances belong to the market,
+    /// payouts credited to balances are owed by it.
+    pub fn sync_round_balances(ctx: Context<SyncRoundBalances>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_keys_eq!(
+            ctx.accounts.market.mint,
+            ctx.accounts.mint.key(),
+            ErrorCode::Unauthorized
+        );
         let round = &ctx.accounts.round;
-        require!(round.revealed_at > 0, ErrorCode::OutcomeNotRevealed);
-
-        let final_byte = match round.outcome {
-            OutcomeType::Community { final_byte, .. } => final_byte,
-            _ => return Err(ErrorCode::InvalidOutcomeType.into()),
-        };
+        require!(
+            round.status == RoundStatus::Settled as u8,
+            ErrorCode::InvalidState
+        );
+        require!(!round.balances_synced, ErrorCode::AlreadySettled);
 
-        let entry = &mut ctx.accounts.community_entry;
-        let distance = calculate_hamming_distance(entry.seed_byte, final_byte);
+        let decimals = ctx.accounts.mint.decimals;
+        let market_key = ctx.accounts.market.key();
+        let mint_key = ctx.accounts.mint.key();
 
-        entry.distance = Some(distance);
-        entry.won = distance == 0;
+        if round.balance_staked > round.balance_credited {
+            let amount = round.balance_staked - round.balance_credited;
+            let bump = ctx.accounts.market.balance_vault_bump;
+            let signer_slice: &[&[u8]] = &[BALANCE_VAULT_SEED, mint_key.as_ref(), &[bump]];
+            let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
 
-        if entry.won {
-            let payout = 1_000_000_000u64;
-            let decimals = ctx.accounts.mint.decimals;
-            let market_key = ctx.accounts.market.key();
-            let seeds = &[VAULT_SEED, market_key.as_ref()];
-            let (_vault_pda, bump) = Pubkey::find_program_address(seeds, ctx.program_id);
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.balance_vault_token.to_account_info(),
+                to: ctx.accounts.vault_token.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.balance_vault_authority.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            token::transfer_checked(cpi_ctx, amount, decimals)?;
+        } else if round.balance_credited > round.balance_staked {
+            let amount = round.balance_credited - round.balance_staked;
+            let bump = ctx.accounts.market.vault_bump;
             let signer_slice: &[&[u8]] = &[VAULT_SEED, market_key.as_ref(), &[bump]];
             let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
 
             let cpi_accounts = TransferChecked {
                 from: ctx.accounts.vault_token.to_account_info(),
-                to: ctx.accounts.user_token.to_account_info(),
+                to: ctx.accounts.balance_vault_token.to_account_info(),
                 mint: ctx.accounts.mint.to_account_info(),
                 authority: ctx.accounts.vault_authority.to_account_info(),
             };
@@ -814,61 +1945,101 @@ pub mod tossr_engine {
                 cpi_accounts,
                 signer_seeds,
             );
-            token::transfer_checked(cpi_ctx, payout, decimals)?;
+            token::transfer_checked(cpi_ctx, amount, decimals)?;
         }
 
+        ctx.accounts.round.balances_synced = true;
         Ok(())
     }
 
-    pub fn init_jackpot_pot(ctx: Context<InitJackpotPot>) -> Result<()> {
-        let pot = &mut ctx.accounts.jackpot_pot;
-        pot.market = ctx.accounts.market.key();
-        pot.current_pot = 0;
-        pot.last_winner = None;
-        pot.total_contributed = 0;
+    pub fn init_vault(_ctx: Context<InitVault>) -> Result<()> {
         Ok(())
     }
 
-    pub fn contribute_to_jackpot(ctx: Context<ContributeToJackpot>, amount: u64) -> Result<()> {
-        require!(amount > 0, ErrorCode::InvalidStake);
-
-        let decimals = ctx.accounts.mint.decimals;
-        let cpi_accounts = TransferChecked {
-            from: ctx.accounts.user_token.to_account_info(),
-            to: ctx.accounts.vault_token.to_account_info(),
-            mint: ctx.accounts.mint.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer_checked(cpi_ctx, amount, decimals)?;
-
-        let pot = &mut ctx.accounts.jackpot_pot;
-        pot.current_pot = pot.current_pot.saturating_add(amount);
-        pot.total_contributed = pot.total_contributed.saturating_add(amount);
+    pub fn init_treasury(ctx: Context<InitTreasury>) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Owner,
+            &ctx.accounts.admin.key(),
+        )?;
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.version = Treasury::VERSION;
+        treasury.market = ctx.accounts.market.key();
+        treasury.settled_through = 0;
+        treasury.streak_liability = 0;
+        treasury.total_withdrawn = 0;
+        treasury.lp_enabled = false;
+        treasury.crank_reward = 0;
+        treasury.parlay_liability = 0;
+        Ok(())
+    }
 
+    /// Skip past rounds that no longer carry liabilities so withdrawals only
+    /// need to pass the rounds still open. Permissionless; pass consecutive
+    /// round PDAs starting at `settled_through + 1` as remaining accounts.
+    /// A long backlog can be cleared in chunks over several calls; the cursor
+    /// stops at the first round still open or delegated.
+    pub fn advance_treasury_cursor(ctx: Context<AdvanceTreasuryCursor>) -> Result<()> {
+        let market_key = ctx.accounts.market.key();
+        let treasury = &mut ctx.accounts.treasury;
+        for info in ctx.remaining_accounts.iter() {
+            let number = treasury.settled_through.saturating_add(1);
+            let round = load_market_round(info, &market_key, number, ctx.program_id)?;
+            if !round_is_closed_out(&round) {
+                break;
+            }
+            treasury.settled_through = number;
+        }
         Ok(())
     }
 
-    pub fn claim_jackpot(ctx: Context<ClaimJackpot>) -> Result<()> {
-        let bet = &ctx.accounts.bet;
-        require!(bet.won, ErrorCode::BetNotWon);
-        require!(bet.settled, ErrorCode::BetNotSettled);
+    /// Withdraw house profit from the market vault. Only the excess over
+    /// outstanding liabilities can leave: the worst-case payouts of every round
+    /// after `settled_through` (passed in order as remaining accounts), unpaid
+    /// ER payouts, the jackpot pot and open streak rewards.
+    pub fn withdraw_house_funds(ctx: Context<WithdrawHouseFunds>, amount: u64) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Treasurer,
+            &ctx.accounts.treasurer.key(),
+        )?;
+        require_keys_eq!(
+            ctx.accounts.market.mint,
+            ctx.accounts.mint.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(amount > 0, ErrorCode::InvalidStake);
 
-        let pot = &mut ctx.accounts.jackpot_pot;
-        let jackpot_amount = pot.current_pot;
+        require!(
+            !ctx.accounts.treasury.lp_enabled,
+            ErrorCode::LiquidityPoolActive
+        );
 
-        require!(jackpot_amount > 0, ErrorCode::EmptyJackpot);
+        let market_key = ctx.accounts.market.key();
+        let liabilities = scan_house_liabilities(
+            &mut ctx.accounts.treasury,
+            &market_key,
+            ctx.accounts.market.last_round,
+            ctx.remaining_accounts,
+            &ctx.accounts.jackpot_pot,
+            ctx.program_id,
+        )?;
+
+        let vault_balance = ctx.accounts.vault_token.amount;
+        let available = liabilities.withdrawal_equity(vault_balance)?;
+        require!(amount <= available, ErrorCode::InsufficientHouseFunds);
 
         let decimals = ctx.accounts.mint.decimals;
-        let market_key = ctx.accounts.market.key();
-        let seeds = &[VAULT_SEED, market_key.as_ref()];
-        let (_vault_pda, bump) = Pubkey::find_program_address(seeds, ctx.program_id);
+        let bump = ctx.accounts.market.vault_bump;
         let signer_slice: &[&[u8]] = &[VAULT_SEED, market_key.as_ref(), &[bump]];
         let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
 
         let cpi_accounts = TransferChecked {
             from: ctx.accounts.vault_token.to_account_info(),
-            to: ctx.accounts.user_token.to_account_info(),
+            to: ctx.accounts.destination.to_account_info(),
             mint: ctx.accounts.mint.to_account_info(),
             authority: ctx.accounts.vault_authority.to_account_info(),
         };
@@ -877,1222 +2048,6574 @@ pub mod tossr_engine {
             cpi_accounts,
             signer_seeds,
         );
-        token::transfer_checked(cpi_ctx, jackpot_amount, decimals)?;
-
-        pot.current_pot = 0;
-        pot.last_winner = Some(bet.user);
+        token::transfer_checked(cpi_ctx, amount, decimals)?;
 
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.total_withdrawn = treasury.total_withdrawn.saturating_add(amount);
+
+        emit!(HouseFundsWithdrawn {
+            market: market_key,
+            destination: ctx.accounts.destination.key(),
+            amount,
+            vault_balance: vault_balance - amount,
+            liabilities: liabilities.total()?,
+            total_withdrawn: treasury.total_withdrawn,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
-    pub fn set_pattern_config(
-        ctx: Context<SetPatternConfig>,
-        pattern_id: u8,
-        pattern_type: PatternType,
+    /// Open the market vault to outside liquidity. The existing house equity
+    /// is minted as shares to the admin, and admin withdrawals via
+    /// `withdraw_house_funds` are disabled from then on.
+    pub fn init_liquidity_pool(
+        ctx: Context<InitLiquidityPool>,
+        cooldown_seconds: i64,
     ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Owner,
+            &ctx.accounts.admin.key(),
+        )?;
         require_keys_eq!(
-            ctx.accounts.market.admin,
-            ctx.accounts.admin.key(),
+            ctx.accounts.market.mint,
+            ctx.accounts.mint.key(),
             ErrorCode::Unauthorized
         );
+        require!(
+            (0..=MAX_LP_COOLDOWN).contains(&cooldown_seconds),
+            ErrorCode::InvalidCooldown
+        );
 
-        let config = &mut ctx.accounts.pattern_config;
-        config.market = ctx.accounts.market.key();
-        config.pattern_id = pattern_id;
-        config.pattern_type = pattern_type;
-        config.is_active = true;
+        let market_key = ctx.accounts.market.key();
+        let liabilities = scan_house_liabilities(
+            &mut ctx.accounts.treasury,
+            &market_key,
+            ctx.accounts.market.last_round,
+            ctx.remaining_accounts,
+            &ctx.accounts.jackpot_pot,
+            ctx.program_id,
+        )?;
+        let equity = liabilities.withdrawal_equity(ctx.accounts.vault_token.amount)?;
+        ctx.accounts.treasury.lp_enabled = true;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.version = LiquidityPool::VERSION;
+        pool.market = market_key;
+        pool.share_mint = ctx.accounts.share_mint.key();
+        pool.cooldown_seconds = cooldown_seconds;
+        pool.total_deposited = equity;
+        pool.total_withdrawn = 0;
+        pool.bump = ctx.bumps.pool;
+
+        if equity > 0 {
+            let bump = pool.bump;
+            let signer_slice: &[&[u8]] = &[POOL_SEED, market_key.as_ref(), &[bump]];
+            let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
 
+            let cpi_accounts = MintTo {
+                mint: ctx.accounts.share_mint.to_account_info(),
+                to: ctx.accounts.admin_shares.to_account_info(),
+                authority: ctx.accounts.pool.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            token::mint_to(cpi_ctx, equity)?;
+        }
         Ok(())
     }
 
-    pub fn create_round_permission_group(
-        ctx: Context<CreateRoundPermissionGroup>,
-        allowed_viewers: Vec<Pubkey>,
-    ) -> Result<()> {
+    /// Add tokens to the market vault in exchange for pool shares priced at
+    /// the vault balance minus what is already owed. Open bets' worst-case
+    /// payouts are not deducted, so inflating them cannot buy cheap shares.
+    /// Equity left in a pool with no shares outstanding is first minted to
+    /// the owner, as `init_liquidity_pool` does, so the depositor pays for it.
+    pub fn deposit_liquidity(ctx: Context<DepositLiquidity>, amount: u64) -> Result<()> {
         require_keys_eq!(
-            ctx.accounts.market.admin,
-            ctx.accounts.admin.key(),
+            ctx.accounts.market.mint,
+            ctx.accounts.mint.key(),
             ErrorCode::Unauthorized
         );
+        require_keys_eq!(
+            ctx.accounts.market.admin,
+            ctx.accounts.config.key(),
+            ErrorCode::MarketNotMigrated
+        );
+        require!(amount > 0, ErrorCode::InvalidStake);
 
-        let permission_group = &mut ctx.accounts.permission_group;
-        permission_group.round = ctx.accounts.round.key();
-        permission_group.allowed_viewers = allowed_viewers;
-        permission_group.is_private = true;
+        let market_key = ctx.accounts.market.key();
+        let liabilities = scan_house_liabilities(
+            &mut ctx.accounts.treasury,
+            &market_key,
+            ctx.accounts.market.last_round,
+            ctx.remaining_accounts,
+            &ctx.accounts.jackpot_pot,
+            ctx.program_id,
+        )?;
+        let equity = liabilities.deposit_equity(ctx.accounts.vault_token.amount);
+        let owner_shares = residual_shares(equity, ctx.accounts.share_mint.supply);
+        let supply = ctx.accounts.share_mint.supply.max(owner_shares);
+        let shares = shares_for_deposit(amount, equity, supply)?;
+        require!(shares > 0, ErrorCode::InvalidStake);
+
+        let decimals = ctx.accounts.mint.decimals;
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.provider_token.to_account_info(),
+            to: ctx.accounts.vault_token.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            authority: ctx.accounts.provider.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let bump = ctx.accounts.pool.bump;
+        let signer_slice: &[&[u8]] = &[POOL_SEED, market_key.as_ref(), &[bump]];
+        let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
+
+        if owner_shares > 0 {
+            let cpi_accounts = MintTo {
+                mint: ctx.accounts.share_mint.to_account_info(),
+                to: ctx.accounts.owner_shares.to_account_info(),
+                authority: ctx.accounts.pool.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            token::mint_to(cpi_ctx, owner_shares)?;
+        }
 
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.share_mint.to_account_info(),
+            to: ctx.accounts.provider_shares.to_account_info(),
+            authority: ctx.accounts.pool.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token::mint_to(cpi_ctx, shares)?;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.total_deposited = pool.total_deposited.saturating_add(amount);
+
+        emit!(LiquidityDeposited {
+            market: market_key,
+            provider: ctx.accounts.provider.key(),
+            amount,
+            shares,
+            equity,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
-    pub fn add_viewer_to_permission_group(
-        ctx: Context<UpdatePermissionGroup>,
-        viewer: Pubkey,
+    /// Start the cooldown for redeeming `shares`. Replaces any pending request.
+    pub fn request_liquidity_withdrawal(
+        ctx: Context<RequestLiquidityWithdrawal>,
+        shares: u64,
     ) -> Result<()> {
-        require_keys_eq!(
-            ctx.accounts.market.admin,
-            ctx.accounts.admin.key(),
-            ErrorCode::Unauthorized
+        require!(shares > 0, ErrorCode::InsufficientShares);
+        require!(
+            ctx.accounts.provider_shares.amount >= shares,
+            ErrorCode::InsufficientShares
         );
 
-        let permission_group = &mut ctx.accounts.permission_group;
-        require!(
-            !permission_group.allowed_viewers.contains(&viewer),
-            ErrorCode::ViewerAlreadyExists
+        let withdrawal = &mut ctx.accounts.withdrawal;
+        withdrawal.version = LpWithdrawal::VERSION;
+        withdrawal.provider = ctx.accounts.provider.key();
+        withdrawal.pool = ctx.accounts.pool.key();
+        withdrawal.shares = shares;
+        withdrawal.requested_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Redeem a matured withdrawal request. Shares are priced at equity net of
+    /// every open round's worst-case payouts, so exposure stays covered.
+    pub fn withdraw_liquidity(ctx: Context<WithdrawLiquidity>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_keys_eq!(
+            ctx.accounts.market.mint,
+            ctx.accounts.mint.key(),
+            ErrorCode::Unauthorized
         );
+        let now = Clock::get()?.unix_timestamp;
+        let withdrawal = &ctx.accounts.withdrawal;
         require!(
-            permission_group.allowed_viewers.len() < 50,
-            ErrorCode::MaxViewersReached
+            now >= withdrawal
+                .requested_at
+                .saturating_add(ctx.accounts.pool.cooldown_seconds),
+            ErrorCode::CooldownActive
         );
+        let shares = withdrawal.shares;
 
-        permission_group.allowed_viewers.push(viewer);
+        let market_key = ctx.accounts.market.key();
+        let liabilities = scan_house_liabilities(
+            &mut ctx.accounts.treasury,
+            &market_key,
+            ctx.accounts.market.last_round,
+            ctx.remaining_accounts,
+            &ctx.accounts.jackpot_pot,
+            ctx.program_id,
+        )?;
+        let equity = liabilities.withdrawal_equity(ctx.accounts.vault_token.amount)?;
+        let amount = amount_for_shares(shares, equity, ctx.accounts.share_mint.supply)?;
+
+        let cpi_accounts = Burn {
+            mint: ctx.accounts.share_mint.to_account_info(),
+            from: ctx.accounts.provider_shares.to_account_info(),
+            authority: ctx.accounts.provider.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::burn(cpi_ctx, shares)?;
+
+        if amount > 0 {
+            let decimals = ctx.accounts.mint.decimals;
+            let bump = ctx.accounts.market.vault_bump;
+            let signer_slice: &[&[u8]] = &[VAULT_SEED, market_key.as_ref(), &[bump]];
+            let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
+
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.vault_token.to_account_info(),
+                to: ctx.accounts.provider_token.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            token::transfer_checked(cpi_ctx, amount, decimals)?;
+        }
 
+        let pool = &mut ctx.accounts.pool;
+        pool.total_withdrawn = pool.total_withdrawn.saturating_add(amount);
+
+        emit!(LiquidityWithdrawn {
+            market: market_key,
+            provider: ctx.accounts.provider.key(),
+            amount,
+            shares,
+            equity,
+            timestamp: now,
+        });
         Ok(())
     }
 
-    pub fn remove_viewer_from_permission_group(
-        ctx: Context<UpdatePermissionGroup>,
-        viewer: Pubkey,
-    ) -> Result<()> {
-        require_keys_eq!(
-            ctx.accounts.market.admin,
-            ctx.accounts.admin.key(),
-            ErrorCode::Unauthorized
-        );
+    pub fn init_streak(ctx: Context<InitStreak>, target: u16) -> Result<()> {
+        require!(target >= 2 && target <= 10, ErrorCode::InvalidStreakTarget);
 
-        let permission_group = &mut ctx.accounts.permission_group;
-        permission_group.allowed_viewers.retain(|&v| v != viewer);
+        let streak = &mut ctx.accounts.streak;
+        streak.version = Streak::VERSION;
+        streak.user = ctx.accounts.user.key();
+        streak.market = ctx.accounts.market.key();
+        streak.target = target;
+        streak.current_streak = 0;
+        streak.status = StreakStatus::Active as u8;
+        streak.last_round = Pubkey::default();
+        streak.rent_payer = ctx.accounts.payer.key();
 
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.streak_liability = treasury
+            .streak_liability
+            .checked_add(streak_reward(target)?)
+            .ok_or(ErrorCode::Overflow)?;
         Ok(())
     }
-}
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum MarketType {
-    PickRange,
-    EvenOdd,
-    LastDigit,
-    ModuloThree,
-    PatternOfDay,
-    ShapeColor,
-    Jackpot,
-    EntropyBattle,
-    StreakMeter,
-    CommunitySeed,
-}
+    pub fn update_streak(ctx: Context<UpdateStreak>, won: bool) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        let streak = &mut ctx.accounts.streak;
+        require!(
+            streak.status == StreakStatus::Active as u8,
+            ErrorCode::StreakNotActive
+        );
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
-pub enum OutcomeType {
-    Pending,
-    Numeric {
-        value: u16,
-    },
-    Shape {
-        shape: u8,
-        color: u8,
-        size: u8,
-    },
-    Pattern {
-        pattern_id: u8,
-        matched_value: u16,
-    },
-    Entropy {
-        tee_score: u16,
-        chain_score: u16,
-        sensor_score: u16,
-        winner: u8,
-    },
-    Community {
-        final_byte: u8,
-        seed_hash: [u8; 32],
-    },
-}
+        if won {
+            streak.current_streak = streak.current_streak.saturating_add(1);
+            streak.last_round = ctx.accounts.round.key();
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum SelectionKind {
-    Range = 0,
-    Single = 1,
-    Parity = 2,
-    Digit = 3,
-    Modulo = 4,
-    Pattern = 5,
-    Shape = 6,
-    Entropy = 7,
-    Streak = 8,
-    Community = 9,
-}
+            if streak.current_streak >= streak.target {
+                streak.status = StreakStatus::Completed as u8;
+            }
+        } else {
+            streak.status = StreakStatus::Failed as u8;
+            let treasury = &mut ctx.accounts.treasury;
+            treasury.streak_liability = treasury
+                .streak_liability
+                .saturating_sub(streak_reward(streak.target)?);
+        }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub struct Selection {
-    pub kind: u8,
-    pub a: u16,
-    pub b: u16,
-    pub c: u16,
-}
+        Ok(())
+    }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum RoundStatus {
-    Predicting = 0,
-    Locked = 1,
-    Settled = 2,
-}
+    pub fn claim_streak_reward(ctx: Context<ClaimStreakReward>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        let streak = &ctx.accounts.streak;
+        require!(
+            streak.status == StreakStatus::Completed as u8,
+            ErrorCode::StreakNotCompleted
+        );
+        require_keys_eq!(
+            streak.user,
+            ctx.accounts.user.key(),
+            ErrorCode::Unauthorized
+        );
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum StreakStatus {
-    Active = 0,
-    Completed = 1,
-    Failed = 2,
-    Claimed = 3,
-}
+        let payout = streak_reward(streak.target)?;
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum PatternType {
-    Prime,
-    Fibonacci,
-    PerfectSquare,
-    EndsWithSeven,
-    Palindrome,
-    Even,
-    Odd,
-}
+        let decimals = ctx.accounts.mint.decimals;
+        let market_key = ctx.accounts.market.key();
+        let bump = ctx.accounts.market.vault_bump;
+        let signer_slice: &[&[u8]] = &[VAULT_SEED, market_key.as_ref(), &[bump]];
+        let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
 
-#[account]
-pub struct Market {
-    pub admin: Pubkey,
-    pub name: String,
-    pub is_active: bool,
-    pub last_round: u64,
-    pub house_edge_bps: u16,
-    pub mint: Pubkey,
-    pub market_type: MarketType,
-    pub index: u16,
-}
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_token.to_account_info(),
+            to: ctx.accounts.user_token.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            authority: ctx.accounts.vault_authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token::transfer_checked(cpi_ctx, payout, decimals)?;
 
-#[account]
-pub struct Round {
-    pub market: Pubkey,
-    pub number: u64,
-    pub status: u8,
-    pub inputs_hash: [u8; 32],
-    pub outcome: OutcomeType,
-    pub unsettled_bets: u32,
-    pub opened_at: i64,
-    pub lock_scheduled_at: i64,
-    pub locked_at: i64,
-    pub commitment_hash: Option<[u8; 32]>,
-    pub revealed_at: i64,
-}
+        let streak_mut = &mut ctx.accounts.streak;
+        streak_mut.status = StreakStatus::Claimed as u8;
 
-#[account]
-pub struct Bet {
-    pub user: Pubkey,
-    pub round: Pubkey,
-    pub stake: u64,
-    pub selection: Selection,
-    pub odds_bps: u16,
-    pub settled: bool,
-    pub won: bool,
-    pub payout: u64,
-    pub placed_at: i64,
-}
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.streak_liability = treasury.streak_liability.saturating_sub(payout);
 
-#[account]
-pub struct Streak {
-    pub user: Pubkey,
-    pub market: Pubkey,
-    pub target: u16,
-    pub current_streak: u16,
-    pub status: u8,
-    pub last_round: Pubkey,
-}
+        Ok(())
+    }
 
-#[account]
-pub struct CommunityEntry {
-    pub user: Pubkey,
-    pub round: Pubkey,
-    pub seed_byte: u8,
-    pub distance: Option<u8>,
-    pub won: bool,
-}
+    pub fn join_community_round(ctx: Context<JoinCommunityRound>, seed_byte: u8) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_BETTING)?;
+        let round = &mut ctx.accounts.round;
+        require!(
+            round.status == RoundStatus::Predicting as u8,
+            ErrorCode::RoundNotPredicting
+        );
 
-#[account]
-pub struct JackpotPot {
-    pub market: Pubkey,
-    pub current_pot: u64,
-    pub last_winner: Option<Pubkey>,
-    pub total_contributed: u64,
-}
+        let clock = Clock::get()?;
+        if round.lock_scheduled_at > 0 {
+            require!(
+                clock.unix_timestamp < round.lock_scheduled_at,
+                ErrorCode::BettingClosed
+            );
+        }
 
-#[account]
-pub struct PatternConfig {
-    pub market: Pubkey,
-    pub pattern_id: u8,
-    pub pattern_type: PatternType,
-    pub is_active: bool,
-}
+        let community = &mut ctx.accounts.community_entry;
+        community.version = CommunityEntry::VERSION;
+        community.user = ctx.accounts.user.key();
+        community.round = round.key();
+        community.seed_byte = seed_byte;
+        community.distance = None;
+        community.won = false;
+        community.rent_payer = ctx.accounts.payer.key();
+        round.open_bets = round.open_bets.saturating_add(1);
 
-#[account]
-pub struct PermissionGroup {
-    pub round: Pubkey,
-    pub allowed_viewers: Vec<Pubkey>,
-    pub is_private: bool,
-}
+        Ok(())
+    }
 
-#[derive(Accounts)]
-#[instruction(market_index: u16)]
-pub struct InitializeMarket<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + 32 + 4 + 64 + 1 + 8 + 2 + 32 + 16 + 2,
-        seeds = [MARKET_SEED, admin.key().as_ref(), &market_index.to_le_bytes()],
-        bump,
-    )]
-    pub market: Account<'info, Market>,
-    pub mint: Account<'info, Mint>,
-    pub system_program: Program<'info, System>,
-}
+    pub fn finalize_community_round(
+        ctx: Context<FinalizeCommunityRound>,
+        all_seeds: Vec<u8>,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REVEAL)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+        require!(all_seeds.len() > 0, ErrorCode::NoCommunitySeedsProvided);
 
-#[derive(Accounts)]
-pub struct ToggleMarket<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    #[account(mut, seeds = [MARKET_SEED, admin.key().as_ref(), &market.index.to_le_bytes()], bump)]
-    pub market: Account<'info, Market>,
-}
+        let mut hasher = Sha256::new();
+        hasher.update(&all_seeds);
+        let hash_result = hasher.finalize();
 
-#[derive(Accounts)]
-pub struct SetHouseEdge<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    #[account(mut, seeds = [MARKET_SEED, admin.key().as_ref(), &market.index.to_le_bytes()], bump)]
-    pub market: Account<'info, Market>,
-}
+        let mut seed_hash = [0u8; 32];
+        seed_hash.copy_from_slice(&hash_result);
 
-#[derive(Accounts)]
-pub struct OpenRound<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    #[account(mut, seeds = [MARKET_SEED, admin.key().as_ref(), &market.index.to_le_bytes()], bump)]
-    pub market: Account<'info, Market>,
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + 32 + 8 + 1 + 32 + 256 + 4 + 8 + 8 + 8 + 33 + 8,
-        seeds = [ROUND_SEED, market.key().as_ref(), &market.last_round.saturating_add(1).to_le_bytes()],
-        bump,
-    )]
-    pub round: Account<'info, Round>,
-    pub system_program: Program<'info, System>,
-}
+        let final_byte = seed_hash[31];
 
-#[derive(Accounts)]
-pub struct ScheduleLock<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
+        let round = &mut ctx.accounts.round;
+        round.outcome = OutcomeType::Community {
+            final_byte,
+            seed_hash,
+        };
+
+        Ok(())
+    }
+
+    pub fn settle_community_entry(ctx: Context<SettleCommunityEntry>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+        let round = &ctx.accounts.round;
+        require!(round.revealed_at > 0, ErrorCode::OutcomeNotRevealed);
+
+        let final_byte = match round.outcome {
+            OutcomeType::Community { final_byte, .. } => final_byte,
+            _ => return Err(ErrorCode::InvalidOutcomeType.into()),
+        };
+
+        let entry = &mut ctx.accounts.community_entry;
+        let distance = calculate_hamming_distance(entry.seed_byte, final_byte);
+
+        entry.distance = Some(distance);
+        entry.won = distance == 0;
+
+        if entry.won {
+            let payout = 1_000_000_000u64;
+            let decimals = ctx.accounts.mint.decimals;
+            let market_key = ctx.accounts.market.key();
+            let bump = ctx.accounts.market.vault_bump;
+            let signer_slice: &[&[u8]] = &[VAULT_SEED, market_key.as_ref(), &[bump]];
+            let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
+
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.vault_token.to_account_info(),
+                to: ctx.accounts.user_token.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            token::transfer_checked(cpi_ctx, payout, decimals)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn init_jackpot_pot(ctx: Context<InitJackpotPot>) -> Result<()> {
+        let pot = &mut ctx.accounts.jackpot_pot;
+        pot.version = JackpotPot::VERSION;
+        pot.market = ctx.accounts.market.key();
+        pot.current_pot = 0;
+        pot.last_winner = None;
+        pot.total_contributed = 0;
+        Ok(())
+    }
+
+    pub fn contribute_to_jackpot(ctx: Context<ContributeToJackpot>, amount: u64) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_BETTING)?;
+        require!(amount > 0, ErrorCode::InvalidStake);
+
+        let decimals = ctx.accounts.mint.decimals;
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_token.to_account_info(),
+            to: ctx.accounts.vault_token.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let pot = &mut ctx.accounts.jackpot_pot;
+        pot.current_pot = pot.current_pot.saturating_add(amount);
+        pot.total_contributed = pot.total_contributed.saturating_add(amount);
+
+        Ok(())
+    }
+
+    pub fn claim_jackpot(ctx: Context<ClaimJackpot>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        let bet = &ctx.accounts.bet;
+        require!(bet.won, ErrorCode::BetNotWon);
+        require!(bet.settled, ErrorCode::BetNotSettled);
+
+        let pot = &mut ctx.accounts.jackpot_pot;
+        let jackpot_amount = pot.current_pot;
+
+        require!(jackpot_amount > 0, ErrorCode::EmptyJackpot);
+
+        let decimals = ctx.accounts.mint.decimals;
+        let market_key = ctx.accounts.market.key();
+        let bump = ctx.accounts.market.vault_bump;
+        let signer_slice: &[&[u8]] = &[VAULT_SEED, market_key.as_ref(), &[bump]];
+        let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_token.to_account_info(),
+            to: ctx.accounts.user_token.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            authority: ctx.accounts.vault_authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token::transfer_checked(cpi_ctx, jackpot_amount, decimals)?;
+
+        pot.current_pot = 0;
+        pot.last_winner = Some(bet.user);
+
+        Ok(())
+    }
+
+    pub fn set_pattern_config(
+        ctx: Context<SetPatternConfig>,
+        pattern_id: u8,
+        pattern_type: PatternType,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Owner,
+            &ctx.accounts.admin.key(),
+        )?;
+
+        let config = &mut ctx.accounts.pattern_config;
+        config.version = PatternConfig::VERSION;
+        config.market = ctx.accounts.market.key();
+        config.pattern_id = pattern_id;
+        config.pattern_type = pattern_type;
+        config.is_active = true;
+
+        Ok(())
+    }
+
+    /// Place one stake across 2-8 legs, each a `Selection` on a different
+    /// round of any market. Pass every leg's market and round as remaining
+    /// accounts, in leg order. The stake goes to `market`'s vault, which
+    /// pays the parlay if no leg loses.
+    pub fn place_parlay<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PlaceParlay<'info>>,
+        id: u64,
+        selections: Vec<Selection>,
+        stake: u64,
+        min_odds_bps: u32,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_BETTING)?;
+        require!(
+            (MIN_PARLAY_LEGS..=MAX_PARLAY_LEGS).contains(&selections.len())
+                && ctx.remaining_accounts.len() == 2 * selections.len(),
+            ErrorCode::InvalidParlayLegs
+        );
+        check_stake(&ctx.accounts.params.current, stake)?;
+        require_keys_eq!(
+            ctx.accounts.market.mint,
+            ctx.accounts.mint.key(),
+            ErrorCode::Unauthorized
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut legs: Vec<ParlayLeg> = Vec::with_capacity(selections.len());
+        for (selection, pair) in selections.iter().zip(ctx.remaining_accounts.chunks(2)) {
+            let market: Account<'info, Market> = Account::try_from(&pair[0])?;
+            let mut round: Account<'info, Round> = Account::try_from(&pair[1])?;
+            require!(pair[1].is_writable, ErrorCode::InvalidRoundAccount);
+            let expected = Pubkey::create_program_address(
+                &[
+                    ROUND_SEED,
+                    market.key().as_ref(),
+                    &round.number.to_le_bytes(),
+                    &[round.bump],
+                ],
+                ctx.program_id,
+            )
+            .map_err(|_| ErrorCode::InvalidRoundAccount)?;
+            require_keys_eq!(expected, round.key(), ErrorCode::InvalidRoundAccount);
+            require!(
+                legs.iter().all(|leg| leg.round != round.key()),
+                ErrorCode::InvalidParlayLegs
+            );
+            check_parlay_leg(&market, &round, &ctx.accounts.market.mint, now)?;
+            validate_selection(selection, market.market_type)?;
+            legs.push(ParlayLeg {
+                round: round.key(),
+                selection: *selection,
+                odds_bps: compute_odds_bps(selection, market.market_type, 0)?,
+                status: LegStatus::Pending as u8,
+            });
+            // The leg keeps its round open until `settle_parlay_leg` runs.
+            if round.open_bets != ROUND_BETS_UNTRACKED {
+                round.open_bets = round.open_bets.saturating_add(1);
+            }
+            round.exit(ctx.program_id)?;
+        }
+        let house_edge_bps = ctx.accounts.params.current.house_edge_bps;
+        let odds_bps = parlay_odds(&legs, house_edge_bps)?;
+        require!(odds_bps > ODDS_ONE, ErrorCode::OddsTooLow);
+        check_slippage(odds_bps, min_odds_bps, stake, None)?;
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_token.to_account_info(),
+            to: ctx.accounts.vault_token.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer_checked(cpi_ctx, stake, ctx.accounts.mint.decimals)?;
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.parlay_liability = treasury
+            .parlay_liability
+            .checked_add(compute_payout(stake, odds_bps)?)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let parlay = &mut ctx.accounts.parlay;
+        parlay.version = Parlay::VERSION;
+        parlay.user = ctx.accounts.user.key();
+        parlay.market = ctx.accounts.market.key();
+        parlay.id = id;
+        parlay.stake = stake;
+        parlay.odds_bps = odds_bps;
+        parlay.house_edge_bps = house_edge_bps;
+        parlay.legs = legs;
+        parlay.status = ParlayStatus::Open as u8;
+        parlay.payout = 0;
+        parlay.paid_out = false;
+        parlay.placed_at = now;
+        parlay.rent_payer = ctx.accounts.user.key();
+        parlay.bump = ctx.bumps.parlay;
+        Ok(())
+    }
+
+    /// Resolve the parlay's leg on `round` once that round is revealed or
+    /// cancelled. Permissionless; a losing leg settles the parlay at once.
+    /// The legs left on a lost parlay still need resolving to release their
+    /// rounds.
+    pub fn settle_parlay_leg(ctx: Context<SettleParlayLeg>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        let round_key = ctx.accounts.round.key();
+        let parlay = &mut ctx.accounts.parlay;
+        let was_open = parlay.status == ParlayStatus::Open as u8;
+        apply_leg_result(parlay, &round_key, &ctx.accounts.round)?;
+
+        let round = &mut ctx.accounts.round;
+        if round.open_bets != ROUND_BETS_UNTRACKED {
+            round.open_bets = round.open_bets.saturating_sub(1);
+        }
+        if !was_open {
+            return Ok(());
+        }
+        if parlay.status == ParlayStatus::Lost as u8 {
+            let treasury = &mut ctx.accounts.treasury;
+            treasury.parlay_liability = treasury
+                .parlay_liability
+                .saturating_sub(compute_payout(parlay.stake, parlay.odds_bps)?);
+        }
+        if parlay.status != ParlayStatus::Open as u8 {
+            emit!(ParlayResolved {
+                parlay: parlay.key(),
+                user: parlay.user,
+                status: parlay.status,
+                payout: parlay.payout,
+            });
+        }
+        Ok(())
+    }
+
+    /// Pay a won or refunded parlay to the bettor's token account.
+    /// Permissionless, like `claim_bet`.
+    pub fn claim_parlay(ctx: Context<ClaimParlay>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_keys_eq!(
+            ctx.accounts.market.mint,
+            ctx.accounts.mint.key(),
+            ErrorCode::Unauthorized
+        );
+        let parlay = &ctx.accounts.parlay;
+        require!(
+            parlay.status == ParlayStatus::Won as u8
+                || parlay.status == ParlayStatus::Refunded as u8,
+            ErrorCode::ParlayNotResolved
+        );
+        require!(!parlay.paid_out, ErrorCode::AlreadyPaidOut);
+
+        let market_key = ctx.accounts.market.key();
+        pay_from_vault(
+            &market_key,
+            ctx.accounts.market.vault_bump,
+            &ctx.accounts.vault_authority,
+            &ctx.accounts.vault_token,
+            &ctx.accounts.user_token,
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+            parlay.payout,
+        )?;
+
+        let max_payout = compute_payout(parlay.stake, parlay.odds_bps)?;
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.parlay_liability = treasury.parlay_liability.saturating_sub(max_payout);
+        ctx.accounts.parlay.paid_out = true;
+        Ok(())
+    }
+
+    /// Close a resolved parlay once it has nothing left to pay.
+    pub fn close_parlay(ctx: Context<CloseParlay>) -> Result<()> {
+        require_player_or_operator(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            &ctx.accounts.parlay.user,
+            &ctx.accounts.closer.key(),
+        )?;
+        let parlay = &ctx.accounts.parlay;
+        require!(
+            parlay.status != ParlayStatus::Open as u8,
+            ErrorCode::ParlayNotResolved
+        );
+        require!(parlay.paid_out, ErrorCode::PayoutNotCollected);
+        require!(
+            parlay
+                .legs
+                .iter()
+                .all(|l| l.status != LegStatus::Pending as u8),
+            ErrorCode::ParlayLegsPending
+        );
+        Ok(())
+    }
+
+    /// Withdraw a vault-funded bet while its round still takes bets. The
+    /// stake is refunded minus the round's `cash_out_fee_bps`, which stays
+    /// in the vault, and the bet is closed back to whoever paid its rent.
+    pub fn cash_out(ctx: Context<CashOut>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_PAYOUT)?;
+        require_keys_eq!(
+            ctx.accounts.market.mint,
+            ctx.accounts.mint.key(),
+            ErrorCode::Unauthorized
+        );
+        let bet = &ctx.accounts.bet;
+        require!(!bet.funded_from_balance, ErrorCode::BalanceFundedBet);
+        let round = &ctx.accounts.round;
+        check_cash_out(round, Clock::get()?.unix_timestamp)?;
+
+        let fee = cash_out_fee(bet.stake, round.params.cash_out_fee_bps)?;
+        let refund = bet.stake - fee;
+        unrecord_bet(bet, &mut ctx.accounts.round)?;
+
+        if refund > 0 {
+            let market_key = ctx.accounts.market.key();
+            pay_from_vault(
+                &market_key,
+                ctx.accounts.market.vault_bump,
+                &ctx.accounts.vault_authority,
+                &ctx.accounts.vault_token,
+                &ctx.accounts.user_token,
+                &ctx.accounts.mint,
+                &ctx.accounts.token_program,
+                refund,
+            )?;
+        }
+        emit!(BetCashedOut {
+            bet: ctx.accounts.bet.key(),
+            user: ctx.accounts.bet.user,
+            round: ctx.accounts.round.key(),
+            refund,
+            fee,
+        });
+        Ok(())
+    }
+
+    /// Close a settled, paid-out bet and return its rent to whoever funded it.
+    pub fn close_bet(ctx: Context<CloseBet>) -> Result<()> {
+        require_player_or_operator(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            &ctx.accounts.bet.user,
+            &ctx.accounts.closer.key(),
+        )?;
+        let bet = &ctx.accounts.bet;
+        require!(bet.settled, ErrorCode::BetNotSettled);
+        require!(bet.paid_out, ErrorCode::PayoutNotCollected);
+
+        let round = &mut ctx.accounts.round;
+        if round.open_bets != ROUND_BETS_UNTRACKED {
+            round.open_bets = round.open_bets.saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    /// Close a round once every bet and community entry on it is closed and
+    /// the treasury cursor has moved past it, so liability scans never need
+    /// it again.
+    pub fn close_round(ctx: Context<CloseRound>) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+        let round = &ctx.accounts.round;
+        require!(
+            round.status == RoundStatus::Settled as u8
+                || round.status == RoundStatus::Cancelled as u8,
+            ErrorCode::InvalidState
+        );
+        require!(
+            round.number <= ctx.accounts.treasury.settled_through,
+            ErrorCode::RoundNotClosedOut
+        );
+        require!(round.open_bets == 0, ErrorCode::BetsStillOpen);
+        Ok(())
+    }
+
+    pub fn close_community_entry(ctx: Context<CloseCommunityEntry>) -> Result<()> {
+        require_player_or_operator(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            &ctx.accounts.community_entry.user,
+            &ctx.accounts.closer.key(),
+        )?;
+        require!(
+            ctx.accounts.community_entry.distance.is_some(),
+            ErrorCode::BetNotSettled
+        );
+
+        let round = &mut ctx.accounts.round;
+        if round.open_bets != ROUND_BETS_UNTRACKED {
+            round.open_bets = round.open_bets.saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    /// Close a streak that failed or has been claimed; it no longer counts
+    /// towards treasury liabilities.
+    pub fn close_streak(ctx: Context<CloseStreak>) -> Result<()> {
+        require_player_or_operator(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            &ctx.accounts.streak.user,
+            &ctx.accounts.closer.key(),
+        )?;
+        let status = ctx.accounts.streak.status;
+        require!(
+            status == StreakStatus::Failed as u8 || status == StreakStatus::Claimed as u8,
+            ErrorCode::StreakStillOpen
+        );
+        Ok(())
+    }
+
+    /// Close a round's permission group once the round has settled (or has
+    /// already been closed).
+    pub fn close_permission_group(ctx: Context<ClosePermissionGroup>) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+        let rent_payer = ctx.accounts.permission_group.rent_payer;
+        if rent_payer != Pubkey::default() {
+            require_keys_eq!(
+                ctx.accounts.rent_payer.key(),
+                rent_payer,
+                ErrorCode::InvalidRentPayer
+            );
+        }
+
+        let round_info = ctx.accounts.round.to_account_info();
+        if !round_info.data_is_empty() {
+            require_keys_eq!(*round_info.owner, crate::ID, ErrorCode::InvalidRoundAccount);
+            let round = Round::try_deserialize(&mut &round_info.try_borrow_data()?[..])?;
+            require_keys_eq!(
+                round.market,
+                ctx.accounts.market.key(),
+                ErrorCode::InvalidRoundAccount
+            );
+            require!(
+                round.status == RoundStatus::Settled as u8,
+                ErrorCode::InvalidState
+            );
+        }
+        Ok(())
+    }
+
+    pub fn create_round_permission_group(
+        ctx: Context<CreateRoundPermissionGroup>,
+        allowed_viewers: Vec<Pubkey>,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+
+        require!(
+            allowed_viewers.len() <= MAX_VIEWERS,
+            ErrorCode::MaxViewersReached
+        );
+        let permission_group = &mut ctx.accounts.permission_group;
+        permission_group.version = PermissionGroup::VERSION;
+        permission_group.round = ctx.accounts.round.key();
+        permission_group.allowed_viewers = allowed_viewers;
+        permission_group.is_private = true;
+        permission_group.rent_payer = ctx.accounts.admin.key();
+
+        Ok(())
+    }
+
+    pub fn add_viewer_to_permission_group(
+        ctx: Context<UpdatePermissionGroup>,
+        viewer: Pubkey,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+
+        let permission_group = &mut ctx.accounts.permission_group;
+        require!(
+            !permission_group.allowed_viewers.contains(&viewer),
+            ErrorCode::ViewerAlreadyExists
+        );
+        require!(
+            permission_group.allowed_viewers.len() < MAX_VIEWERS,
+            ErrorCode::MaxViewersReached
+        );
+
+        permission_group.allowed_viewers.push(viewer);
+
+        Ok(())
+    }
+
+    pub fn remove_viewer_from_permission_group(
+        ctx: Context<UpdatePermissionGroup>,
+        viewer: Pubkey,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            Role::Operator,
+            &ctx.accounts.admin.key(),
+        )?;
+
+        let permission_group = &mut ctx.accounts.permission_group;
+        permission_group.allowed_viewers.retain(|&v| v != viewer);
+
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum MarketType {
+    PickRange,
+    EvenOdd,
+    LastDigit,
+    ModuloThree,
+    PatternOfDay,
+    ShapeColor,
+    Jackpot,
+    EntropyBattle,
+    StreakMeter,
+    CommunitySeed,
+    /// European wheel: pockets 0 through 36.
+    Roulette,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum OutcomeType {
+    Pending,
+    Numeric {
+        value: u16,
+    },
+    Shape {
+        shape: u8,
+        color: u8,
+        size: u8,
+    },
+    Pattern {
+        pattern_id: u8,
+        matched_value: u16,
+    },
+    Entropy {
+        tee_score: u16,
+        chain_score: u16,
+        sensor_score: u16,
+        winner: u8,
+    },
+    Community {
+        final_byte: u8,
+        seed_hash: [u8; 32],
+    },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionKind {
+    Range = 0,
+    Single = 1,
+    Parity = 2,
+    Digit = 3,
+    Modulo = 4,
+    Pattern = 5,
+    Shape = 6,
+    Entropy = 7,
+    Streak = 8,
+    Community = 9,
+    /// One pocket `a`, 0-36.
+    RouletteStraight = 10,
+    /// Two adjacent pockets `a < b`, including 0 with 1, 2 or 3.
+    RouletteSplit = 11,
+    /// The row of three starting at `a` (1, 4, ..., 34).
+    RouletteStreet = 12,
+    /// The block of four whose lowest pocket is `a`.
+    RouletteCorner = 13,
+    /// 1-12, 13-24 or 25-36 for `a` = 0, 1, 2.
+    RouletteDozen = 14,
+    /// The column holding pocket `a + 1`.
+    RouletteColumn = 15,
+    /// 0 = red, 1 = black.
+    RouletteColor = 16,
+    /// 0 = low (1-18), 1 = high (19-36).
+    RouletteHighLow = 17,
+    /// 0 = even, 1 = odd. Zero is neither.
+    RouletteParity = 18,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct Selection {
+    pub kind: u8,
+    pub a: u16,
+    pub b: u16,
+    pub c: u16,
+}
+
+/// Total staked on one selection of a pari-mutuel round.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct PoolBucket {
+    pub selection: Selection,
+    pub staked: u64,
+}
+
+/// One selection of a parlay.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct ParlayLeg {
+    pub round: Pubkey,
+    pub selection: Selection,
+    /// Fair odds of the leg on its own, before any house edge.
+    pub odds_bps: u32,
+    pub status: u8,
+}
+
+/// Stakes and potential payouts booked on one selection of a dynamic-odds
+/// round.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct Exposure {
+    pub selection: Selection,
+    pub staked: u64,
+    pub liability: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Owner,
+    Operator,
+    Oracle,
+    Treasurer,
+    Guardian,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RoundStatus {
+    Predicting = 0,
+    Locked = 1,
+    Settled = 2,
+    Cancelled = 3,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ParlayStatus {
+    Open = 0,
+    Won = 1,
+    Lost = 2,
+    /// Every leg was voided; the stake is returned.
+    Refunded = 3,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum LegStatus {
+    Pending = 0,
+    Won = 1,
+    Lost = 2,
+    /// The leg's round was cancelled; it drops out of the combined odds.
+    Void = 3,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DelegationStatus {
+    Undelegated = 0,
+    Delegated = 1,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BettingMode {
+    /// Each bet is paid at the odds quoted when it was placed.
+    FixedOdds = 0,
+    /// Stakes are pooled per selection and winners split the losing pools.
+    PariMutuel = 1,
+    /// Fixed odds at placement, shaded by the round's exposure on the pick.
+    DynamicOdds = 2,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SettlementMode {
+    Base = 0,
+    Rollup = 1,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum StreakStatus {
+    Active = 0,
+    Completed = 1,
+    Failed = 2,
+    Claimed = 3,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum PatternType {
+    Prime,
+    Fibonacci,
+    PerfectSquare,
+    EndsWithSeven,
+    Palindrome,
+    Even,
+    Odd,
+}
+
+/// Market parameters a round snapshots when it opens.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, InitSpace)]
+pub struct ParamValues {
+    pub house_edge_bps: u16,
+    pub min_stake: u64,
+    pub max_stake: u64,
+    pub max_predicting_duration: i64,
+    pub min_lock_duration: i64,
+    /// All zero until configured; a market needs valid bounds to run
+    /// `BettingMode::DynamicOdds`.
+    pub dynamic_odds: DynamicOdds,
+    /// Share of the stake kept by the house on `cash_out`; zero disables it.
+    pub cash_out_fee_bps: u16,
+}
+
+/// Bounds for dynamic odds, as multiples of the fixed-odds quote in basis
+/// points (`ODDS_ONE` = unchanged).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, InitSpace)]
+pub struct DynamicOdds {
+    pub min_bps: u32,
+    pub max_bps: u32,
+    /// Stake-equivalent added to both sides of the book before pricing.
+    pub depth: u64,
+}
+
+impl DynamicOdds {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.min_bps > 0
+                && self.min_bps <= ODDS_ONE
+                && self.max_bps >= ODDS_ONE
+                && self.max_bps <= MAX_DYNAMIC_ODDS_BPS,
+            ErrorCode::ParamOutOfBounds
+        );
+        require!(self.depth > 0, ErrorCode::ParamOutOfBounds);
+        Ok(())
+    }
+}
+
+impl ParamValues {
+    pub fn with_house_edge(house_edge_bps: u16) -> Self {
+        Self {
+            house_edge_bps,
+            min_stake: 1,
+            max_stake: u64::MAX,
+            max_predicting_duration: MAX_PREDICTING_DURATION,
+            min_lock_duration: MIN_LOCK_DURATION,
+            dynamic_odds: DynamicOdds::default(),
+            cash_out_fee_bps: DEFAULT_CASH_OUT_FEE_BPS,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.house_edge_bps <= MAX_HOUSE_EDGE_BPS,
+            ErrorCode::ParamOutOfBounds
+        );
+        require!(
+            self.min_stake > 0 && self.min_stake <= self.max_stake,
+            ErrorCode::ParamOutOfBounds
+        );
+        require!(
+            self.max_predicting_duration > 0
+                && self.max_predicting_duration <= MAX_PREDICTING_DURATION_CAP,
+            ErrorCode::ParamOutOfBounds
+        );
+        require!(
+            (0..=MAX_MIN_LOCK_DURATION).contains(&self.min_lock_duration),
+            ErrorCode::ParamOutOfBounds
+        );
+        if self.dynamic_odds != DynamicOdds::default() {
+            self.dynamic_odds.validate()?;
+        }
+        require!(
+            self.cash_out_fee_bps <= MAX_CASH_OUT_FEE_BPS,
+            ErrorCode::ParamOutOfBounds
+        );
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParamChange {
+    pub house_edge_bps: Option<u16>,
+    pub min_stake: Option<u64>,
+    pub max_stake: Option<u64>,
+    pub max_predicting_duration: Option<i64>,
+    pub min_lock_duration: Option<i64>,
+    pub dynamic_odds: Option<DynamicOdds>,
+    pub cash_out_fee_bps: Option<u16>,
+}
+
+impl ParamChange {
+    pub fn apply_to(&self, current: &ParamValues) -> ParamValues {
+        ParamValues {
+            house_edge_bps: self.house_edge_bps.unwrap_or(current.house_edge_bps),
+            min_stake: self.min_stake.unwrap_or(current.min_stake),
+            max_stake: self.max_stake.unwrap_or(current.max_stake),
+            max_predicting_duration: self
+                .max_predicting_duration
+                .unwrap_or(current.max_predicting_duration),
+            min_lock_duration: self.min_lock_duration.unwrap_or(current.min_lock_duration),
+            dynamic_odds: self.dynamic_odds.unwrap_or(current.dynamic_odds),
+            cash_out_fee_bps: self.cash_out_fee_bps.unwrap_or(current.cash_out_fee_bps),
+        }
+    }
+}
+
+/// Layout version stored as the first field of every program account. Bump
+/// it together with a `migrate_*` arm when an account gains fields.
+pub trait Versioned {
+    const VERSION: u8;
+
+    /// Fill the fields added after version `from` for the account at `key`.
+    /// Migrated bytes leave them zeroed or stale, so every new field must be
+    /// written here.
+    fn upgrade_from(&mut self, _from: u8, _key: &Pubkey) {}
+}
+
+impl Versioned for MarketParams {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for Config {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for Market {
+    const VERSION: u8 = 1;
+
+    fn upgrade_from(&mut self, from: u8, key: &Pubkey) {
+        if from == 0 {
+            self.vault_bump =
+                Pubkey::find_program_address(&[VAULT_SEED, key.as_ref()], &crate::ID).1;
+            self.balance_vault_bump =
+                Pubkey::find_program_address(&[BALANCE_VAULT_SEED, self.mint.as_ref()], &crate::ID)
+                    .1;
+        }
+    }
+}
+
+impl Versioned for Round {
+    const VERSION: u8 = 1;
+
+    /// v0 rounds read the stake and timing limits the program used to
+    /// hard-code. Their house edge lived on the market and is not known
+    /// here, so a round still taking bets closes betting and can be locked.
+    fn upgrade_from(&mut self, from: u8, _key: &Pubkey) {
+        if from == 0 {
+            self.params = ParamValues {
+                cash_out_fee_bps: 0,
+                ..ParamValues::with_house_edge(0)
+            };
+            self.params_revision = 0;
+            if self.status == RoundStatus::Predicting as u8 {
+                self.lock_scheduled_at = self.opened_at.max(1);
+            }
+            self.open_bets = ROUND_BETS_UNTRACKED;
+            self.rent_payer = Pubkey::default();
+            self.bump = Pubkey::find_program_address(
+                &[ROUND_SEED, self.market.as_ref(), &self.number.to_le_bytes()],
+                &crate::ID,
+            )
+            .1;
+        }
+    }
+}
+
+impl Versioned for Bet {
+    const VERSION: u8 = 1;
+
+    fn upgrade_from(&mut self, from: u8, _key: &Pubkey) {
+        if from == 0 {
+            self.rent_payer = self.user;
+            self.bump = Pubkey::find_program_address(
+                &[BET_SEED, self.round.as_ref(), self.user.as_ref()],
+                &crate::ID,
+            )
+            .1;
+        }
+    }
+}
+
+impl Versioned for MarketLedger {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for PlayerBalance {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for SessionToken {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for Treasury {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for Parlay {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for LiquidityPool {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for LpWithdrawal {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for Streak {
+    const VERSION: u8 = 1;
+
+    fn upgrade_from(&mut self, from: u8, _key: &Pubkey) {
+        if from == 0 {
+            self.rent_payer = self.user;
+        }
+    }
+}
+
+impl Versioned for CommunityEntry {
+    const VERSION: u8 = 1;
+
+    fn upgrade_from(&mut self, from: u8, _key: &Pubkey) {
+        if from == 0 {
+            self.rent_payer = self.user;
+        }
+    }
+}
+
+impl Versioned for JackpotPot {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for PatternConfig {
+    const VERSION: u8 = 1;
+}
+
+impl Versioned for PermissionGroup {
+    const VERSION: u8 = 1;
+
+    fn upgrade_from(&mut self, from: u8, _key: &Pubkey) {
+        // The operator that paid is unknown; any operator may reclaim it.
+        if from == 0 {
+            self.rent_payer = Pubkey::default();
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MarketParams {
+    pub version: u8,
+    pub market: Pubkey,
+    pub current: ParamValues,
+    pub pending: ParamValues,
+    pub has_pending: bool,
+    pub pending_eta: i64,
+    pub proposed_at: i64,
+    pub revision: u32,
+    pub updated_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub pending_owner: Pubkey,
+    pub operator: Pubkey,
+    pub oracle: Pubkey,
+    pub treasurer: Pubkey,
+    pub guardian: Pubkey,
+    /// Bitmask of `PAUSE_*` flags set by the guardian during an incident.
+    pub pause_flags: u8,
+}
+
+impl Config {
+    pub fn role_key(&self, role: Role) -> Pubkey {
+        match role {
+            Role::Owner => self.owner,
+            Role::Operator => self.operator,
+            Role::Oracle => self.oracle,
+            Role::Treasurer => self.treasurer,
+            Role::Guardian => self.guardian,
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Market {
+    pub version: u8,
+    /// Governing authority: the `Config` PDA, or the creating key for a
+    /// legacy market that has not been through `migrate_market` yet.
+    pub admin: Pubkey,
+    #[max_len(MAX_MARKET_NAME_LEN)]
+    pub name: String,
+    pub is_active: bool,
+    pub last_round: u64,
+    pub house_edge_bps: u16,
+    pub mint: Pubkey,
+    pub market_type: MarketType,
+    pub index: u16,
+    /// Bump of the `[VAULT_SEED, market]` vault authority PDA.
+    pub vault_bump: u8,
+    /// Bump of the `[BALANCE_VAULT_SEED, mint]` balance vault authority PDA.
+    pub balance_vault_bump: u8,
+    /// `BettingMode` new rounds open with.
+    pub betting_mode: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Round {
+    pub version: u8,
+    pub market: Pubkey,
+    pub number: u64,
+    pub status: u8,
+    pub inputs_hash: [u8; 32],
+    pub outcome: OutcomeType,
+    pub unsettled_bets: u32,
+    pub opened_at: i64,
+    pub lock_scheduled_at: i64,
+    pub locked_at: i64,
+    pub commitment_hash: Option<[u8; 32]>,
+    pub revealed_at: i64,
+    pub player_entropy: [u8; 32],
+    pub entropy_contributions: u32,
+    pub vrf_seed: [u8; 32],
+    pub vrf_requested_at: i64,
+    pub vrf_fulfilled: bool,
+    pub delegation_status: u8,
+    pub settlement_mode: u8,
+    pub balance_staked: u64,
+    pub balance_credited: u64,
+    pub balances_synced: bool,
+    pub open_liability: u64,
+    pub params: ParamValues,
+    pub params_revision: u32,
+    /// Bets and community entries not yet closed.
+    pub open_bets: u32,
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+    /// `BettingMode` snapshot from the market when the round opened.
+    pub betting_mode: u8,
+    /// Stakes per selection; only used in pari-mutuel rounds.
+    #[max_len(MAX_POOL_BUCKETS)]
+    pub pools: Vec<PoolBucket>,
+    /// Per-selection exposure; only used in dynamic-odds rounds.
+    #[max_len(MAX_POOL_BUCKETS)]
+    pub exposures: Vec<Exposure>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Bet {
+    pub version: u8,
+    pub user: Pubkey,
+    pub round: Pubkey,
+    pub stake: u64,
+    pub selection: Selection,
+    /// Payout multiplier in basis points (`ODDS_ONE` = 1x).
+    pub odds_bps: u32,
+    pub settled: bool,
+    pub won: bool,
+    pub payout: u64,
+    pub placed_at: i64,
+    pub paid_out: bool,
+    pub funded_from_balance: bool,
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Parlay {
+    pub version: u8,
+    pub user: Pubkey,
+    /// Market whose vault holds the stake and pays the parlay.
+    pub market: Pubkey,
+    pub id: u64,
+    pub stake: u64,
+    /// Combined odds at placement: the legs' fair odds multiplied together
+    /// with the house edge applied once. Voided legs can only lower them.
+    pub odds_bps: u32,
+    pub house_edge_bps: u16,
+    #[max_len(MAX_PARLAY_LEGS)]
+    pub legs: Vec<ParlayLeg>,
+    pub status: u8,
+    pub payout: u64,
+    pub paid_out: bool,
+    pub placed_at: i64,
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+/// Bet layout with percent odds, read only by `migrate_bet`.
+#[derive(AnchorDeserialize)]
+struct LegacyBet {
+    user: Pubkey,
+    round: Pubkey,
+    stake: u64,
+    selection: Selection,
+    odds_pct: u16,
+    settled: bool,
+    won: bool,
+    payout: u64,
+    placed_at: i64,
+}
+
+impl LegacyBet {
+    /// The same bet with basis-point odds, as a v0 body upgraded for `key`.
+    fn upgrade(self, key: &Pubkey) -> Bet {
+        let mut bet = Bet {
+            version: Bet::VERSION,
+            user: self.user,
+            round: self.round,
+            stake: self.stake,
+            selection: self.selection,
+            odds_bps: self.odds_pct as u32 * (ODDS_ONE / 100),
+            settled: self.settled,
+            won: self.won,
+            payout: self.payout,
+            placed_at: self.placed_at,
+            // Base-layer settlement paid winners in the same instruction.
+            paid_out: self.settled,
+            funded_from_balance: false,
+            rent_payer: Pubkey::default(),
+            bump: 0,
+        };
+        bet.upgrade_from(0, key);
+        bet
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MarketLedger {
+    pub version: u8,
+    pub market: Pubkey,
+    /// Winnings of ER-settled bets, owed to the balance vault.
+    pub total_credited: u64,
+    pub settled_bets: u64,
+    pub delegation_status: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PlayerBalance {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub balance: u64,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub delegation_status: u8,
+    pub bump: u8,
+    /// Bump of the `[BALANCE_VAULT_SEED, mint]` balance vault authority PDA.
+    pub vault_bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct SessionToken {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub session_signer: Pubkey,
+    pub mint: Pubkey,
+    pub spend_cap: u64,
+    pub spent: u64,
+    pub expires_at: i64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    pub version: u8,
+    pub market: Pubkey,
+    pub settled_through: u64,
+    pub streak_liability: u64,
+    pub total_withdrawn: u64,
+    pub lp_enabled: bool,
+    /// Paid from the vault to whoever locks a round after its scheduled time.
+    pub crank_reward: u64,
+    /// Maximum payout of every parlay funded by this market's vault that is
+    /// not yet lost or paid.
+    pub parlay_liability: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LiquidityPool {
+    pub version: u8,
+    pub market: Pubkey,
+    pub share_mint: Pubkey,
+    pub cooldown_seconds: i64,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LpWithdrawal {
+    pub version: u8,
+    pub provider: Pubkey,
+    pub pool: Pubkey,
+    pub shares: u64,
+    pub requested_at: i64,
+}
+
+#[event]
+pub struct LiquidityDeposited {
+    pub market: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub equity: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidityWithdrawn {
+    pub market: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub equity: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BetsBatchSettled {
+    pub round: Pubkey,
+    pub settled: u32,
+    pub total_payout: u64,
+}
+
+#[event]
+pub struct BetClaimed {
+    pub bet: Pubkey,
+    pub user: Pubkey,
+    pub claimer: Pubkey,
+    pub won: bool,
+    pub payout: u64,
+}
+
+#[event]
+pub struct BetCashedOut {
+    pub bet: Pubkey,
+    pub user: Pubkey,
+    pub round: Pubkey,
+    pub refund: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct RoundCancelled {
+    pub round: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ParlayResolved {
+    pub parlay: Pubkey,
+    pub user: Pubkey,
+    pub status: u8,
+    pub payout: u64,
+}
+
+#[event]
+pub struct RoundLockCranked {
+    pub round: Pubkey,
+    pub cranker: Pubkey,
+    pub reward: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseFlagsUpdated {
+    pub authority: Pubkey,
+    pub flags: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct HouseFundsWithdrawn {
+    pub market: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub vault_balance: u64,
+    pub liabilities: u64,
+    pub total_withdrawn: u64,
+    pub timestamp: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Streak {
+    pub version: u8,
+    pub user: Pubkey,
+    pub market: Pubkey,
+    pub target: u16,
+    pub current_streak: u16,
+    pub status: u8,
+    pub last_round: Pubkey,
+    pub rent_payer: Pubkey,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct CommunityEntry {
+    pub version: u8,
+    pub user: Pubkey,
+    pub round: Pubkey,
+    pub seed_byte: u8,
+    pub distance: Option<u8>,
+    pub won: bool,
+    pub rent_payer: Pubkey,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct JackpotPot {
+    pub version: u8,
+    pub market: Pubkey,
+    pub current_pot: u64,
+    pub last_winner: Option<Pubkey>,
+    pub total_contributed: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PatternConfig {
+    pub version: u8,
+    pub market: Pubkey,
+    pub pattern_id: u8,
+    pub pattern_type: PatternType,
+    pub is_active: bool,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PermissionGroup {
+    pub version: u8,
+    pub round: Pubkey,
+    #[max_len(MAX_VIEWERS)]
+    pub allowed_viewers: Vec<Pubkey>,
+    pub is_private: bool,
+    /// `Pubkey::default()` for groups migrated from before it was recorded.
+    pub rent_payer: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct InitConfig<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [CONFIG_SEED],
+        bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::Unauthorized)]
+    pub program: Program<'info, crate::program::TossrEngine>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(payer.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPauseFlags<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOwnership<'info> {
+    pub new_owner: Signer<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMarket<'info> {
+    pub owner: Signer<'info>,
+    pub legacy_admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, legacy_admin.key().as_ref(), &market.index.to_le_bytes()],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Account in an earlier layout; owner, discriminator and size are
+    /// checked by the migration before it is rewritten
+    #[account(mut)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct InitializeMarket<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [MARKET_SEED, &market_index.to_le_bytes()],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + MarketParams::INIT_SPACE,
+        seeds = [PARAMS_SEED, market.key().as_ref()],
+        bump,
+    )]
+    pub params: Account<'info, MarketParams>,
+    pub mint: Account<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ToggleMarket<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct SetBettingMode<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [PARAMS_SEED, market.key().as_ref()], bump)]
+    pub params: Account<'info, MarketParams>,
+}
+
+#[derive(Accounts)]
+pub struct InitMarketParams<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + MarketParams::INIT_SPACE,
+        seeds = [PARAMS_SEED, market.key().as_ref()],
+        bump,
+    )]
+    pub params: Account<'info, MarketParams>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarketParams<'info> {
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [PARAMS_SEED, market.key().as_ref()], bump)]
+    pub params: Account<'info, MarketParams>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteMarketParams<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut, seeds = [PARAMS_SEED, market.key().as_ref()], bump)]
+    pub params: Account<'info, MarketParams>,
+}
+
+#[derive(Accounts)]
+pub struct OpenRound<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Round::INIT_SPACE,
+        seeds = [ROUND_SEED, market.key().as_ref(), &market.last_round.saturating_add(1).to_le_bytes()],
+        bump,
+    )]
+    pub round: Account<'info, Round>,
+    #[account(seeds = [PARAMS_SEED, market.key().as_ref()], bump)]
+    pub params: Account<'info, MarketParams>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ScheduleLock<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRound<'info> {
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+}
+
+#[derive(Accounts)]
+pub struct LockRound<'info> {
+    /// The operator, or any cranker once the scheduled lock time has passed.
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    // Crank reward accounts; omit them to lock without collecting a reward
+    // (and inside the ER, where the vault is not available).
+    #[account(seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: Option<AccountInfo<'info>>,
+    #[account(mut)]
+    pub vault_token: Option<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub cranker_token: Option<Account<'info, TokenAccount>>,
+    pub mint: Option<Account<'info, Mint>>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct SetCrankReward<'info> {
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [PARAMS_SEED, market.key().as_ref()], bump)]
+    pub params: Account<'info, MarketParams>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[derive(Accounts)]
+pub struct CommitOutcome<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+}
+
+#[derive(Accounts)]
+pub struct RevealOutcome<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+}
+
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateRound<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut, del)]
+    /// CHECK: Delegated PDA validated by the delegation program (`del` macro applies checks)
+    pub pda: AccountInfo<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+}
+
+#[commit]
+#[derive(Accounts)]
+pub struct RoundCommitCtx<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+}
+
+#[derive(Accounts)]
+pub struct UndelegateRound<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Delegated PDA being undelegated via CPI; validated by delegation program
+    pub pda: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: Delegation buffer account used by delegation program CPI
+    pub delegation_buffer: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleRound<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBet<'info> {
+    /// The bettor, or a session signer acting for `user`.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Bet owner; must equal `payer` unless a valid `session_token` is supplied
+    pub user: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [SESSION_SEED, user.key().as_ref(), mint.key().as_ref()],
+        bump = session_token.bump,
+    )]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [BET_SEED, round.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateSession<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + SessionToken::INIT_SPACE,
+        seeds = [SESSION_SEED, owner.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = owner,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSession<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [SESSION_SEED, owner.key().as_ref(), mint.key().as_ref()],
+        bump = session_token.bump,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = owner,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBetFromBalance<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [BET_SEED, round.key().as_ref(), payer.key().as_ref()],
+        bump,
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        seeds = [PLAYER_BALANCE_SEED, payer.key().as_ref(), market.mint.as_ref()],
+        bump = player_balance.bump,
+    )]
+    pub player_balance: Account<'info, PlayerBalance>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleBet<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(mut, seeds = [BET_SEED, round.key().as_ref(), bet.user.as_ref()], bump = bet.bump)]
+    pub bet: Account<'info, Bet>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    #[account(address = bet.user)]
+    /// CHECK: Address constraint ensures this is the bet.user
+    pub user: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleBetsBatch<'info> {
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBet<'info> {
+    /// The bettor or any cranker; pays for the bettor's token account if needed.
+    #[account(mut)]
+    pub claimer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(mut, seeds = [BET_SEED, round.key().as_ref(), bet.user.as_ref()], bump = bet.bump)]
+    pub bet: Account<'info, Bet>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = claimer,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    #[account(address = bet.user)]
+    /// CHECK: Address constraint ensures this is the bet.user
+    pub user: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CashOut<'info> {
+    #[account(address = bet.user @ ErrorCode::Unauthorized)]
+    pub user: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(
+        mut,
+        seeds = [BET_SEED, round.key().as_ref(), bet.user.as_ref()],
+        bump = bet.bump,
+        close = rent_payer,
+    )]
+    pub bet: Account<'info, Bet>,
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut, address = bet.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitMarketLedger<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + MarketLedger::INIT_SPACE,
+        seeds = [LEDGER_SEED, market.key().as_ref()],
+        bump,
+    )]
+    pub ledger: Account<'info, MarketLedger>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EnableErSettlement<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+}
+
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateBet<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut, del)]
+    /// CHECK: Delegated PDA validated by the delegation program (`del` macro applies checks)
+    pub pda: AccountInfo<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(seeds = [BET_SEED, round.key().as_ref(), bet.user.as_ref()], bump = bet.bump)]
+    pub bet: Account<'info, Bet>,
+}
+
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateMarketLedger<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut, del)]
+    /// CHECK: Delegated PDA validated by the delegation program (`del` macro applies checks)
+    pub pda: AccountInfo<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [LEDGER_SEED, market.key().as_ref()], bump)]
+    pub ledger: Account<'info, MarketLedger>,
+}
+
+#[derive(Accounts)]
+pub struct ErSettleBet<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(mut, seeds = [BET_SEED, round.key().as_ref(), bet.user.as_ref()], bump = bet.bump)]
+    pub bet: Account<'info, Bet>,
+    #[account(mut, seeds = [LEDGER_SEED, market.key().as_ref()], bump)]
+    pub ledger: Account<'info, MarketLedger>,
+}
+
+#[commit]
+#[derive(Accounts)]
+pub struct ErSettlementCommitCtx<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(mut, seeds = [LEDGER_SEED, market.key().as_ref()], bump)]
+    pub ledger: Account<'info, MarketLedger>,
+}
+
+#[commit]
+#[derive(Accounts)]
+pub struct ErBetsCommitCtx<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+}
+
+#[commit]
+#[derive(Accounts)]
+pub struct MarketLedgerCommitCtx<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [LEDGER_SEED, market.key().as_ref()], bump)]
+    pub ledger: Account<'info, MarketLedger>,
+}
+
+#[derive(Accounts)]
+pub struct CollectPayout<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(mut, seeds = [BET_SEED, round.key().as_ref(), bet.user.as_ref()], bump = bet.bump)]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PlayerBalance::INIT_SPACE,
+        seeds = [PLAYER_BALANCE_SEED, bet.user.as_ref(), market.mint.as_ref()],
+        bump,
+    )]
+    pub player_balance: Account<'info, PlayerBalance>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + PlayerBalance::INIT_SPACE,
+        seeds = [PLAYER_BALANCE_SEED, user.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub player_balance: Account<'info, PlayerBalance>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    #[account(seeds = [BALANCE_VAULT_SEED, mint.key().as_ref()], bump)]
+    /// CHECK: Program-derived address used as balance vault authority; seeds verified by Anchor
+    pub balance_vault_authority: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint,
+        associated_token::authority = balance_vault_authority,
+    )]
+    pub balance_vault_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PLAYER_BALANCE_SEED, user.key().as_ref(), mint.key().as_ref()],
+        bump = player_balance.bump,
+    )]
+    pub player_balance: Account<'info, PlayerBalance>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    #[account(seeds = [BALANCE_VAULT_SEED, mint.key().as_ref()], bump = player_balance.vault_bump)]
+    /// CHECK: Program-derived address used as balance vault authority; seeds verified by Anchor
+    pub balance_vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = balance_vault_authority,
+    )]
+    pub balance_vault_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegatePlayerBalance<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut, del)]
+    /// CHECK: Delegated PDA validated by the delegation program (`del` macro applies checks)
+    pub pda: AccountInfo<'info>,
+    #[account(
+        seeds = [PLAYER_BALANCE_SEED, payer.key().as_ref(), player_balance.mint.as_ref()],
+        bump = player_balance.bump,
+    )]
+    pub player_balance: Account<'info, PlayerBalance>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[commit]
+#[derive(Accounts)]
+pub struct PlayerBalanceCommitCtx<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PLAYER_BALANCE_SEED, payer.key().as_ref(), player_balance.mint.as_ref()],
+        bump = player_balance.bump,
+    )]
+    pub player_balance: Account<'info, PlayerBalance>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct SettleBetToBalance<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(mut, seeds = [BET_SEED, round.key().as_ref(), bet.user.as_ref()], bump = bet.bump)]
+    pub bet: Account<'info, Bet>,
+}
+
+#[derive(Accounts)]
+pub struct SyncRoundBalances<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(seeds = [BALANCE_VAULT_SEED, mint.key().as_ref()], bump = market.balance_vault_bump)]
+    /// CHECK: Program-derived address used as balance vault authority; seeds verified by Anchor
+    pub balance_vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = balance_vault_authority,
+    )]
+    pub balance_vault_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitVault<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitTreasury<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED, market.key().as_ref()],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdvanceTreasuryCursor<'info> {
+    pub market: Account<'info, Market>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawHouseFunds<'info> {
+    #[account(mut)]
+    pub treasurer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+    /// CHECK: JackpotPot PDA; may not exist yet, read via `load_optional_pda`
+    pub jackpot_pot: UncheckedAccount<'info>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = treasurer,
+        associated_token::mint = mint,
+        associated_token::authority = treasurer,
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitLiquidityPool<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + LiquidityPool::INIT_SPACE,
+        seeds = [POOL_SEED, market.key().as_ref()],
+        bump,
+    )]
+    pub pool: Account<'info, LiquidityPool>,
+    #[account(
+        init,
+        payer = admin,
+        mint::decimals = mint.decimals,
+        mint::authority = pool,
+        seeds = [LP_MINT_SEED, market.key().as_ref()],
+        bump,
+    )]
+    pub share_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        associated_token::mint = share_mint,
+        associated_token::authority = admin,
+    )]
+    pub admin_shares: Account<'info, TokenAccount>,
+    /// CHECK: JackpotPot PDA; may not exist yet, read via `load_optional_pda`
+    pub jackpot_pot: UncheckedAccount<'info>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositLiquidity<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: config owner; only receives shares, never signs
+    #[account(address = config.owner)]
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, LiquidityPool>,
+    #[account(mut, address = pool.share_mint)]
+    pub share_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = provider,
+        associated_token::mint = share_mint,
+        associated_token::authority = provider,
+    )]
+    pub provider_shares: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = provider,
+        associated_token::mint = share_mint,
+        associated_token::authority = owner,
+    )]
+    pub owner_shares: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = provider,
+    )]
+    pub provider_token: Account<'info, TokenAccount>,
+    /// CHECK: JackpotPot PDA; may not exist yet, read via `load_optional_pda`
+    pub jackpot_pot: UncheckedAccount<'info>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestLiquidityWithdrawal<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, LiquidityPool>,
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + LpWithdrawal::INIT_SPACE,
+        seeds = [LP_WITHDRAWAL_SEED, pool.key().as_ref(), provider.key().as_ref()],
+        bump,
+    )]
+    pub withdrawal: Account<'info, LpWithdrawal>,
+    #[account(
+        associated_token::mint = pool.share_mint,
+        associated_token::authority = provider,
+    )]
+    pub provider_shares: Account<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawLiquidity<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, LiquidityPool>,
+    #[account(
+        mut,
+        close = provider,
+        seeds = [LP_WITHDRAWAL_SEED, pool.key().as_ref(), provider.key().as_ref()],
+        bump,
+    )]
+    pub withdrawal: Account<'info, LpWithdrawal>,
+    #[account(mut, address = pool.share_mint)]
+    pub share_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = share_mint,
+        associated_token::authority = provider,
+    )]
+    pub provider_shares: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = provider,
+        associated_token::mint = mint,
+        associated_token::authority = provider,
+    )]
+    pub provider_token: Account<'info, TokenAccount>,
+    /// CHECK: JackpotPot PDA; may not exist yet, read via `load_optional_pda`
+    pub jackpot_pot: UncheckedAccount<'info>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitStreak<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub user: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Streak::INIT_SPACE,
+        seeds = [STREAK_SEED, user.key().as_ref(), market.key().as_ref()],
+        bump,
+    )]
+    pub streak: Account<'info, Streak>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateStreak<'info> {
+    pub user: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    pub round: Account<'info, Round>,
+    #[account(
+        mut,
+        seeds = [STREAK_SEED, user.key().as_ref(), market.key().as_ref()],
+        bump,
+    )]
+    pub streak: Account<'info, Streak>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimStreakReward<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [STREAK_SEED, user.key().as_ref(), market.key().as_ref()],
+        bump,
+    )]
+    pub streak: Account<'info, Streak>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct JoinCommunityRound<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub user: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + CommunityEntry::INIT_SPACE,
+        seeds = [COMMUNITY_SEED, round.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub community_entry: Account<'info, CommunityEntry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeCommunityRound<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+}
+
+#[derive(Accounts)]
+pub struct SettleCommunityEntry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(
+        mut,
+        seeds = [COMMUNITY_SEED, round.key().as_ref(), community_entry.user.as_ref()],
+        bump,
+    )]
+    pub community_entry: Account<'info, CommunityEntry>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    #[account(address = community_entry.user)]
+    /// CHECK: Address constraint ensures this is the recorded community_entry.user
+    pub user: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitJackpotPot<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + JackpotPot::INIT_SPACE,
+        seeds = [JACKPOT_POT_SEED, market.key().as_ref()],
+        bump,
+    )]
+    pub jackpot_pot: Account<'info, JackpotPot>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ContributeToJackpot<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [JACKPOT_POT_SEED, market.key().as_ref()],
+        bump,
+    )]
+    pub jackpot_pot: Account<'info, JackpotPot>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimJackpot<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    pub round: Account<'info, Round>,
+    #[account(seeds = [BET_SEED, round.key().as_ref(), user.key().as_ref()], bump = bet.bump)]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        seeds = [JACKPOT_POT_SEED, market.key().as_ref()],
+        bump,
+    )]
+    pub jackpot_pot: Account<'info, JackpotPot>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetPatternConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + PatternConfig::INIT_SPACE,
+        seeds = [PATTERN_SEED, market.key().as_ref()],
+        bump,
+    )]
+    pub pattern_config: Account<'info, PatternConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateRoundPermissionGroup<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PermissionGroup::INIT_SPACE,
+        seeds = [PERMISSION_GROUP_SEED, round.key().as_ref()],
+        bump,
+    )]
+    pub permission_group: Account<'info, PermissionGroup>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct PlaceParlay<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [PARAMS_SEED, market.key().as_ref()], bump)]
+    pub params: Account<'info, MarketParams>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Parlay::INIT_SPACE,
+        seeds = [PARLAY_SEED, user.key().as_ref(), &id.to_le_bytes()],
+        bump,
+    )]
+    pub parlay: Account<'info, Parlay>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleParlayLeg<'info> {
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [TREASURY_SEED, parlay.market.as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        mut,
+        seeds = [PARLAY_SEED, parlay.user.as_ref(), &parlay.id.to_le_bytes()],
+        bump = parlay.bump,
+    )]
+    pub parlay: Account<'info, Parlay>,
+    /// A leg's round; matched against the parlay's legs by address.
+    #[account(mut)]
+    pub round: Account<'info, Round>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimParlay<'info> {
+    /// The bettor or any cranker; pays for the bettor's token account if needed.
+    #[account(mut)]
+    pub claimer: Signer<'info>,
+    #[account(address = parlay.market)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        mut,
+        seeds = [PARLAY_SEED, parlay.user.as_ref(), &parlay.id.to_le_bytes()],
+        bump = parlay.bump,
+    )]
+    pub parlay: Account<'info, Parlay>,
+    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump = market.vault_bump)]
+    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = claimer,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    #[account(address = parlay.user)]
+    /// CHECK: Address constraint ensures this is the parlay's bettor
+    pub user: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseParlay<'info> {
+    pub closer: Signer<'info>,
+    #[account(address = parlay.market)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [PARLAY_SEED, parlay.user.as_ref(), &parlay.id.to_le_bytes()],
+        bump = parlay.bump,
+        close = rent_payer,
+    )]
+    pub parlay: Account<'info, Parlay>,
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut, address = parlay.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBet<'info> {
+    pub closer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(
+        mut,
+        seeds = [BET_SEED, round.key().as_ref(), bet.user.as_ref()],
+        bump = bet.bump,
+        close = rent_payer,
+    )]
+    pub bet: Account<'info, Bet>,
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut, address = bet.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseRound<'info> {
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [TREASURY_SEED, market.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()],
+        bump = round.bump,
+        close = rent_payer,
+    )]
+    pub round: Account<'info, Round>,
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut, address = round.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseCommunityEntry<'info> {
+    pub closer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(
+        mut,
+        seeds = [COMMUNITY_SEED, round.key().as_ref(), community_entry.user.as_ref()],
+        bump,
+        close = rent_payer,
+    )]
+    pub community_entry: Account<'info, CommunityEntry>,
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut, address = community_entry.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseStreak<'info> {
+    pub closer: Signer<'info>,
+    #[account(address = streak.market)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [STREAK_SEED, streak.user.as_ref(), market.key().as_ref()],
+        bump,
+        close = rent_payer,
+    )]
+    pub streak: Account<'info, Streak>,
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut, address = streak.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePermissionGroup<'info> {
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: The group's round; may already be closed, otherwise decoded
+    /// and checked in `close_permission_group`
+    #[account(address = permission_group.round)]
+    pub round: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [PERMISSION_GROUP_SEED, round.key().as_ref()],
+        bump,
+        close = rent_payer,
+    )]
+    pub permission_group: Account<'info, PermissionGroup>,
+    /// CHECK: Receives the reclaimed rent; checked against the recorded payer
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePermissionGroup<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    #[account(
+        mut,
+        seeds = [PERMISSION_GROUP_SEED, round.key().as_ref()],
+        bump,
+    )]
+    pub permission_group: Account<'info, PermissionGroup>,
+}
+
+#[vrf]
+#[derive(Accounts)]
+pub struct VrfRequestCtx<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub market: Account<'info, Market>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
+    pub round: Account<'info, Round>,
+    /// CHECK: default oracle queue address
+    #[account(mut, address = ephemeral_vrf_sdk::consts::DEFAULT_QUEUE)]
+    pub oracle_queue: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VrfCallbackCtx<'info> {
+    #[account(address = ephemeral_vrf_sdk::consts::VRF_PROGRAM_IDENTITY)]
+    pub vrf_program_identity: Signer<'info>,
     pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
+    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump = round.bump)]
     pub round: Account<'info, Round>,
 }
 
-#[derive(Accounts)]
-pub struct LockRound<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Market is inactive")]
+    MarketInactive,
+    #[msg("Invalid state transition")]
+    InvalidState,
+    #[msg("Invalid stake")]
+    InvalidStake,
+    #[msg("Overflow")]
+    Overflow,
+    #[msg("Bet already settled")]
+    AlreadySettled,
+    #[msg("Unsettled bets remain")]
+    UnsettledBetsRemain,
+    #[msg("Invalid streak target")]
+    InvalidStreakTarget,
+    #[msg("Streak not active")]
+    StreakNotActive,
+    #[msg("Streak not completed")]
+    StreakNotCompleted,
+    #[msg("Round not in predicting state")]
+    RoundNotPredicting,
+    #[msg("No community seeds provided")]
+    NoCommunitySeedsProvided,
+    #[msg("Invalid outcome type")]
+    InvalidOutcomeType,
+    #[msg("Bet not won")]
+    BetNotWon,
+    #[msg("Bet not settled")]
+    BetNotSettled,
+    #[msg("Empty jackpot")]
+    EmptyJackpot,
+    #[msg("Invalid attestation")]
+    InvalidAttestation,
+    #[msg("Invalid lock time")]
+    InvalidLockTime,
+    #[msg("Lock time too late")]
+    LockTimeTooLate,
+    #[msg("Lock time not reached")]
+    LockTimeNotReached,
+    #[msg("Min lock duration not met")]
+    MinLockDurationNotMet,
+    #[msg("No commitment")]
+    NoCommitment,
+    #[msg("Invalid commitment")]
+    InvalidCommitment,
+    #[msg("Outcome not revealed")]
+    OutcomeNotRevealed,
+    #[msg("Betting closed")]
+    BettingClosed,
+    #[msg("Viewer already exists")]
+    ViewerAlreadyExists,
+    #[msg("Max viewers reached")]
+    MaxViewersReached,
+    #[msg("VRF randomness not requested")]
+    VrfNotRequested,
+    #[msg("VRF randomness already requested and still pending")]
+    VrfRequestPending,
+    #[msg("VRF randomness already fulfilled")]
+    VrfAlreadyFulfilled,
+    #[msg("Delegated account does not match round")]
+    InvalidDelegatedAccount,
+    #[msg("Round already delegated")]
+    AlreadyDelegated,
+    #[msg("Round not delegated")]
+    NotDelegated,
+    #[msg("Round has outstanding work and cannot be undelegated")]
+    RoundWorkOutstanding,
+    #[msg("Missing or invalid magic context/program accounts")]
+    MissingMagicAccounts,
+    #[msg("Round settlement mode does not allow this instruction")]
+    WrongSettlementMode,
+    #[msg("Bet already paid out")]
+    AlreadyPaidOut,
+    #[msg("Insufficient balance")]
+    InsufficientBalance,
+    #[msg("Bet was staked from a balance")]
+    BalanceFundedBet,
+    #[msg("Invalid session")]
+    InvalidSession,
+    #[msg("Session expired")]
+    SessionExpired,
+    #[msg("Session spend cap exceeded")]
+    SessionSpendCapExceeded,
+    #[msg("Round account does not match the expected round PDA")]
+    InvalidRoundAccount,
+    #[msg("Liability account does not match the expected PDA")]
+    InvalidLiabilityAccount,
+    #[msg("Withdrawal exceeds funds above outstanding liabilities")]
+    InsufficientHouseFunds,
+    #[msg("Vault is LP-owned; house withdrawals are disabled")]
+    LiquidityPoolActive,
+    #[msg("Invalid withdrawal cooldown")]
+    InvalidCooldown,
+    #[msg("Withdrawal cooldown has not elapsed")]
+    CooldownActive,
+    #[msg("Insufficient pool shares")]
+    InsufficientShares,
+    #[msg("Pool has no equity to price new shares")]
+    PoolInsolvent,
+    #[msg("Market is not governed by the config; run migrate_market")]
+    MarketNotMigrated,
+    #[msg("Invalid role")]
+    InvalidRole,
+    #[msg("No pending owner")]
+    NoPendingOwner,
+    #[msg("Parameter outside its allowed bounds")]
+    ParamOutOfBounds,
+    #[msg("No pending parameter change")]
+    NoPendingParams,
+    #[msg("Parameter change timelock has not elapsed")]
+    TimelockNotElapsed,
+    #[msg("Stake below market minimum")]
+    StakeBelowMinimum,
+    #[msg("Stake above market maximum")]
+    StakeAboveMaximum,
+    #[msg("Protocol is paused for this action")]
+    ProtocolPaused,
+    #[msg("Unknown pause flag")]
+    InvalidPauseFlags,
+    #[msg("Odds fell below the requested minimum")]
+    OddsBelowMinimum,
+    #[msg("Stake exceeds the accepted maximum")]
+    StakeAboveLimit,
+    #[msg("Selection kind does not match market type")]
+    SelectionKindMismatch,
+    #[msg("Selection field out of range")]
+    SelectionOutOfRange,
+    #[msg("Selection covers every outcome")]
+    SelectionCoversAllOutcomes,
+    #[msg("Market type does not accept bets")]
+    SelectionNotSupported,
+    #[msg("Odds would pay less than the stake")]
+    OddsTooLow,
+    #[msg("Account is not a Bet in a known layout")]
+    InvalidBetAccount,
+    #[msg("Account already uses the current layout")]
+    AlreadyMigrated,
+    #[msg("Account is not in a known layout")]
+    InvalidAccountLayout,
+    #[msg("Market name too long")]
+    NameTooLong,
+    #[msg("Winnings have not been paid out yet")]
+    PayoutNotCollected,
+    #[msg("Round still has open bets or entries")]
+    BetsStillOpen,
+    #[msg("Treasury cursor has not passed this round")]
+    RoundNotClosedOut,
+    #[msg("Streak is still active or has an unclaimed reward")]
+    StreakStillOpen,
+    #[msg("Rent must return to the account that paid it")]
+    InvalidRentPayer,
+    #[msg("Batch must be non-empty (bet, user_token) pairs")]
+    InvalidBatch,
+    #[msg("Market type does not support this betting mode")]
+    BettingModeNotSupported,
+    #[msg("Round has no room for another selection")]
+    PoolFull,
+    #[msg("Parlay needs 2 to 8 legs on distinct rounds")]
+    InvalidParlayLegs,
+    #[msg("Combined parlay odds are too high")]
+    ParlayOddsTooHigh,
+    #[msg("Round is not a pending leg of this parlay")]
+    LegNotFound,
+    #[msg("Parlay has not won or been refunded")]
+    ParlayNotResolved,
+    #[msg("Round payouts have not reached the balance vault yet")]
+    BalancesNotSynced,
+    #[msg("Cash-out is disabled for this round")]
+    CashOutDisabled,
+    #[msg("Parlay still has legs to settle")]
+    ParlayLegsPending,
+    #[msg("Bet was staked from the vault, not a balance")]
+    VaultFundedBet,
+}
+
+fn verify_attestation(inputs_hash: &[u8; 32], signature: &[u8; 64]) -> Result<()> {
+    // Try all possible recovery IDs (0..=3) and accept if any recovers to TEE_PUBKEY.
+    let expected_uncompressed_xy = &TEE_PUBKEY[1..65];
+    for rid in 0u8..4u8 {
+        if let Ok(recovered) = secp256k1_recover(inputs_hash, rid, signature) {
+            if recovered.to_bytes() == expected_uncompressed_xy {
+                return Ok(());
+            }
+        }
+    }
+    Err(ErrorCode::InvalidAttestation.into())
+}
+
+fn verify_commitment(commitment: &[u8; 32], outcome: &[u8], nonce: &[u8; 32]) -> Result<()> {
+    let mut hasher = Sha256::new();
+    hasher.update(outcome);
+    hasher.update(nonce);
+    let computed_hash = hasher.finalize();
+
+    let mut computed = [0u8; 32];
+    computed.copy_from_slice(&computed_hash);
+
+    require!(computed == *commitment, ErrorCode::InvalidCommitment);
+
+    Ok(())
+}
+
+/// A round can be called off while its outcome is unknown to everyone: it
+/// is unrevealed, no outcome hash is committed, VRF has not fulfilled, and
+/// any VRF request has timed out. Otherwise the operator could void a round
+/// after seeing how it went.
+fn check_cancellable(round: &Round, now: i64) -> Result<()> {
+    require!(
+        round.status == RoundStatus::Predicting as u8 || round.status == RoundStatus::Locked as u8,
+        ErrorCode::InvalidState
+    );
+    require!(round.revealed_at == 0, ErrorCode::InvalidState);
+    require!(round.commitment_hash.is_none(), ErrorCode::InvalidState);
+    require!(!round.vrf_fulfilled, ErrorCode::VrfAlreadyFulfilled);
+    if round.vrf_requested_at > 0 {
+        require!(
+            now >= round.vrf_requested_at + VRF_RETRY_TIMEOUT,
+            ErrorCode::VrfRequestPending
+        );
+    }
+    require!(
+        round.settlement_mode == SettlementMode::Base as u8,
+        ErrorCode::WrongSettlementMode
+    );
+    Ok(())
+}
+
+/// A delegated round may leave the ER unless a VRF request is in flight or
+/// the round is locked with its outcome still unrevealed.
+fn can_undelegate_round(round: &Round) -> bool {
+    let vrf_pending = round.vrf_requested_at > 0 && !round.vrf_fulfilled;
+    let awaiting_reveal = round.status == RoundStatus::Locked as u8 && round.revealed_at == 0;
+    !vrf_pending && !awaiting_reveal
+}
+
+fn initial_player_entropy(market: &Pubkey, round_number: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(ROUND_SEED);
+    hasher.update(market.as_ref());
+    hasher.update(round_number.to_le_bytes());
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&hasher.finalize());
+    out
+}
+
+/// Running hash of player seeds: H(prev || player || seed).
+/// Binding the player key keeps one bettor from cancelling another's seed.
+fn fold_player_entropy(prev: &[u8; 32], player: &Pubkey, seed: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(prev);
+    hasher.update(player.as_ref());
+    hasher.update(seed);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&hasher.finalize());
+    out
+}
+
+fn determine_entropy_winner(tee: u16, chain: u16, sensor: u16) -> u8 {
+    if tee > chain && tee > sensor {
+        0
+    } else if chain > tee && chain > sensor {
+        1
+    } else {
+        2
+    }
+}
+
+fn calculate_hamming_distance(byte1: u8, byte2: u8) -> u8 {
+    let xor = byte1 ^ byte2;
+    xor.count_ones() as u8
+}
+
+fn u16_from(bytes: &[u8], idx: usize) -> u16 {
+    u16::from_le_bytes([bytes[idx % 32], bytes[(idx + 1) % 32]])
+}
+fn u8_from(bytes: &[u8], idx: usize) -> u8 {
+    bytes[idx % 32]
+}
+
+fn derive_outcome_from_randomness(mt: MarketType, rnd: &[u8; 32]) -> OutcomeType {
+    match mt {
+        MarketType::PickRange => {
+            let v = (u16_from(rnd, 0) % 100) + 1;
+            OutcomeType::Numeric { value: v }
+        }
+        MarketType::EvenOdd => {
+            let v = (u8_from(rnd, 0) % 2) as u16;
+            OutcomeType::Numeric { value: v }
+        }
+        MarketType::LastDigit => {
+            let v = (u8_from(rnd, 1) % 10) as u16;
+            OutcomeType::Numeric { value: v }
+        }
+        MarketType::ModuloThree => {
+            let v = (u8_from(rnd, 2) % 3) as u16;
+            OutcomeType::Numeric { value: v }
+        }
+        MarketType::PatternOfDay => {
+            let val = u16_from(rnd, 3) % 1000;
+            let mut pid: u8 = 6;
+            if is_prime_u16(val) {
+                pid = 0;
+            } else if is_fib_u16(val) {
+                pid = 1;
+            } else if is_square_u16(val) {
+                pid = 2;
+            } else if (val % 10) == 7 {
+                pid = 3;
+            } else if is_pal_u16(val) {
+                pid = 4;
+            } else if (val % 2) == 0 {
+                pid = 5;
+            }
+            OutcomeType::Pattern {
+                pattern_id: pid,
+                matched_value: val,
+            }
+        }
+        MarketType::ShapeColor => {
+            let shape = u8_from(rnd, 4) % 4;
+            let color = u8_from(rnd, 5) % 6;
+            let size = u8_from(rnd, 6) % 3;
+            OutcomeType::Shape { shape, color, size }
+        }
+        MarketType::Jackpot => {
+            let v = (u8_from(rnd, 7) % 100) as u16;
+            OutcomeType::Numeric { value: v }
+        }
+        MarketType::EntropyBattle => {
+            let tee = (u16_from(rnd, 8) % 512) + 1;
+            let chain = (u16_from(rnd, 10) % 512) + 1;
+            let sensor = (u16_from(rnd, 12) % 512) + 1;
+            let winner = determine_entropy_winner(tee, chain, sensor);
+            OutcomeType::Entropy {
+                tee_score: tee,
+                chain_score: chain,
+                sensor_score: sensor,
+                winner,
+            }
+        }
+        MarketType::StreakMeter => {
+            let v = (u8_from(rnd, 14) % 100) as u16;
+            OutcomeType::Numeric { value: v }
+        }
+        MarketType::CommunitySeed => {
+            let final_byte = u8_from(rnd, 15);
+            let mut sh = [0u8; 32];
+            sh.copy_from_slice(rnd);
+            OutcomeType::Community {
+                final_byte,
+                seed_hash: sh,
+            }
+        }
+        MarketType::Roulette => {
+            let v = u16_from(rnd, 16) % ROULETTE_POCKETS;
+            OutcomeType::Numeric { value: v }
+        }
+    }
+}
+
+fn is_prime_u16(n: u16) -> bool {
+    if n < 2 {
+        return false;
+    }
+    if n == 2 {
+        return true;
+    }
+    if n % 2 == 0 {
+        return false;
+    }
+    let mut i = 3u16;
+    while (i as u32) * (i as u32) <= (n as u32) {
+        if n % i == 0 {
+            return false;
+        }
+        i += 2;
+    }
+    true
+}
+fn is_square_u16(n: u16) -> bool {
+    let r = (n as f64).sqrt() as u16;
+    r * r == n
+}
+fn is_pal_u16(n: u16) -> bool {
+    let s = itoa::Buffer::new().format(n).to_string();
+    s.chars().rev().collect::<String>() == s
+}
+fn is_fib_u16(n: u16) -> bool {
+    // quick check from precomputed small fibs up to 987
+    matches!(
+        n,
+        0 | 1 | 2 | 3 | 5 | 8 | 13 | 21 | 34 | 55 | 89 | 144 | 233 | 377 | 610 | 987
+    )
+}
+
+/// Streak reward multiplier in basis points.
+fn compute_streak_odds(target: u16) -> Result<u32> {
+    let base_odds = 2 * ODDS_ONE;
+    let multiplier = match target {
+        2 => 3,
+        3 => 7,
+        4 => 12,
+        5 => 20,
+        6 => 32,
+        7 => 50,
+        8 => 75,
+        9 => 100,
+        10 => 150,
+        _ => 2,
+    };
+    Ok(base_odds * multiplier / 10)
+}
+
+/// Wildcard value for an unconstrained ShapeColor field.
+const SHAPE_ANY: u16 = 255;
+
+/// Pockets on a European wheel, 0 through 36.
+const ROULETTE_POCKETS: u16 = 37;
+const ROULETTE_RED: [u16; 18] = [
+    1, 3, 5, 7, 9, 12, 14, 16, 18, 19, 21, 23, 25, 27, 30, 32, 34, 36,
+];
+const ROULETTE_KINDS: [SelectionKind; 9] = [
+    SelectionKind::RouletteStraight,
+    SelectionKind::RouletteSplit,
+    SelectionKind::RouletteStreet,
+    SelectionKind::RouletteCorner,
+    SelectionKind::RouletteDozen,
+    SelectionKind::RouletteColumn,
+    SelectionKind::RouletteColor,
+    SelectionKind::RouletteHighLow,
+    SelectionKind::RouletteParity,
+];
+
+/// Whether roulette selection `sel` covers pocket `v`. Only straight-up
+/// bets and splits can cover zero; every other bet loses on it. Pockets
+/// 1-36 sit on the table in rows of three, so `v + 3` is the pocket below.
+fn roulette_covers(sel: &Selection, v: u16) -> bool {
+    if v >= ROULETTE_POCKETS {
+        return false;
+    }
+    match sel.kind {
+        x if x == SelectionKind::RouletteStraight as u8 => v == sel.a,
+        x if x == SelectionKind::RouletteSplit as u8 => v == sel.a || v == sel.b,
+        x if x == SelectionKind::RouletteStreet as u8 => v != 0 && v.wrapping_sub(sel.a) <= 2,
+        x if x == SelectionKind::RouletteCorner as u8 => {
+            v != 0 && matches!(v.wrapping_sub(sel.a), 0 | 1 | 3 | 4)
+        }
+        x if x == SelectionKind::RouletteDozen as u8 => v != 0 && (v - 1) / 12 == sel.a,
+        x if x == SelectionKind::RouletteColumn as u8 => v != 0 && (v - 1) % 3 == sel.a,
+        x if x == SelectionKind::RouletteColor as u8 => {
+            v != 0 && ROULETTE_RED.contains(&v) == (sel.a == 0)
+        }
+        x if x == SelectionKind::RouletteHighLow as u8 => v != 0 && (v > 18) == (sel.a == 1),
+        x if x == SelectionKind::RouletteParity as u8 => v != 0 && v % 2 == sel.a,
+        _ => false,
+    }
+}
+
+/// Validate `sel` against the market and price it. Runs before any stake
+/// moves, so malformed or no-win selections are rejected instead of taken.
+fn quote_odds(sel: &Selection, market_type: MarketType, house_edge_bps: u16) -> Result<u32> {
+    validate_selection(sel, market_type)?;
+    let odds_bps = compute_odds_bps(sel, market_type, house_edge_bps)?;
+    require!(odds_bps > ODDS_ONE, ErrorCode::OddsTooLow);
+    Ok(odds_bps)
+}
+
+/// Odds locked in when a bet is placed on `round`. Pari-mutuel bets carry
+/// 1x: what they pay is only known once the round's pools are final.
+/// Dynamic odds stay below the zero-edge fair odds, so every bet loses on
+/// average and no set of bets can guarantee a profit against the book.
+fn quote_round_odds(sel: &Selection, market: &Market, round: &Round) -> Result<u32> {
+    if round.betting_mode == BettingMode::PariMutuel as u8 {
+        validate_selection(sel, market.market_type)?;
+        return Ok(ODDS_ONE);
+    }
+    let odds_bps = quote_odds(sel, market.market_type, round.params.house_edge_bps)?;
+    if round.betting_mode != BettingMode::DynamicOdds as u8 {
+        return Ok(odds_bps);
+    }
+    let fair_bps = compute_odds_bps(sel, market.market_type, 0)?;
+    let odds_bps = dynamic_odds(odds_bps, sel, round, &round.params.dynamic_odds)?
+        .min(fair_bps.saturating_sub(1));
+    require!(odds_bps > ODDS_ONE, ErrorCode::OddsTooLow);
+    Ok(odds_bps)
+}
+
+/// Scale the fixed-odds quote by how exposed `round` already is on `sel`.
+/// A book is balanced when each selection's potential payouts match the
+/// round's total stakes; a selection paying out more is offered less and
+/// one paying out less is offered more, within `config`'s bounds. `depth`
+/// is added to both sides so early bets barely move the price.
+fn dynamic_odds(
+    base_bps: u32,
+    sel: &Selection,
+    round: &Round,
+    config: &DynamicOdds,
+) -> Result<u32> {
+    let staked: u128 = round.exposures.iter().map(|e| e.staked as u128).sum();
+    let liability = round
+        .exposures
+        .iter()
+        .find(|e| e.selection == *sel)
+        .map_or(0, |e| e.liability as u128);
+    let depth = config.depth as u128;
+    let factor = ((staked + depth) * ODDS_ONE as u128 / (liability + depth).max(1))
+        .clamp(config.min_bps as u128, config.max_bps as u128);
+    let odds_bps = base_bps as u128 * factor / ODDS_ONE as u128;
+    u32::try_from(odds_bps).map_err(|_| ErrorCode::Overflow.into())
+}
+
+/// Check that the selection kind belongs to the market type and that every
+/// field lies inside the outcome space `derive_outcome_from_randomness`
+/// produces for it. Unused fields must be zero.
+fn validate_selection(sel: &Selection, market_type: MarketType) -> Result<()> {
+    let expect_kind = |kinds: &[SelectionKind]| -> Result<()> {
+        require!(
+            kinds.iter().any(|k| *k as u8 == sel.kind),
+            ErrorCode::SelectionKindMismatch
+        );
+        Ok(())
+    };
+    let in_range = |v: u16, lo: u16, hi: u16| -> Result<()> {
+        require!(v >= lo && v <= hi, ErrorCode::SelectionOutOfRange);
+        Ok(())
+    };
+    let unused = |v: u16| -> Result<()> {
+        require!(v == 0, ErrorCode::SelectionOutOfRange);
+        Ok(())
+    };
+
+    match market_type {
+        MarketType::PickRange => {
+            expect_kind(&[SelectionKind::Range, SelectionKind::Single])?;
+            if sel.kind == SelectionKind::Range as u8 {
+                in_range(sel.a, 1, 100)?;
+                in_range(sel.b, sel.a, 100)?;
+                require!(
+                    !(sel.a == 1 && sel.b == 100),
+                    ErrorCode::SelectionCoversAllOutcomes
+                );
+            } else {
+                in_range(sel.a, 1, 100)?;
+                unused(sel.b)?;
+            }
+            unused(sel.c)
+        }
+        MarketType::EvenOdd => {
+            expect_kind(&[SelectionKind::Parity])?;
+            in_range(sel.a, 0, 1)?;
+            unused(sel.b)?;
+            unused(sel.c)
+        }
+        MarketType::LastDigit => {
+            expect_kind(&[SelectionKind::Digit])?;
+            in_range(sel.a, 0, 9)?;
+            unused(sel.b)?;
+            unused(sel.c)
+        }
+        MarketType::ModuloThree => {
+            expect_kind(&[SelectionKind::Modulo])?;
+            in_range(sel.a, 0, 2)?;
+            unused(sel.b)?;
+            unused(sel.c)
+        }
+        MarketType::PatternOfDay => {
+            expect_kind(&[SelectionKind::Pattern])?;
+            in_range(sel.a, 0, 6)?;
+            unused(sel.b)?;
+            unused(sel.c)
+        }
+        MarketType::ShapeColor => {
+            expect_kind(&[SelectionKind::Shape])?;
+            let field = |v: u16, max: u16| -> Result<()> {
+                if v == SHAPE_ANY {
+                    return Ok(());
+                }
+                in_range(v, 0, max)
+            };
+            field(sel.a, 3)?;
+            field(sel.b, 5)?;
+            field(sel.c, 2)?;
+            require!(
+                !(sel.a == SHAPE_ANY && sel.b == SHAPE_ANY && sel.c == SHAPE_ANY),
+                ErrorCode::SelectionCoversAllOutcomes
+            );
+            Ok(())
+        }
+        MarketType::Jackpot => {
+            expect_kind(&[SelectionKind::Single])?;
+            in_range(sel.a, 0, 99)?;
+            unused(sel.b)?;
+            unused(sel.c)
+        }
+        MarketType::EntropyBattle => {
+            expect_kind(&[SelectionKind::Entropy])?;
+            in_range(sel.a, 0, 2)?;
+            unused(sel.b)?;
+            unused(sel.c)
+        }
+        MarketType::CommunitySeed => {
+            expect_kind(&[SelectionKind::Community])?;
+            in_range(sel.a, 0, u8::MAX as u16)?;
+            // Tolerance 8 matches every byte.
+            in_range(sel.b, 0, 7)?;
+            unused(sel.c)
+        }
+        MarketType::Roulette => {
+            expect_kind(&ROULETTE_KINDS)?;
+            let (a, b) = (sel.a, sel.b);
+            match sel.kind {
+                x if x == SelectionKind::RouletteStraight as u8 => in_range(a, 0, 36)?,
+                x if x == SelectionKind::RouletteSplit as u8 => {
+                    in_range(a, 0, 35)?;
+                    in_range(b, a + 1, 36)?;
+                    let adjacent = match a {
+                        0 => b <= 3,
+                        _ => b == a + 3 || (b == a + 1 && a % 3 != 0),
+                    };
+                    require!(adjacent, ErrorCode::SelectionOutOfRange);
+                }
+                x if x == SelectionKind::RouletteStreet as u8 => {
+                    in_range(a, 1, 34)?;
+                    require!(a % 3 == 1, ErrorCode::SelectionOutOfRange);
+                }
+                x if x == SelectionKind::RouletteCorner as u8 => {
+                    in_range(a, 1, 32)?;
+                    require!(a % 3 != 0, ErrorCode::SelectionOutOfRange);
+                }
+                x if x == SelectionKind::RouletteDozen as u8
+                    || x == SelectionKind::RouletteColumn as u8 =>
+                {
+                    in_range(a, 0, 2)?
+                }
+                _ => in_range(a, 0, 1)?,
+            }
+            if sel.kind != SelectionKind::RouletteSplit as u8 {
+                unused(b)?;
+            }
+            unused(sel.c)
+        }
+        // Streaks are tracked through `init_streak` / `update_streak`; a
+        // `Streak` selection never wins a numeric outcome.
+        MarketType::StreakMeter => Err(ErrorCode::SelectionNotSupported.into()),
+    }
+}
+
+/// Compute odds in basis points (e.g. 15_000 => 1.5x)
+/// using real bookmaking economics:
+/// - For mutually exclusive equal bins (N), M = N / (1 + edge)
+/// - For general probability p, M = (1 / p) / (1 + edge)
+///
+/// Where edge = house_edge_bps / 10_000. Returned value is M * 10_000.
+///
+/// Rounding: M is computed with a single floor division, so the quoted odds
+/// are never above the exact value and at most 1 bp below it.
+fn compute_odds_bps(sel: &Selection, market_type: MarketType, house_edge_bps: u16) -> Result<u32> {
+    let edge_bps: u64 = (house_edge_bps as u64).min(10_000);
+    let denom: u64 = ODDS_ONE as u64 + edge_bps; // 1 + edge in bps
+    let scale: u64 = ODDS_ONE as u64 * ODDS_ONE as u64;
+
+    // helper: from N equal outcomes -> odds_bps = N * 10_000 * 10_000 / denom
+    let from_equal_bins = |n: u64| -> u32 {
+        let m = n.saturating_mul(scale) / denom;
+        m.min(u32::MAX as u64) as u32
+    };
+
+    // helper: from probability p = num/den -> odds_bps = den * 10_000 * 10_000 / (num * denom)
+    let from_probability = |num: u64, den: u64| -> u32 {
+        if num == 0 || den == 0 {
+            return 0;
+        }
+        let m = den.saturating_mul(scale) / num.saturating_mul(denom);
+        m.min(u32::MAX as u64) as u32
+    };
+
+    match market_type {
+        MarketType::EvenOdd => {
+            // 2 bins
+            Ok(from_equal_bins(2))
+        }
+        MarketType::ModuloThree => Ok(from_equal_bins(3)),
+        MarketType::LastDigit => Ok(from_equal_bins(10)),
+        MarketType::Jackpot => Ok(from_equal_bins(100)),
+        MarketType::EntropyBattle => Ok(from_equal_bins(3)),
+        MarketType::PickRange => {
+            match sel.kind {
+                x if x == SelectionKind::Range as u8 => {
+                    let width: u64 = if sel.b >= sel.a {
+                        (sel.b - sel.a + 1) as u64
+                    } else {
+                        0
+                    };
+                    if width == 0 {
+                        return Ok(0);
+                    }
+                    if 100 % width == 0 {
+                        let n = 100u64 / width; // equal partitions
+                        Ok(from_equal_bins(n))
+                    } else {
+                        // generic probability p = width/100
+                        Ok(from_probability(width, 100))
+                    }
+                }
+                x if x == SelectionKind::Single as u8 => Ok(from_equal_bins(100)),
+                _ => Ok(from_equal_bins(2)),
+            }
+        }
+        MarketType::ShapeColor => {
+            // Total combos = 4 * 6 * 3 = 72
+            let shapes = if sel.a == 255 { 4 } else { 1 };
+            let colors = if sel.b == 255 { 6 } else { 1 };
+            let sizes = if sel.c == 255 { 3 } else { 1 };
+            let matched: u64 = (shapes * colors * sizes) as u64;
+            let total: u64 = 72;
+            Ok(from_probability(matched, total))
+        }
+        MarketType::PatternOfDay => {
+            // Precomputed precedence-adjusted counts for 0..=999 as in derive_outcome_from_randomness
+            // [Prime, Fib, Square, EndsWith7, Palindrome, Even, Odd]
+            let counts: [u64; 7] = [168, 10, 29, 52, 73, 437, 231];
+            let pid = sel.a as usize;
+            let total: u64 = 1000;
+            let p_num = if pid < counts.len() {
+                counts[pid]
+            } else {
+                counts[6]
+            };
+            Ok(from_probability(p_num, total))
+        }
+        MarketType::CommunitySeed => {
+            // Selection fields: a = chosen byte, b = tolerance (0..8)
+            let t: u8 = (sel.b as u8).min(8);
+            // p = sum_{k=0..t} C(8,k) / 256
+            let mut num: u64 = 0;
+            for k in 0..=t {
+                num += n_choose_k(8, k as u32) as u64;
+            }
+            Ok(from_probability(num, 256))
+        }
+        MarketType::StreakMeter => {
+            // Not bet-based here; provide a conservative default (2 bins)
+            Ok(from_equal_bins(2))
+        }
+        MarketType::Roulette => {
+            // p = pockets covered / 37; the house edge replaces the zero's.
+            let covered = (0..ROULETTE_POCKETS)
+                .filter(|v| roulette_covers(sel, *v))
+                .count();
+            Ok(from_probability(covered as u64, ROULETTE_POCKETS as u64))
+        }
+    }
+}
+
+fn check_betting_open(round: &Round, now: i64) -> Result<()> {
+    require!(
+        round.status == RoundStatus::Predicting as u8,
+        ErrorCode::InvalidState
+    );
+    if round.lock_scheduled_at > 0 {
+        require!(now < round.lock_scheduled_at, ErrorCode::BettingClosed);
+    }
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct CommitOutcome<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
+#[allow(clippy::too_many_arguments)]
+fn record_bet(
+    bet: &mut Bet,
+    round: &mut Round,
+    round_key: Pubkey,
+    user: Pubkey,
+    rent_payer: Pubkey,
+    bump: u8,
+    selection: Selection,
+    stake: u64,
+    odds_bps: u32,
+    placed_at: i64,
+    funded_from_balance: bool,
+    player_seed: Option<[u8; 32]>,
+) -> Result<()> {
+    bet.version = Bet::VERSION;
+    bet.user = user;
+    bet.round = round_key;
+    bet.stake = stake;
+    bet.selection = selection;
+    bet.odds_bps = odds_bps;
+    bet.settled = false;
+    bet.won = false;
+    bet.payout = 0;
+    bet.placed_at = placed_at;
+    bet.paid_out = false;
+    bet.funded_from_balance = funded_from_balance;
+    bet.rent_payer = rent_payer;
+    bet.bump = bump;
+
+    if round.betting_mode == BettingMode::PariMutuel as u8 {
+        add_to_pool(round, selection, stake)?;
+    } else if round.betting_mode == BettingMode::DynamicOdds as u8 {
+        add_exposure(round, selection, stake, compute_payout(stake, odds_bps)?)?;
+    }
+    round.unsettled_bets = round.unsettled_bets.saturating_add(1);
+    round.open_bets = round.open_bets.saturating_add(1);
+    round.open_liability = round
+        .open_liability
+        .checked_add(bet_liability(bet)?)
+        .ok_or(ErrorCode::Overflow)?;
+    if let Some(seed) = player_seed {
+        round.player_entropy = fold_player_entropy(&round.player_entropy, &user, &seed);
+        round.entropy_contributions = round.entropy_contributions.saturating_add(1);
+    }
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct RevealOutcome<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
+/// Undo `record_bet` for a bet leaving the round before it locks: its stake
+/// comes out of the pool or exposure book and its liability is released.
+fn unrecord_bet(bet: &Bet, round: &mut Round) -> Result<()> {
+    if round.betting_mode == BettingMode::PariMutuel as u8 {
+        let pools = &mut round.pools;
+        if let Some(i) = pools.iter().position(|b| b.selection == bet.selection) {
+            pools[i].staked = pools[i].staked.saturating_sub(bet.stake);
+            if pools[i].staked == 0 {
+                pools.swap_remove(i);
+            }
+        }
+    } else if round.betting_mode == BettingMode::DynamicOdds as u8 {
+        let payout = compute_payout(bet.stake, bet.odds_bps)?;
+        if let Some(exposure) = round
+            .exposures
+            .iter_mut()
+            .find(|e| e.selection == bet.selection)
+        {
+            exposure.staked = exposure.staked.saturating_sub(bet.stake);
+            exposure.liability = exposure.liability.saturating_sub(payout);
+        }
+    }
+    round.unsettled_bets = round.unsettled_bets.saturating_sub(1);
+    if round.open_bets != ROUND_BETS_UNTRACKED {
+        round.open_bets = round.open_bets.saturating_sub(1);
+    }
+    round.open_liability = round.open_liability.saturating_sub(bet_liability(bet)?);
+    Ok(())
 }
 
-#[delegate]
-#[derive(Accounts)]
-pub struct DelegateRound<'info> {
-    pub payer: Signer<'info>,
-    #[account(mut, del)]
-    /// CHECK: Delegated PDA validated by the delegation program (`del` macro applies checks)
-    pub pda: AccountInfo<'info>,
-    pub market: Account<'info, Market>,
-    pub round: Account<'info, Round>,
+/// Bets can be cashed out while the round takes bets, unless it opened
+/// without a cash-out fee. Dynamic-odds rounds are excluded: a bet moves
+/// their prices, and backing it out at the stake would let a bettor shift
+/// the odds for free.
+fn check_cash_out(round: &Round, now: i64) -> Result<()> {
+    check_betting_open(round, now)?;
+    require!(
+        round.betting_mode != BettingMode::DynamicOdds as u8,
+        ErrorCode::BettingModeNotSupported
+    );
+    require!(
+        round.params.cash_out_fee_bps > 0,
+        ErrorCode::CashOutDisabled
+    );
+    Ok(())
 }
 
-#[commit]
-#[derive(Accounts)]
-pub struct RoundCommitCtx<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
+/// Part of `stake` kept by the house when a bet is cashed out.
+fn cash_out_fee(stake: u64, fee_bps: u16) -> Result<u64> {
+    let fee = (stake as u128) * (fee_bps.min(MAX_CASH_OUT_FEE_BPS) as u128) / 10_000;
+    u64::try_from(fee).map_err(|_| ErrorCode::Overflow.into())
 }
 
-#[derive(Accounts)]
-pub struct UndelegateRound<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut)]
-    /// CHECK: Delegated PDA being undelegated via CPI; validated by delegation program
-    pub pda: AccountInfo<'info>,
-    #[account(mut)]
-    /// CHECK: Delegation buffer account used by delegation program CPI
-    pub delegation_buffer: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
+/// Worst-case amount the market vault owes for an unsettled bet. A
+/// balance-funded stake never reaches the vault until `sync_round_balances`,
+/// so only the winnings above it count.
+fn bet_liability(bet: &Bet) -> Result<u64> {
+    let max_payout = compute_payout(bet.stake, bet.odds_bps)?;
+    if bet.funded_from_balance {
+        Ok(max_payout.saturating_sub(bet.stake))
+    } else {
+        Ok(max_payout)
+    }
 }
 
-#[derive(Accounts)]
-pub struct SettleRound<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
+/// Settle a vault-funded bet inside the ER. Returns the payout.
+fn credit_er_bet(round: &mut Round, bet: &mut Bet, ledger: &mut MarketLedger) -> Result<u64> {
+    require!(!bet.settled, ErrorCode::AlreadySettled);
+    require!(!bet.funded_from_balance, ErrorCode::BalanceFundedBet);
+
+    let payout_amount = credit_round_bet(round, bet)?;
+    ledger.total_credited = ledger
+        .total_credited
+        .checked_add(payout_amount)
+        .ok_or(ErrorCode::Overflow)?;
+    ledger.settled_bets = ledger.settled_bets.saturating_add(1);
+    Ok(payout_amount)
+}
+
+/// Settle a bet staked from the bettor's balance. Returns the payout.
+fn credit_balance_bet(round: &mut Round, bet: &mut Bet) -> Result<u64> {
+    require!(!bet.settled, ErrorCode::AlreadySettled);
+    require!(bet.funded_from_balance, ErrorCode::VaultFundedBet);
+    credit_round_bet(round, bet)
+}
+
+/// Record a bet's result without paying it: the winnings are added to the
+/// round's balance credits, which `sync_round_balances` moves to the balance
+/// vault in one transfer before `collect_payout` credits the bettor.
+fn credit_round_bet(round: &mut Round, bet: &mut Bet) -> Result<u64> {
+    let (won, payout_amount) = bet_payout(bet, round)?;
+
+    bet.settled = true;
+    bet.won = won;
+    bet.payout = payout_amount;
+    bet.paid_out = payout_amount == 0;
+
+    round.unsettled_bets = round.unsettled_bets.saturating_sub(1);
+    release_bet_liability(round, bet)?;
+    round.balance_credited = round
+        .balance_credited
+        .checked_add(payout_amount)
+        .ok_or(ErrorCode::Overflow)?;
+    Ok(payout_amount)
+}
+
+/// The settled bets of `round` among `infos`, ready to be written back to base.
+fn settled_round_bets<'info>(
+    round: &Pubkey,
+    infos: &'info [AccountInfo<'info>],
+) -> Result<Vec<AccountInfo<'info>>> {
+    let mut bets = Vec::with_capacity(infos.len());
+    for info in infos {
+        let bet: Account<Bet> = Account::try_from(info)?;
+        require_keys_eq!(bet.round, *round, ErrorCode::InvalidDelegatedAccount);
+        require!(bet.settled, ErrorCode::BetNotSettled);
+        bets.push(info.clone());
+    }
+    Ok(bets)
+}
+
+fn release_bet_liability(round: &mut Round, bet: &Bet) -> Result<()> {
+    round.open_liability = round
+        .open_liability
+        .saturating_sub(settled_liability(round, bet)?);
+    Ok(())
+}
+
+/// Liability a settled bet takes off its round. A pari-mutuel stake is
+/// booked at 1x but its winnings come out of the whole pool, so a vault-paid
+/// winner releases what it was paid and losers release nothing; the rest is
+/// cleared when the round settles.
+fn settled_liability(round: &Round, bet: &Bet) -> Result<u64> {
+    if round.betting_mode != BettingMode::PariMutuel as u8 {
+        return bet_liability(bet);
+    }
+    Ok(if bet.funded_from_balance { 0 } else { bet.payout })
+}
+
+/// Add `stake` to the pool bucket for `selection`, opening one if needed.
+fn add_to_pool(round: &mut Round, selection: Selection, stake: u64) -> Result<()> {
+    if let Some(bucket) = round.pools.iter_mut().find(|b| b.selection == selection) {
+        bucket.staked = bucket.staked.checked_add(stake).ok_or(ErrorCode::Overflow)?;
+        return Ok(());
+    }
+    require!(round.pools.len() < MAX_POOL_BUCKETS, ErrorCode::PoolFull);
+    round.pools.push(PoolBucket {
+        selection,
+        staked: stake,
+    });
+    Ok(())
+}
+
+/// Book a dynamic-odds bet against its selection's exposure.
+fn add_exposure(round: &mut Round, selection: Selection, stake: u64, payout: u64) -> Result<()> {
+    let exposure = match round.exposures.iter().position(|e| e.selection == selection) {
+        Some(i) => &mut round.exposures[i],
+        None => {
+            require!(round.exposures.len() < MAX_POOL_BUCKETS, ErrorCode::PoolFull);
+            round.exposures.push(Exposure {
+                selection,
+                staked: 0,
+                liability: 0,
+            });
+            round.exposures.last_mut().unwrap()
+        }
+    };
+    exposure.staked = exposure.staked.checked_add(stake).ok_or(ErrorCode::Overflow)?;
+    exposure.liability = exposure
+        .liability
+        .checked_add(payout)
+        .ok_or(ErrorCode::Overflow)?;
+    Ok(())
+}
+
+/// What a winning `stake` on a pari-mutuel round pays: the stake back plus
+/// its pro-rata share of the losing pools after the house rake.
+fn pool_payout(round: &Round, stake: u64) -> Result<u64> {
+    let mut winning: u128 = 0;
+    let mut losing: u128 = 0;
+    for bucket in &round.pools {
+        if evaluate_winner(&bucket.selection, &round.outcome)? {
+            winning += bucket.staked as u128;
+        } else {
+            losing += bucket.staked as u128;
+        }
+    }
+    require!(winning >= stake as u128, ErrorCode::InvalidState);
+    let edge_bps = (round.params.house_edge_bps as u128).min(ODDS_ONE as u128);
+    let distributable = losing * (ODDS_ONE as u128 - edge_bps) / ODDS_ONE as u128;
+    let share = (stake as u128)
+        .checked_mul(distributable)
+        .ok_or(ErrorCode::Overflow)?
+        / winning;
+    u64::try_from(stake as u128 + share).map_err(|_| ErrorCode::Overflow.into())
+}
+
+/// Whether `bet` won `round` and what it pays under the round's betting mode.
+/// A cancelled round refunds the stake.
+fn bet_payout(bet: &Bet, round: &Round) -> Result<(bool, u64)> {
+    if round.status == RoundStatus::Cancelled as u8 {
+        return Ok((false, bet.stake));
+    }
+    if !evaluate_winner(&bet.selection, &round.outcome)? {
+        return Ok((false, 0));
+    }
+    let payout = if round.betting_mode == BettingMode::PariMutuel as u8 {
+        pool_payout(round, bet.stake)?
+    } else {
+        compute_payout(bet.stake, bet.odds_bps)?
+    };
+    Ok((true, payout))
+}
+
+fn streak_reward(target: u16) -> Result<u64> {
+    compute_payout(STREAK_BASE_STAKE, compute_streak_odds(target)?)
+}
+
+/// A round stops contributing to house liabilities once it is settled (or
+/// cancelled with every bet refunded), back on base, and any balance-funded
+/// result has been moved into the market vault.
+fn round_is_closed_out(round: &Round) -> bool {
+    let finished = round.status == RoundStatus::Settled as u8
+        || (round.status == RoundStatus::Cancelled as u8 && round.unsettled_bets == 0);
+    finished
+        && round.delegation_status == DelegationStatus::Undelegated as u8
+        && round.open_liability == 0
+        && (round.balances_synced || round.balance_credited <= round.balance_staked)
+}
+
+/// Outstanding claims on a market vault, split by whether they are already
+/// owed or only possible.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct HouseLiabilities {
+    /// Winnings credited but not yet moved out, and the jackpot pot.
+    owed: u64,
+    /// Worst-case payouts of open bets, parlays and streaks.
+    exposure: u64,
+}
+
+impl HouseLiabilities {
+    fn total(&self) -> Result<u64> {
+        Ok(self
+            .owed
+            .checked_add(self.exposure)
+            .ok_or(ErrorCode::Overflow)?)
+    }
+
+    /// Equity that redeems shares and caps house withdrawals: the vault net
+    /// of every claim, so open bets stay covered.
+    fn withdrawal_equity(&self, vault: u64) -> Result<u64> {
+        Ok(vault.saturating_sub(self.total()?))
+    }
+
+    /// Equity new shares are priced at. Open exposure is not deducted, so a
+    /// long-odds bet cannot make shares cheap and dilute the pool.
+    fn deposit_equity(&self, vault: u64) -> u64 {
+        vault.saturating_sub(self.owed)
+    }
+}
+
+/// Sum every outstanding claim on the market vault. `rounds` must be the
+/// round PDAs after `treasury.settled_through` up to `last_round`, in order;
+/// the cursor is advanced past any leading rounds that have closed out.
+fn scan_house_liabilities(
+    treasury: &mut Treasury,
+    market_key: &Pubkey,
+    last_round: u64,
+    rounds: &[AccountInfo],
+    jackpot_pot: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<HouseLiabilities> {
+    let first_open = treasury.settled_through.saturating_add(1);
+    require!(
+        rounds.len() as u64 == last_round.saturating_sub(treasury.settled_through),
+        ErrorCode::InvalidRoundAccount
+    );
+
+    let mut liabilities = HouseLiabilities {
+        owed: 0,
+        exposure: treasury
+            .streak_liability
+            .checked_add(treasury.parlay_liability)
+            .ok_or(ErrorCode::Overflow)?,
+    };
+    let mut still_settled = true;
+    for (i, info) in rounds.iter().enumerate() {
+        let number = first_open + i as u64;
+        let round = load_market_round(info, market_key, number, program_id)?;
+        if still_settled && round_is_closed_out(&round) {
+            treasury.settled_through = number;
+            continue;
+        }
+        still_settled = false;
+        liabilities.owed = liabilities
+            .owed
+            .checked_add(unsynced_credits(&round))
+            .ok_or(ErrorCode::Overflow)?;
+        liabilities.exposure = liabilities
+            .exposure
+            .checked_add(round.open_liability)
+            .ok_or(ErrorCode::Overflow)?;
+    }
+
+    if let Some(pot) = load_optional_pda::<JackpotPot>(
+        jackpot_pot,
+        &[JACKPOT_POT_SEED, market_key.as_ref()],
+        program_id,
+    )? {
+        liabilities.owed = liabilities
+            .owed
+            .checked_add(pot.current_pot)
+            .ok_or(ErrorCode::Overflow)?;
+    }
+    Ok(liabilities)
+}
+
+/// Shares minted for `amount` deposited into a pool worth `equity`.
+fn shares_for_deposit(amount: u64, equity: u64, supply: u64) -> Result<u64> {
+    if supply == 0 {
+        return Ok(amount);
+    }
+    require!(equity > 0, ErrorCode::PoolInsolvent);
+    let shares = (amount as u128)
+        .checked_mul(supply as u128)
+        .ok_or(ErrorCode::Overflow)?
+        / equity as u128;
+    u64::try_from(shares).map_err(|_| ErrorCode::Overflow.into())
+}
+
+/// Shares owed to the owner for equity left in a pool after every share was
+/// redeemed; otherwise the next depositor's shares would claim it for free.
+fn residual_shares(equity: u64, supply: u64) -> u64 {
+    if supply == 0 {
+        equity
+    } else {
+        0
+    }
+}
+
+/// Tokens paid out for redeeming `shares` of a pool worth `equity`.
+fn amount_for_shares(shares: u64, equity: u64, supply: u64) -> Result<u64> {
+    require!(supply > 0 && shares <= supply, ErrorCode::InsufficientShares);
+    let amount = (shares as u128)
+        .checked_mul(equity as u128)
+        .ok_or(ErrorCode::Overflow)?
+        / supply as u128;
+    u64::try_from(amount).map_err(|_| ErrorCode::Overflow.into())
+}
+
+/// Balance winnings of a round that the market vault still has to move to
+/// the balance vault.
+fn unsynced_credits(round: &Round) -> u64 {
+    if round.balances_synced {
+        0
+    } else {
+        round.balance_credited.saturating_sub(round.balance_staked)
+    }
+}
+
+/// Load `[ROUND_SEED, market, number]` from `info`, rejecting any other account.
+/// A round delegated to the ER is read from its last committed state, so its
+/// recorded `open_liability` still counts while it is away from base.
+fn load_market_round(
+    info: &AccountInfo,
+    market: &Pubkey,
+    number: u64,
+    program_id: &Pubkey,
+) -> Result<Round> {
+    require!(
+        *info.owner == *program_id || *info.owner == DELEGATION_PROGRAM_ID,
+        ErrorCode::InvalidRoundAccount
+    );
+    let round = Round::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    let expected = Pubkey::create_program_address(
+        &[ROUND_SEED, market.as_ref(), &number.to_le_bytes(), &[round.bump]],
+        program_id,
+    )
+    .map_err(|_| ErrorCode::InvalidRoundAccount)?;
+    require_keys_eq!(info.key(), expected, ErrorCode::InvalidRoundAccount);
+    Ok(round)
+}
+
+/// Read an optional program account at a known PDA: missing accounts count
+/// as empty, delegated ones cannot be trusted from base.
+fn load_optional_pda<T: AccountDeserialize>(
+    info: &AccountInfo,
+    seeds: &[&[u8]],
+    program_id: &Pubkey,
+) -> Result<Option<T>> {
+    let (expected, _bump) = Pubkey::find_program_address(seeds, program_id);
+    require_keys_eq!(info.key(), expected, ErrorCode::InvalidLiabilityAccount);
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(*info.owner, *program_id, ErrorCode::AlreadyDelegated);
+    Ok(Some(T::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+}
+
+fn authorize_session_spend(
+    session: &mut SessionToken,
+    signer: Pubkey,
+    stake: u64,
+    now: i64,
+) -> Result<()> {
+    require_keys_eq!(session.session_signer, signer, ErrorCode::Unauthorized);
+    require!(now < session.expires_at, ErrorCode::SessionExpired);
+    let spent = session
+        .spent
+        .checked_add(stake)
+        .ok_or(ErrorCode::Overflow)?;
+    require!(spent <= session.spend_cap, ErrorCode::SessionSpendCapExceeded);
+    session.spent = spent;
+    Ok(())
+}
+
+fn init_params(params: &mut MarketParams, market: Pubkey, values: ParamValues) -> Result<()> {
+    params.version = MarketParams::VERSION;
+    params.market = market;
+    params.current = values;
+    params.pending = values;
+    params.has_pending = false;
+    params.pending_eta = 0;
+    params.proposed_at = 0;
+    params.revision = 0;
+    params.updated_at = Clock::get()?.unix_timestamp;
+    Ok(())
+}
+
+/// Reject the bet if the odds computed on-chain fall below what the player
+/// was quoted, or if the stake exceeds the bound the player signed for.
+fn check_slippage(
+    odds_bps: u32,
+    min_odds_bps: u32,
+    stake: u64,
+    max_stake: Option<u64>,
+) -> Result<()> {
+    require!(odds_bps >= min_odds_bps, ErrorCode::OddsBelowMinimum);
+    if let Some(max_stake) = max_stake {
+        require!(stake <= max_stake, ErrorCode::StakeAboveLimit);
+    }
+    Ok(())
+}
+
+fn check_stake(params: &ParamValues, stake: u64) -> Result<()> {
+    require!(stake > 0, ErrorCode::InvalidStake);
+    require!(stake >= params.min_stake, ErrorCode::StakeBelowMinimum);
+    require!(stake <= params.max_stake, ErrorCode::StakeAboveMaximum);
+    Ok(())
+}
+
+/// Require `signer` to hold `role` in the config that governs `market`.
+fn require_role(
+    config: &Account<Config>,
+    market: &Market,
+    role: Role,
+    signer: &Pubkey,
+) -> Result<()> {
+    require_keys_eq!(market.admin, config.key(), ErrorCode::MarketNotMigrated);
+    require_keys_eq!(config.role_key(role), *signer, ErrorCode::Unauthorized);
+    Ok(())
+}
+
+/// Upgrade an account of type `T` from an earlier layout in place.
+/// `layouts[v]` is the allocated size of version `v`; v0 has no version byte
+/// and is recognised by size alone. Fields are only ever appended, so each
+/// older body is a prefix of the current one.
+fn migrate_layout<T>(accounts: &MigrateAccount, layouts: &[usize]) -> Result<()>
+where
+    T: AccountSerialize + AccountDeserialize + Discriminator + Space + Versioned,
+{
+    let info = accounts.account.to_account_info();
+    require_keys_eq!(*info.owner, crate::ID, ErrorCode::InvalidAccountLayout);
+    let migrated = {
+        let data = info.try_borrow_data()?;
+        require!(
+            data.len() > 8 && data[..8] == *T::DISCRIMINATOR,
+            ErrorCode::InvalidAccountLayout
+        );
+        require!(data.len() != 8 + T::INIT_SPACE, ErrorCode::AlreadyMigrated);
+        let from = if data.len() == layouts[0] {
+            0
+        } else {
+            let version = data[8];
+            require!(
+                version > 0
+                    && (version as usize) < layouts.len()
+                    && layouts[version as usize] == data.len(),
+                ErrorCode::InvalidAccountLayout
+            );
+            version
+        };
+        upgrade_layout::<T>(&data, from, &info.key())?
+    };
+    write_migrated(&info, &accounts.payer, &accounts.system_program, &migrated)
+}
+
+/// Decode `data`, stored in layout version `from` at `key`, as the current `T`.
+fn upgrade_layout<T>(data: &[u8], from: u8, key: &Pubkey) -> Result<T>
+where
+    T: AccountDeserialize + Space + Versioned,
+{
+    let mut upgraded = Vec::with_capacity(8 + T::INIT_SPACE);
+    upgraded.extend_from_slice(&data[..8]);
+    if from == 0 {
+        upgraded.push(T::VERSION);
+        upgraded.extend_from_slice(&data[8..]);
+    } else {
+        upgraded.extend_from_slice(&data[8..]);
+        upgraded[8] = T::VERSION;
+    }
+    // Appended fields decode from zero padding until `upgrade_from` sets them.
+    if upgraded.len() < 8 + T::INIT_SPACE {
+        upgraded.resize(8 + T::INIT_SPACE, 0);
+    }
+    let mut value = T::try_deserialize(&mut &upgraded[..])?;
+    value.upgrade_from(from, key);
+    Ok(value)
+}
+
+/// Settle a vault-funded bet on a base-layer round and transfer any winnings
+/// to the bettor. Returns the payout.
+#[allow(clippy::too_many_arguments)]
+fn settle_bet_from_vault<'info>(
+    round: &mut Account<'info, Round>,
+    bet: &mut Account<'info, Bet>,
+    market_key: &Pubkey,
+    vault_authority: &AccountInfo<'info>,
+    vault_token: &Account<'info, TokenAccount>,
+    user_token: &Account<'info, TokenAccount>,
+    mint: &Account<'info, Mint>,
+    token_program: &Program<'info, Token>,
+    vault_bump: u8,
+) -> Result<u64> {
+    require_base_settlement(round)?;
+    let payout_amount = resolve_vault_bet(bet, round)?;
+
+    if payout_amount > 0 {
+        pay_from_vault(
+            market_key,
+            vault_bump,
+            vault_authority,
+            vault_token,
+            user_token,
+            mint,
+            token_program,
+            payout_amount,
+        )?;
+    }
+
+    round.unsettled_bets = round.unsettled_bets.saturating_sub(1);
+    release_bet_liability(round, bet)?;
+    Ok(payout_amount)
+}
+
+/// Bets on a round settle once its outcome is revealed, or as refunds once
+/// it is cancelled.
+fn require_settleable(round: &Round) -> Result<()> {
+    if round.status == RoundStatus::Cancelled as u8 {
+        return Ok(());
+    }
+    require!(
+        round.status == RoundStatus::Locked as u8,
+        ErrorCode::InvalidState
+    );
+    require!(round.revealed_at > 0, ErrorCode::OutcomeNotRevealed);
+    Ok(())
+}
+
+/// A parlay leg must be a fixed-odds round open for betting on an active
+/// market that pays in the parlay's mint: leg odds are the fixed quote, and
+/// the parlay is paid from one vault.
+fn check_parlay_leg(market: &Market, round: &Round, mint: &Pubkey, now: i64) -> Result<()> {
+    require!(market.is_active, ErrorCode::MarketInactive);
+    require_keys_eq!(market.mint, *mint, ErrorCode::InvalidParlayLegs);
+    require!(
+        round.betting_mode == BettingMode::FixedOdds as u8,
+        ErrorCode::BettingModeNotSupported
+    );
+    check_betting_open(round, now)
+}
+
+/// Combined odds of the legs that still count: their fair odds multiplied
+/// together, with the house edge applied once to the product.
+fn parlay_odds(legs: &[ParlayLeg], house_edge_bps: u16) -> Result<u32> {
+    let one = ODDS_ONE as u128;
+    let mut odds = one;
+    for leg in legs.iter().filter(|l| l.status != LegStatus::Void as u8) {
+        odds = odds * leg.odds_bps as u128 / one;
+        require!(odds <= u32::MAX as u128, ErrorCode::ParlayOddsTooHigh);
+    }
+    let edge_bps = (house_edge_bps as u128).min(one);
+    Ok((odds * one / (one + edge_bps)) as u32)
+}
+
+/// Record `round`'s result on the parlay's pending leg for it. The parlay is
+/// lost on its first losing leg, and won (or refunded, if every leg was
+/// voided) once no leg is pending. Legs resolved after a loss only record
+/// their result.
+fn apply_leg_result(parlay: &mut Parlay, round_key: &Pubkey, round: &Round) -> Result<()> {
+    let leg = parlay
+        .legs
+        .iter_mut()
+        .find(|l| l.round == *round_key && l.status == LegStatus::Pending as u8)
+        .ok_or(ErrorCode::LegNotFound)?;
+    leg.status = if round.status == RoundStatus::Cancelled as u8 {
+        LegStatus::Void as u8
+    } else {
+        require!(round.revealed_at > 0, ErrorCode::OutcomeNotRevealed);
+        if evaluate_winner(&leg.selection, &round.outcome)? {
+            LegStatus::Won as u8
+        } else {
+            LegStatus::Lost as u8
+        }
+    };
+
+    let lost = leg.status == LegStatus::Lost as u8;
+    if parlay.status != ParlayStatus::Open as u8 {
+        return Ok(());
+    }
+
+    let count = |status: LegStatus| {
+        parlay
+            .legs
+            .iter()
+            .filter(|l| l.status == status as u8)
+            .count()
+    };
+    let (pending, void) = (count(LegStatus::Pending), count(LegStatus::Void));
+    if lost {
+        parlay.status = ParlayStatus::Lost as u8;
+        parlay.payout = 0;
+        parlay.paid_out = true;
+    } else if pending == 0 && void == parlay.legs.len() {
+        parlay.status = ParlayStatus::Refunded as u8;
+        parlay.payout = parlay.stake;
+    } else if pending == 0 {
+        let odds_bps = parlay_odds(&parlay.legs, parlay.house_edge_bps)?;
+        parlay.status = ParlayStatus::Won as u8;
+        parlay.payout = compute_payout(parlay.stake, odds_bps)?;
+    }
+    Ok(())
+}
+
+fn require_base_settlement(round: &Round) -> Result<()> {
+    require_settleable(round)?;
+    require!(
+        round.settlement_mode == SettlementMode::Base as u8,
+        ErrorCode::WrongSettlementMode
+    );
+    Ok(())
+}
+
+/// Record the result of a vault-funded bet and return what it pays. The
+/// caller transfers the payout and updates the round.
+fn resolve_vault_bet(bet: &mut Bet, round: &Round) -> Result<u64> {
+    require!(!bet.settled, ErrorCode::AlreadySettled);
+    require!(!bet.funded_from_balance, ErrorCode::BalanceFundedBet);
+
+    let (won, payout_amount) = bet_payout(bet, round)?;
+
+    bet.settled = true;
+    bet.won = won;
+    bet.payout = payout_amount;
+    bet.paid_out = true;
+    Ok(payout_amount)
 }
 
-#[derive(Accounts)]
-pub struct PlaceBet<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + 32 + 32 + 8 + 8 + 2 + 1 + 1 + 8 + 8,
-        seeds = [BET_SEED, round.key().as_ref(), payer.key().as_ref()],
-        bump,
-    )]
-    pub bet: Account<'info, Bet>,
-    #[account(
-        init_if_needed,
-        payer = payer,
-        associated_token::mint = mint,
-        associated_token::authority = payer,
-    )]
-    pub user_token: Account<'info, TokenAccount>,
-    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump)]
-    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
-    pub vault_authority: AccountInfo<'info>,
-    #[account(
-        init_if_needed,
-        payer = payer,
-        associated_token::mint = mint,
-        associated_token::authority = vault_authority,
-    )]
-    pub vault_token: Account<'info, TokenAccount>,
-    pub mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
+#[allow(clippy::too_many_arguments)]
+fn pay_from_vault<'info>(
+    market_key: &Pubkey,
+    vault_bump: u8,
+    vault_authority: &AccountInfo<'info>,
+    vault_token: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    mint: &Account<'info, Mint>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    let signer_slice: &[&[u8]] = &[VAULT_SEED, market_key.as_ref(), &[vault_bump]];
+    let signer_seeds: &[&[&[u8]]] = &[signer_slice];
+
+    let cpi_accounts = TransferChecked {
+        from: vault_token.to_account_info(),
+        to: to.to_account_info(),
+        mint: mint.to_account_info(),
+        authority: vault_authority.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        cpi_accounts,
+        signer_seeds,
+    );
+    token::transfer_checked(cpi_ctx, amount, mint.decimals)
 }
 
-#[derive(Accounts)]
-pub struct SettleBet<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
-    #[account(mut, seeds = [BET_SEED, round.key().as_ref(), bet.user.as_ref()], bump)]
-    pub bet: Account<'info, Bet>,
-    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump)]
-    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
-    pub vault_authority: AccountInfo<'info>,
-    #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = vault_authority,
-    )]
-    pub vault_token: Account<'info, TokenAccount>,
-    #[account(
-        init_if_needed,
-        payer = admin,
-        associated_token::mint = mint,
-        associated_token::authority = user,
-    )]
-    pub user_token: Account<'info, TokenAccount>,
-    #[account(address = bet.user)]
-    /// CHECK: Address constraint ensures this is the bet.user
-    pub user: UncheckedAccount<'info>,
-    pub mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
+/// Pay the treasury's crank reward for a permissionless lock, returning the
+/// amount paid. Nothing is paid while payouts are paused, when the reward
+/// accounts are omitted, or when the vault cannot cover the reward.
+fn check_crank_reward(amount: u64, params: &ParamValues) -> Result<()> {
+    require!(amount <= params.min_stake, ErrorCode::ParamOutOfBounds);
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct InitVault<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump)]
-    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
-    pub vault_authority: AccountInfo<'info>,
-    #[account(
-        init_if_needed,
-        payer = payer,
-        associated_token::mint = mint,
-        associated_token::authority = vault_authority,
-    )]
-    pub vault_token: Account<'info, TokenAccount>,
-    pub mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
+/// Reward owed for a cranked lock: nothing while payouts are paused or when
+/// the vault cannot cover it, so the lock itself never fails on it.
+fn crank_reward_due(reward: u64, pause_flags: u8, vault_balance: u64) -> u64 {
+    if pause_flags & PAUSE_PAYOUT != 0 || vault_balance < reward {
+        return 0;
+    }
+    reward
 }
 
-#[derive(Accounts)]
-pub struct InitStreak<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub user: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + 32 + 32 + 2 + 2 + 1 + 32,
-        seeds = [STREAK_SEED, user.key().as_ref(), market.key().as_ref()],
-        bump,
-    )]
-    pub streak: Account<'info, Streak>,
-    pub system_program: Program<'info, System>,
+fn pay_crank_reward(ctx: &Context<LockRound>) -> Result<u64> {
+    let accounts = &ctx.accounts;
+    let (
+        Some(treasury),
+        Some(vault_authority),
+        Some(vault_token),
+        Some(cranker_token),
+        Some(mint),
+        Some(token_program),
+    ) = (
+        &accounts.treasury,
+        &accounts.vault_authority,
+        &accounts.vault_token,
+        &accounts.cranker_token,
+        &accounts.mint,
+        &accounts.token_program,
+    )
+    else {
+        return Ok(0);
+    };
+    let reward = crank_reward_due(
+        treasury.crank_reward,
+        accounts.config.pause_flags,
+        vault_token.amount,
+    );
+    if reward == 0 {
+        return Ok(0);
+    }
+    require_keys_eq!(accounts.market.mint, mint.key(), ErrorCode::Unauthorized);
+    require_keys_eq!(vault_token.mint, mint.key(), ErrorCode::Unauthorized);
+    require_keys_eq!(vault_token.owner, vault_authority.key(), ErrorCode::Unauthorized);
+    require_keys_eq!(cranker_token.mint, mint.key(), ErrorCode::Unauthorized);
+
+    let market_key = accounts.market.key();
+    let bump = ctx.accounts.market.vault_bump;
+    let signer_slice: &[&[u8]] = &[VAULT_SEED, market_key.as_ref(), &[bump]];
+    let signer_seeds: &[&[&[u8]]] = &[&signer_slice];
+    let cpi_accounts = TransferChecked {
+        from: vault_token.to_account_info(),
+        to: cranker_token.to_account_info(),
+        mint: mint.to_account_info(),
+        authority: vault_authority.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        cpi_accounts,
+        signer_seeds,
+    );
+    token::transfer_checked(cpi_ctx, reward, mint.decimals)?;
+    Ok(reward)
 }
 
-#[derive(Accounts)]
-pub struct UpdateStreak<'info> {
-    pub user: Signer<'info>,
-    pub market: Account<'info, Market>,
-    pub round: Account<'info, Round>,
-    #[account(
-        mut,
-        seeds = [STREAK_SEED, user.key().as_ref(), market.key().as_ref()],
-        bump,
-    )]
-    pub streak: Account<'info, Streak>,
+/// Closing a player's account to reclaim rent is open to the player and to
+/// market operators doing cleanup.
+fn require_player_or_operator(
+    config: &Account<Config>,
+    market: &Market,
+    player: &Pubkey,
+    signer: &Pubkey,
+) -> Result<()> {
+    if player == signer {
+        return Ok(());
+    }
+    require_role(config, market, Role::Operator, signer)
 }
 
-#[derive(Accounts)]
-pub struct ClaimStreakReward<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(
-        mut,
-        seeds = [STREAK_SEED, user.key().as_ref(), market.key().as_ref()],
-        bump,
-    )]
-    pub streak: Account<'info, Streak>,
-    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump)]
-    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
-    pub vault_authority: AccountInfo<'info>,
-    #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = vault_authority,
-    )]
-    pub vault_token: Account<'info, TokenAccount>,
-    #[account(
-        init_if_needed,
-        payer = user,
-        associated_token::mint = mint,
-        associated_token::authority = user,
-    )]
-    pub user_token: Account<'info, TokenAccount>,
-    pub mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
+/// Resize `info` to the derived size of `T`, topping up rent from `payer`,
+/// and write `value` over it.
+fn write_migrated<'info, T>(
+    info: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    value: &T,
+) -> Result<()>
+where
+    T: AccountSerialize + Space,
+{
+    let space = 8 + T::INIT_SPACE;
+    let rent = Rent::get()?.minimum_balance(space);
+    let shortfall = rent.saturating_sub(info.lamports());
+    if shortfall > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    info.resize(space)?;
+
+    let mut data = info.try_borrow_mut_data()?;
+    data.fill(0);
+    let mut cursor: &mut [u8] = &mut data[..];
+    value.try_serialize(&mut cursor)
 }
 
-#[derive(Accounts)]
-pub struct JoinCommunityRound<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub user: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + 32 + 32 + 1 + 2 + 1,
-        seeds = [COMMUNITY_SEED, round.key().as_ref(), user.key().as_ref()],
-        bump,
-    )]
-    pub community_entry: Account<'info, CommunityEntry>,
-    pub system_program: Program<'info, System>,
+fn require_not_paused(config: &Config, flag: u8) -> Result<()> {
+    require!(config.pause_flags & flag == 0, ErrorCode::ProtocolPaused);
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct FinalizeCommunityRound<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
+/// `stake * odds_bps / 10_000`, widened to u128 and floored: the payout is
+/// at most one base unit below the exact amount, never above it.
+fn compute_payout(stake: u64, odds_bps: u32) -> Result<u64> {
+    let payout = (stake as u128) * (odds_bps as u128) / (ODDS_ONE as u128);
+    u64::try_from(payout).map_err(|_| ErrorCode::Overflow.into())
 }
 
-#[derive(Accounts)]
-pub struct SettleCommunityEntry<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
-    #[account(
-        mut,
-        seeds = [COMMUNITY_SEED, round.key().as_ref(), community_entry.user.as_ref()],
-        bump,
-    )]
-    pub community_entry: Account<'info, CommunityEntry>,
-    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump)]
-    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
-    pub vault_authority: AccountInfo<'info>,
-    #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = vault_authority,
-    )]
-    pub vault_token: Account<'info, TokenAccount>,
-    #[account(
-        init_if_needed,
-        payer = admin,
-        associated_token::mint = mint,
-        associated_token::authority = user,
-    )]
-    pub user_token: Account<'info, TokenAccount>,
-    #[account(address = community_entry.user)]
-    /// CHECK: Address constraint ensures this is the recorded community_entry.user
-    pub user: UncheckedAccount<'info>,
-    pub mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
+fn n_choose_k(n: u32, k: u32) -> u32 {
+    if k > n {
+        return 0;
+    }
+    if k == 0 || k == n {
+        return 1;
+    }
+    let mut k = k.min(n - k);
+    let mut numer: u64 = 1;
+    let mut denom: u64 = 1;
+    for i in 0..k {
+        numer = numer.saturating_mul((n - i) as u64);
+        denom = denom.saturating_mul((i + 1) as u64);
+    }
+    (numer / denom) as u32
+}
+
+fn evaluate_winner(sel: &Selection, outcome: &OutcomeType) -> Result<bool> {
+    match outcome {
+        OutcomeType::Numeric { value } => {
+            let v = *value;
+            let won = match sel.kind {
+                x if x == SelectionKind::Single as u8 => v == sel.a,
+                x if x == SelectionKind::Range as u8 => v >= sel.a && v <= sel.b,
+                x if x == SelectionKind::Parity as u8 => {
+                    let even = (v % 2) == 0;
+                    let want_even = sel.a == 0;
+                    even == want_even
+                }
+                x if x == SelectionKind::Digit as u8 => (v % 10) == (sel.a % 10),
+                x if x == SelectionKind::Modulo as u8 => (v % 3) == (sel.a % 3),
+                _ => roulette_covers(sel, v),
+            };
+            Ok(won)
+        }
+        OutcomeType::Shape { shape, color, size } => {
+            if sel.kind != SelectionKind::Shape as u8 {
+                return Ok(false);
+            }
+            let matches = (sel.a == 255 || sel.a == *shape as u16)
+                && (sel.b == 255 || sel.b == *color as u16)
+                && (sel.c == 255 || sel.c == *size as u16);
+            Ok(matches)
+        }
+        OutcomeType::Pattern {
+            pattern_id,
+            matched_value,
+        } => {
+            if sel.kind != SelectionKind::Pattern as u8 {
+                return Ok(false);
+            }
+            Ok(sel.a == *pattern_id as u16 && *matched_value > 0)
+        }
+        OutcomeType::Entropy { winner, .. } => {
+            if sel.kind != SelectionKind::Entropy as u8 {
+                return Ok(false);
+            }
+            Ok(sel.a == *winner as u16)
+        }
+        OutcomeType::Community { final_byte, .. } => {
+            if sel.kind != SelectionKind::Community as u8 {
+                return Ok(false);
+            }
+            let distance = calculate_hamming_distance(sel.a as u8, *final_byte);
+            let tolerance = sel.b as u8;
+            Ok(distance <= tolerance)
+        }
+        OutcomeType::Pending => Ok(false),
+    }
 }
 
-#[derive(Accounts)]
-pub struct InitJackpotPot<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + 32 + 8 + 33 + 8,
-        seeds = [JACKPOT_POT_SEED, market.key().as_ref()],
-        bump,
-    )]
-    pub jackpot_pot: Account<'info, JackpotPot>,
-    pub system_program: Program<'info, System>,
+fn is_prime(n: u16) -> bool {
+    if n < 2 {
+        return false;
+    }
+    if n == 2 {
+        return true;
+    }
+    if n % 2 == 0 {
+        return false;
+    }
+    let sqrt = (n as f64).sqrt() as u16;
+    for i in (3..=sqrt).step_by(2) {
+        if n % i == 0 {
+            return false;
+        }
+    }
+    true
 }
 
-#[derive(Accounts)]
-pub struct ContributeToJackpot<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(
-        mut,
-        seeds = [JACKPOT_POT_SEED, market.key().as_ref()],
-        bump,
-    )]
-    pub jackpot_pot: Account<'info, JackpotPot>,
-    #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = user,
-    )]
-    pub user_token: Account<'info, TokenAccount>,
-    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump)]
-    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
-    pub vault_authority: AccountInfo<'info>,
-    #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = vault_authority,
-    )]
-    pub vault_token: Account<'info, TokenAccount>,
-    pub mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
+fn is_fibonacci(n: u16) -> bool {
+    let fibs: [u16; 17] = [
+        0, 1, 1, 2, 3, 5, 8, 13, 21, 34, 55, 89, 144, 233, 377, 610, 987,
+    ];
+    fibs.contains(&n)
 }
 
-#[derive(Accounts)]
-pub struct ClaimJackpot<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub market: Account<'info, Market>,
-    pub round: Account<'info, Round>,
-    #[account(seeds = [BET_SEED, round.key().as_ref(), user.key().as_ref()], bump)]
-    pub bet: Account<'info, Bet>,
-    #[account(
-        mut,
-        seeds = [JACKPOT_POT_SEED, market.key().as_ref()],
-        bump,
-    )]
-    pub jackpot_pot: Account<'info, JackpotPot>,
-    #[account(seeds = [VAULT_SEED, market.key().as_ref()], bump)]
-    /// CHECK: Program-derived address used as vault authority; seeds verified by Anchor
-    pub vault_authority: AccountInfo<'info>,
-    #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = vault_authority,
-    )]
-    pub vault_token: Account<'info, TokenAccount>,
-    #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = user,
-    )]
-    pub user_token: Account<'info, TokenAccount>,
-    pub mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
+fn is_perfect_square(n: u16) -> bool {
+    let sqrt = (n as f64).sqrt() as u16;
+    sqrt * sqrt == n
 }
 
-#[derive(Accounts)]
-pub struct SetPatternConfig<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(
-        init_if_needed,
-        payer = admin,
-        space = 8 + 32 + 1 + 16 + 1,
-        seeds = [PATTERN_SEED, market.key().as_ref()],
-        bump,
-    )]
-    pub pattern_config: Account<'info, PatternConfig>,
-    pub system_program: Program<'info, System>,
+fn ends_with_seven(n: u16) -> bool {
+    n % 10 == 7
 }
 
-#[derive(Accounts)]
-pub struct CreateRoundPermissionGroup<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + 32 + 4 + (32 * 50) + 1,
-        seeds = [PERMISSION_GROUP_SEED, round.key().as_ref()],
-        bump,
-    )]
-    pub permission_group: Account<'info, PermissionGroup>,
-    pub system_program: Program<'info, System>,
+fn is_palindrome(n: u16) -> bool {
+    let s = n.to_string();
+    s.chars().eq(s.chars().rev())
 }
 
-#[derive(Accounts)]
-pub struct UpdatePermissionGroup<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
-    #[account(
-        mut,
-        seeds = [PERMISSION_GROUP_SEED, round.key().as_ref()],
-        bump,
-    )]
-    pub permission_group: Account<'info, PermissionGroup>,
+pub fn matches_pattern(value: u16, pattern_type: PatternType) -> bool {
+    match pattern_type {
+        PatternType::Prime => is_prime(value),
+        PatternType::Fibonacci => is_fibonacci(value),
+        PatternType::PerfectSquare => is_perfect_square(value),
+        PatternType::EndsWithSeven => ends_with_seven(value),
+        PatternType::Palindrome => is_palindrome(value),
+        PatternType::Even => value % 2 == 0,
+        PatternType::Odd => value % 2 == 1,
+    }
 }
 
-#[vrf]
-#[derive(Accounts)]
-pub struct VrfRequestCtx<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
-    /// CHECK: default oracle queue address
-    #[account(mut, address = ephemeral_vrf_sdk::consts::DEFAULT_QUEUE)]
-    pub oracle_queue: AccountInfo<'info>,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-#[derive(Accounts)]
-pub struct VrfCallbackCtx<'info> {
-    #[account(address = ephemeral_vrf_sdk::consts::VRF_PROGRAM_IDENTITY)]
-    pub vrf_program_identity: Signer<'info>,
-    pub market: Account<'info, Market>,
-    #[account(mut, seeds = [ROUND_SEED, market.key().as_ref(), &round.number.to_le_bytes()], bump)]
-    pub round: Account<'info, Round>,
-}
+    const ALL_MARKETS: [MarketType; 11] = [
+        MarketType::PickRange,
+        MarketType::EvenOdd,
+        MarketType::LastDigit,
+        MarketType::ModuloThree,
+        MarketType::PatternOfDay,
+        MarketType::ShapeColor,
+        MarketType::Jackpot,
+        MarketType::EntropyBattle,
+        MarketType::StreakMeter,
+        MarketType::CommunitySeed,
+        MarketType::Roulette,
+    ];
 
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Unauthorized")]
-    Unauthorized,
-    #[msg("Market is inactive")]
-    MarketInactive,
-    #[msg("Invalid state transition")]
-    InvalidState,
-    #[msg("Invalid stake")]
-    InvalidStake,
-    #[msg("Overflow")]
-    Overflow,
-    #[msg("Bet already settled")]
-    AlreadySettled,
-    #[msg("Unsettled bets remain")]
-    UnsettledBetsRemain,
-    #[msg("Invalid streak target")]
-    InvalidStreakTarget,
-    #[msg("Streak not active")]
-    StreakNotActive,
-    #[msg("Streak not completed")]
-    StreakNotCompleted,
-    #[msg("Round not in predicting state")]
-    RoundNotPredicting,
-    #[msg("No community seeds provided")]
-    NoCommunitySeedsProvided,
-    #[msg("Invalid outcome type")]
-    InvalidOutcomeType,
-    #[msg("Bet not won")]
-    BetNotWon,
-    #[msg("Bet not settled")]
-    BetNotSettled,
-    #[msg("Empty jackpot")]
-    EmptyJackpot,
-    #[msg("Invalid attestation")]
-    InvalidAttestation,
-    #[msg("Invalid lock time")]
-    InvalidLockTime,
-    #[msg("Lock time too late")]
-    LockTimeTooLate,
-    #[msg("Lock time not reached")]
-    LockTimeNotReached,
-    #[msg("Min lock duration not met")]
-    MinLockDurationNotMet,
-    #[msg("No commitment")]
-    NoCommitment,
-    #[msg("Invalid commitment")]
-    InvalidCommitment,
-    #[msg("Outcome not revealed")]
-    OutcomeNotRevealed,
-    #[msg("Betting closed")]
-    BettingClosed,
-    #[msg("Viewer already exists")]
-    ViewerAlreadyExists,
-    #[msg("Max viewers reached")]
-    MaxViewersReached,
-}
+    fn sel(kind: SelectionKind, a: u16, b: u16, c: u16) -> Selection {
+        Selection {
+            kind: kind as u8,
+            a,
+            b,
+            c,
+        }
+    }
+
+    fn err(code: ErrorCode) -> Result<()> {
+        Err(code.into())
+    }
+
+    /// The kinds each market accepts; everything else must be a mismatch.
+    fn accepted_kinds(mt: MarketType) -> &'static [u8] {
+        match mt {
+            MarketType::PickRange => &[SelectionKind::Range as u8, SelectionKind::Single as u8],
+            MarketType::EvenOdd => &[SelectionKind::Parity as u8],
+            MarketType::LastDigit => &[SelectionKind::Digit as u8],
+            MarketType::ModuloThree => &[SelectionKind::Modulo as u8],
+            MarketType::PatternOfDay => &[SelectionKind::Pattern as u8],
+            MarketType::ShapeColor => &[SelectionKind::Shape as u8],
+            MarketType::Jackpot => &[SelectionKind::Single as u8],
+            MarketType::EntropyBattle => &[SelectionKind::Entropy as u8],
+            MarketType::StreakMeter => &[],
+            MarketType::CommunitySeed => &[SelectionKind::Community as u8],
+            MarketType::Roulette => &[10, 11, 12, 13, 14, 15, 16, 17, 18],
+        }
+    }
+
+    #[test]
+    fn rejects_kind_from_another_market() {
+        for mt in ALL_MARKETS {
+            if mt == MarketType::StreakMeter {
+                continue;
+            }
+            for kind in 0..=u8::MAX {
+                if accepted_kinds(mt).contains(&kind) {
+                    continue;
+                }
+                let s = Selection { kind, a: 0, b: 0, c: 0 };
+                assert_eq!(
+                    validate_selection(&s, mt),
+                    err(ErrorCode::SelectionKindMismatch),
+                    "kind {kind}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn streak_meter_takes_no_bets() {
+        for kind in 0..=u8::MAX {
+            let s = Selection { kind, a: 3, b: 0, c: 0 };
+            assert_eq!(
+                validate_selection(&s, MarketType::StreakMeter),
+                err(ErrorCode::SelectionNotSupported)
+            );
+        }
+    }
 
-fn verify_attestation(inputs_hash: &[u8; 32], signature: &[u8; 64]) -> Result<()> {
-    // Try all possible recovery IDs (0..=3) and accept if any recovers to TEE_PUBKEY.
-    let expected_uncompressed_xy = &TEE_PUBKEY[1..65];
-    for rid in 0u8..4u8 {
-        if let Ok(recovered) = secp256k1_recover(inputs_hash, rid, signature) {
-            if recovered.to_bytes() == expected_uncompressed_xy {
-                return Ok(());
+    #[test]
+    fn pick_range_single_bounds() {
+        for a in 0..=200u16 {
+            let res = validate_selection(&sel(SelectionKind::Single, a, 0, 0), MarketType::PickRange);
+            if (1..=100).contains(&a) {
+                assert!(res.is_ok(), "single {a}");
+            } else {
+                assert_eq!(res, err(ErrorCode::SelectionOutOfRange), "single {a}");
             }
         }
     }
-    Err(ErrorCode::InvalidAttestation.into())
-}
 
-fn verify_commitment(commitment: &[u8; 32], outcome: &[u8], nonce: &[u8; 32]) -> Result<()> {
-    let mut hasher = Sha256::new();
-    hasher.update(outcome);
-    hasher.update(nonce);
-    let computed_hash = hasher.finalize();
+    #[test]
+    fn pick_range_range_bounds() {
+        for a in 0..=101u16 {
+            for b in 0..=101u16 {
+                let res = validate_selection(&sel(SelectionKind::Range, a, b, 0), MarketType::PickRange);
+                if a == 1 && b == 100 {
+                    assert_eq!(res, err(ErrorCode::SelectionCoversAllOutcomes));
+                } else if (1..=100).contains(&a) && (a..=100).contains(&b) {
+                    assert!(res.is_ok(), "range {a}..={b}");
+                } else {
+                    assert_eq!(res, err(ErrorCode::SelectionOutOfRange), "range {a}..={b}");
+                }
+            }
+        }
+    }
 
-    let mut computed = [0u8; 32];
-    computed.copy_from_slice(&computed_hash);
+    #[test]
+    fn single_value_markets_bounds() {
+        let cases = [
+            (MarketType::EvenOdd, SelectionKind::Parity, 1u16),
+            (MarketType::LastDigit, SelectionKind::Digit, 9),
+            (MarketType::ModuloThree, SelectionKind::Modulo, 2),
+            (MarketType::PatternOfDay, SelectionKind::Pattern, 6),
+            (MarketType::Jackpot, SelectionKind::Single, 99),
+            (MarketType::EntropyBattle, SelectionKind::Entropy, 2),
+        ];
+        for (mt, kind, max) in cases {
+            for a in 0..=300u16 {
+                let res = validate_selection(&sel(kind, a, 0, 0), mt);
+                if a <= max {
+                    assert!(res.is_ok(), "a = {a}");
+                } else {
+                    assert_eq!(res, err(ErrorCode::SelectionOutOfRange), "a = {a}");
+                }
+            }
+            assert_eq!(
+                validate_selection(&sel(kind, 0, 1, 0), mt),
+                err(ErrorCode::SelectionOutOfRange)
+            );
+            assert_eq!(
+                validate_selection(&sel(kind, 0, 0, 1), mt),
+                err(ErrorCode::SelectionOutOfRange)
+            );
+        }
+    }
 
-    require!(computed == *commitment, ErrorCode::InvalidCommitment);
+    #[test]
+    fn shape_color_fields_and_wildcards() {
+        let values: Vec<u16> = (0..=10).chain([254, 255, 256, 5000]).collect();
+        for &a in &values {
+            for &b in &values {
+                for &c in &values {
+                    let res =
+                        validate_selection(&sel(SelectionKind::Shape, a, b, c), MarketType::ShapeColor);
+                    let ok = |v: u16, max: u16| v == SHAPE_ANY || v <= max;
+                    if a == SHAPE_ANY && b == SHAPE_ANY && c == SHAPE_ANY {
+                        assert_eq!(res, err(ErrorCode::SelectionCoversAllOutcomes));
+                    } else if ok(a, 3) && ok(b, 5) && ok(c, 2) {
+                        assert!(res.is_ok(), "shape {a}/{b}/{c}");
+                    } else {
+                        assert_eq!(res, err(ErrorCode::SelectionOutOfRange), "shape {a}/{b}/{c}");
+                    }
+                }
+            }
+        }
+    }
 
-    Ok(())
-}
+    #[test]
+    fn community_byte_and_tolerance() {
+        for a in 0..=300u16 {
+            for b in 0..=10u16 {
+                let res = validate_selection(
+                    &sel(SelectionKind::Community, a, b, 0),
+                    MarketType::CommunitySeed,
+                );
+                if a <= 255 && b <= 7 {
+                    assert!(res.is_ok(), "community {a}/{b}");
+                } else {
+                    assert_eq!(res, err(ErrorCode::SelectionOutOfRange), "community {a}/{b}");
+                }
+            }
+        }
+    }
 
-fn determine_entropy_winner(tee: u16, chain: u16, sensor: u16) -> u8 {
-    if tee > chain && tee > sensor {
-        0
-    } else if chain > tee && chain > sensor {
-        1
-    } else {
-        2
+    #[test]
+    fn valid_selections_are_priced() {
+        // Every valid selection has non-zero odds; at the maximum house edge
+        // it either pays above 1x or is caught by the odds floor.
+        let mut checked = 0;
+        for mt in ALL_MARKETS {
+            for &kind in accepted_kinds(mt) {
+                for a in 0..=255u16 {
+                    for b in [0u16, 1, 2, 5, 7, 50, 99, 100, 255] {
+                        for c in [0u16, 1, 2, 255] {
+                            let s = Selection { kind, a, b, c };
+                            if validate_selection(&s, mt).is_err() {
+                                continue;
+                            }
+                            checked += 1;
+                            let odds = compute_odds_bps(&s, mt, 0).unwrap();
+                            assert!(odds > 0, "{kind} {a}/{b}/{c} has zero odds");
+                            match quote_odds(&s, mt, MAX_HOUSE_EDGE_BPS) {
+                                Ok(q) => assert!(q > ODDS_ONE),
+                                Err(e) => assert_eq!(e, ErrorCode::OddsTooLow.into()),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        assert!(checked > 0);
     }
-}
 
-fn calculate_hamming_distance(byte1: u8, byte2: u8) -> u8 {
-    let xor = byte1 ^ byte2;
-    xor.count_ones() as u8
-}
+    #[test]
+    fn quote_rejects_before_pricing() {
+        assert_eq!(
+            quote_odds(&sel(SelectionKind::Shape, 0, 0, 0), MarketType::EvenOdd, 0),
+            Err(ErrorCode::SelectionKindMismatch.into())
+        );
+        assert_eq!(
+            quote_odds(&sel(SelectionKind::Single, 5000, 0, 0), MarketType::PickRange, 0),
+            Err(ErrorCode::SelectionOutOfRange.into())
+        );
+        assert_eq!(
+            quote_odds(&sel(SelectionKind::Range, 60, 40, 0), MarketType::PickRange, 0),
+            Err(ErrorCode::SelectionOutOfRange.into())
+        );
+        assert_eq!(
+            quote_odds(&sel(SelectionKind::Range, 1, 99, 0), MarketType::PickRange, 200),
+            Err(ErrorCode::OddsTooLow.into())
+        );
+        assert_eq!(
+            quote_odds(&sel(SelectionKind::Parity, 0, 0, 0), MarketType::EvenOdd, 200),
+            Ok(19_607)
+        );
+    }
 
-fn u16_from(bytes: &[u8], idx: usize) -> u16 {
-    u16::from_le_bytes([bytes[idx % 32], bytes[(idx + 1) % 32]])
-}
-fn u8_from(bytes: &[u8], idx: usize) -> u8 {
-    bytes[idx % 32]
-}
+    #[test]
+    fn odds_round_down_by_at_most_one_bp() {
+        for edge in [0u16, 1, 200, 333, 1_000, MAX_HOUSE_EDGE_BPS] {
+            let denom = 10_000f64 + edge as f64;
+            let cases: [(Selection, MarketType, f64); 4] = [
+                (sel(SelectionKind::Parity, 0, 0, 0), MarketType::EvenOdd, 2.0),
+                (sel(SelectionKind::Modulo, 1, 0, 0), MarketType::ModuloThree, 3.0),
+                (sel(SelectionKind::Range, 1, 33, 0), MarketType::PickRange, 100.0 / 33.0),
+                (sel(SelectionKind::Shape, 1, 255, 2), MarketType::ShapeColor, 72.0 / 6.0),
+            ];
+            for (s, mt, fair) in cases {
+                let exact = fair * 1e8 / denom;
+                let odds = compute_odds_bps(&s, mt, edge).unwrap() as f64;
+                assert!(odds <= exact + 1e-6, "edge {edge}: {odds} > {exact}");
+                assert!(exact - odds < 1.0, "edge {edge}: {odds} vs {exact}");
+            }
+        }
+    }
 
-fn derive_outcome_from_randomness(mt: MarketType, rnd: &[u8; 32]) -> OutcomeType {
-    match mt {
-        MarketType::PickRange => {
-            let v = (u16_from(rnd, 0) % 100) + 1;
-            OutcomeType::Numeric { value: v }
+    #[test]
+    fn payout_rounds_down_by_at_most_one_unit() {
+        for stake in [1u64, 3, 7, 99, 101, 12_345, 1_000_000_007, u64::MAX / 200_000] {
+            for odds in [10_001u32, 13_333, 19_607, 33_333, 990_099] {
+                let payout = compute_payout(stake, odds).unwrap() as u128;
+                let scaled = stake as u128 * odds as u128;
+                assert!(payout * 10_000 <= scaled);
+                assert!(scaled - payout * 10_000 < 10_000);
+            }
         }
-        MarketType::EvenOdd => {
-            let v = (u8_from(rnd, 0) % 2) as u16;
-            OutcomeType::Numeric { value: v }
+        assert_eq!(compute_payout(u64::MAX, 20_000), Err(ErrorCode::Overflow.into()));
+    }
+
+    fn session(spend_cap: u64, expires_at: i64) -> SessionToken {
+        SessionToken {
+            version: SessionToken::VERSION,
+            owner: Pubkey::new_unique(),
+            session_signer: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            spend_cap,
+            spent: 0,
+            expires_at,
+            created_at: 0,
+            bump: 0,
         }
-        MarketType::LastDigit => {
-            let v = (u8_from(rnd, 1) % 10) as u16;
-            OutcomeType::Numeric { value: v }
+    }
+
+    #[test]
+    fn session_spends_up_to_its_cap() {
+        let mut token = session(100, 50);
+        let signer = token.session_signer;
+        authorize_session_spend(&mut token, signer, 60, 10).unwrap();
+        authorize_session_spend(&mut token, signer, 40, 10).unwrap();
+        assert_eq!(token.spent, 100);
+        assert_eq!(
+            authorize_session_spend(&mut token, signer, 1, 10),
+            err(ErrorCode::SessionSpendCapExceeded)
+        );
+        assert_eq!(token.spent, 100);
+
+        let mut token = session(u64::MAX, 50);
+        token.session_signer = signer;
+        token.spent = 1;
+        assert_eq!(
+            authorize_session_spend(&mut token, signer, u64::MAX, 10),
+            err(ErrorCode::Overflow)
+        );
+    }
+
+    #[test]
+    fn session_rejects_other_signers_and_expiry() {
+        let mut token = session(100, 50);
+        let (signer, owner) = (token.session_signer, token.owner);
+        assert_eq!(
+            authorize_session_spend(&mut token, owner, 10, 10),
+            err(ErrorCode::Unauthorized)
+        );
+        assert_eq!(
+            authorize_session_spend(&mut token, signer, 10, 50),
+            err(ErrorCode::SessionExpired)
+        );
+        // Rejected spends leave the budget untouched.
+        assert_eq!(token.spent, 0);
+        authorize_session_spend(&mut token, signer, 10, 49).unwrap();
+    }
+
+    #[test]
+    fn lp_shares_track_equity() {
+        assert_eq!(shares_for_deposit(500, 0, 0), Ok(500));
+        assert_eq!(shares_for_deposit(100, 200, 100), Ok(50));
+        assert_eq!(shares_for_deposit(100, 300, 100), Ok(33));
+        assert_eq!(
+            shares_for_deposit(100, 0, 100),
+            Err(ErrorCode::PoolInsolvent.into())
+        );
+        assert_eq!(amount_for_shares(50, 300, 150), Ok(100));
+        // Equity left after every share was redeemed goes to the owner, and
+        // a deposit into that pool redeems for what it paid.
+        assert_eq!(residual_shares(0, 0), 0);
+        assert_eq!(residual_shares(40, 100), 0);
+        let owner = residual_shares(40, 0);
+        assert_eq!(owner, 40);
+        let shares = shares_for_deposit(100, 40, owner).unwrap();
+        assert_eq!(shares, 100);
+        assert_eq!(amount_for_shares(shares, 140, owner + shares), Ok(100));
+        assert_eq!(amount_for_shares(owner, 140, owner + shares), Ok(40));
+        for (shares, supply) in [(1, 0), (151, 150)] {
+            assert_eq!(
+                amount_for_shares(shares, 300, supply),
+                Err(ErrorCode::InsufficientShares.into())
+            );
         }
-        MarketType::ModuloThree => {
-            let v = (u8_from(rnd, 2) % 3) as u16;
-            OutcomeType::Numeric { value: v }
+        // Rounding never lets a deposit redeem for more than it paid.
+        for (amount, equity, supply) in [
+            (1u64, 3u64, 2u64),
+            (7, 1_000, 999),
+            (10, 999, 1_000),
+            (1_000_003, 7, 1_000_000_000_000),
+            (u64::MAX / 4, 3, 1),
+        ] {
+            let shares = shares_for_deposit(amount, equity, supply).unwrap();
+            let back = amount_for_shares(shares, equity + amount, supply + shares).unwrap();
+            assert!(back <= amount, "{amount} {equity} {supply}");
         }
-        MarketType::PatternOfDay => {
-            let val = u16_from(rnd, 3) % 1000;
-            let mut pid: u8 = 6;
-            if is_prime_u16(val) {
-                pid = 0;
-            } else if is_fib_u16(val) {
-                pid = 1;
-            } else if is_square_u16(val) {
-                pid = 2;
-            } else if (val % 10) == 7 {
-                pid = 3;
-            } else if is_pal_u16(val) {
-                pid = 4;
-            } else if (val % 2) == 0 {
-                pid = 5;
-            }
-            OutcomeType::Pattern {
-                pattern_id: pid,
-                matched_value: val,
+    }
+
+    #[test]
+    fn open_exposure_cannot_dilute_lps() {
+        // One LP owns a 1_000 vault. A bettor stakes 10 at 90x, booking 900
+        // of exposure, and then deposits 100.
+        let supply = 1_000;
+        let vault = 1_010;
+        let book = HouseLiabilities {
+            owed: 0,
+            exposure: 900,
+        };
+        // Priced net of the exposure, the deposit would take over the pool.
+        let equity = book.withdrawal_equity(vault).unwrap();
+        assert_eq!(shares_for_deposit(100, equity, supply), Ok(909));
+
+        let shares = shares_for_deposit(100, book.deposit_equity(vault), supply).unwrap();
+        assert_eq!(shares, 99);
+        let supply = supply + shares;
+        // (vault once the bet loses or wins, LP's funds had nobody deposited)
+        for (vault, alone) in [(1_110, 1_010), (210, 110)] {
+            assert!(amount_for_shares(1_000, vault, supply).unwrap() >= alone);
+            assert!(amount_for_shares(shares, vault, supply).unwrap() <= 100);
+        }
+    }
+
+    #[test]
+    fn liability_scan_splits_owed_from_exposure() {
+        let market = Pubkey::new_unique();
+        let rounds: Vec<Round> = (1..=2u64)
+            .map(|number| {
+                let mut round = settled_round(OutcomeType::Numeric { value: 7 });
+                round.market = market;
+                round.number = number;
+                let seeds = [ROUND_SEED, market.as_ref(), &number.to_le_bytes()];
+                round.bump = Pubkey::find_program_address(&seeds, &crate::ID).1;
+                round
+            })
+            .collect();
+        // The open round is delegated: base only holds its committed snapshot.
+        let mut open = rounds[1].clone();
+        open.status = RoundStatus::Locked as u8;
+        open.delegation_status = DelegationStatus::Delegated as u8;
+        open.open_liability = 900;
+        open.balance_staked = 10;
+        open.balance_credited = 60;
+        let rounds = [rounds[0].clone(), open];
+
+        let keys: Vec<Pubkey> = rounds
+            .iter()
+            .map(|round| {
+                let number = round.number.to_le_bytes();
+                let seeds = [ROUND_SEED, market.as_ref(), &number, &[round.bump]];
+                Pubkey::create_program_address(&seeds, &crate::ID).unwrap()
+            })
+            .collect();
+        let mut data: Vec<Vec<u8>> = rounds
+            .iter()
+            .map(|round| {
+                let mut data = Vec::new();
+                round.try_serialize(&mut data).unwrap();
+                data
+            })
+            .collect();
+        let mut lamports = vec![0u64; rounds.len()];
+        let owners = [crate::ID, DELEGATION_PROGRAM_ID];
+        let infos: Vec<AccountInfo> = keys
+            .iter()
+            .zip(data.iter_mut())
+            .zip(lamports.iter_mut())
+            .zip(owners.iter())
+            .map(|(((key, data), lamports), owner)| {
+                AccountInfo::new(key, false, false, lamports, data, owner, false, 0)
+            })
+            .collect();
+        // No jackpot pot has been created for this market.
+        let pot_seeds = [JACKPOT_POT_SEED, market.as_ref()];
+        let pot_key = Pubkey::find_program_address(&pot_seeds, &crate::ID).0;
+        let (mut pot_lamports, system) = (0, Pubkey::default());
+        let pot = AccountInfo::new(
+            &pot_key,
+            false,
+            false,
+            &mut pot_lamports,
+            &mut [],
+            &system,
+            false,
+            0,
+        );
+
+        let mut treasury = Treasury {
+            version: Treasury::VERSION,
+            market,
+            settled_through: 0,
+            streak_liability: 5,
+            total_withdrawn: 0,
+            lp_enabled: true,
+            crank_reward: 0,
+            parlay_liability: 7,
+        };
+        let liabilities =
+            scan_house_liabilities(&mut treasury, &market, 2, &infos, &pot, &crate::ID).unwrap();
+        assert_eq!(
+            liabilities,
+            HouseLiabilities {
+                owed: 50,
+                exposure: 912,
             }
+        );
+        assert_eq!(liabilities.deposit_equity(2_000), 1_950);
+        assert_eq!(liabilities.withdrawal_equity(2_000), Ok(1_038));
+        // The cursor moved past the closed-out round, so it may not be passed again.
+        assert_eq!(treasury.settled_through, 1);
+        assert_eq!(
+            scan_house_liabilities(&mut treasury, &market, 2, &infos, &pot, &crate::ID),
+            Err(ErrorCode::InvalidRoundAccount.into())
+        );
+        // A snapshot that reads as settled is not passed until it is back on base.
+        let mut returning = rounds[1].clone();
+        returning.status = RoundStatus::Settled as u8;
+        returning.open_liability = 0;
+        returning.balances_synced = true;
+        assert!(!round_is_closed_out(&returning));
+        returning.delegation_status = DelegationStatus::Undelegated as u8;
+        assert!(round_is_closed_out(&returning));
+    }
+
+    #[test]
+    fn crank_reward_is_bounded() {
+        let mut params = settled_round(OutcomeType::Pending).params;
+        params.min_stake = 1_000;
+        assert_eq!(check_crank_reward(0, &params), Ok(()));
+        assert_eq!(check_crank_reward(1_000, &params), Ok(()));
+        assert_eq!(
+            check_crank_reward(1_001, &params),
+            err(ErrorCode::ParamOutOfBounds)
+        );
+
+        assert_eq!(crank_reward_due(500, 0, 500), 500);
+        assert_eq!(crank_reward_due(500, 0, 499), 0);
+        assert_eq!(crank_reward_due(500, PAUSE_PAYOUT, 10_000), 0);
+        assert_eq!(crank_reward_due(500, PAUSE_ALL & !PAUSE_PAYOUT, 10_000), 500);
+    }
+
+    #[test]
+    fn legacy_bet_migrates_percent_odds() {
+        let legacy = LegacyBet {
+            user: Pubkey::new_unique(),
+            round: Pubkey::new_unique(),
+            stake: 5,
+            selection: sel(SelectionKind::Parity, 1, 0, 0),
+            odds_pct: 196,
+            settled: false,
+            won: false,
+            payout: 0,
+            placed_at: 0,
+        };
+        // The percent and basis-point encodings of the same odds pay the same.
+        let old_payout = legacy.stake * legacy.odds_pct as u64 / 100;
+        let new_payout = compute_payout(legacy.stake, legacy.odds_pct as u32 * (ODDS_ONE / 100));
+        assert_eq!(new_payout, Ok(old_payout));
+
+        let user = legacy.user;
+        let bet = legacy.upgrade(&Pubkey::default());
+        assert_eq!(bet.odds_bps, 19_600);
+        assert_eq!(bet.rent_payer, user);
+        assert!(!bet.paid_out);
+    }
+
+    #[test]
+    fn legacy_sizes_are_distinct_from_current() {
+        // `migrate_layout` tells v0 and current accounts apart by size alone.
+        let pairs = [
+            (LEGACY_MARKET_SPACE, 8 + Market::INIT_SPACE),
+            (LEGACY_ROUND_SPACE, 8 + Round::INIT_SPACE),
+            (LEGACY_BET_SPACE, 8 + Bet::INIT_SPACE),
+            (LEGACY_STREAK_SPACE, 8 + Streak::INIT_SPACE),
+            (LEGACY_COMMUNITY_ENTRY_SPACE, 8 + CommunityEntry::INIT_SPACE),
+            (LEGACY_JACKPOT_POT_SPACE, 8 + JackpotPot::INIT_SPACE),
+            (LEGACY_PATTERN_CONFIG_SPACE, 8 + PatternConfig::INIT_SPACE),
+            (LEGACY_PERMISSION_GROUP_SPACE, 8 + PermissionGroup::INIT_SPACE),
+        ];
+        for (legacy, current) in pairs {
+            assert_ne!(legacy, current);
         }
-        MarketType::ShapeColor => {
-            let shape = u8_from(rnd, 4) % 4;
-            let color = u8_from(rnd, 5) % 6;
-            let size = u8_from(rnd, 6) % 3;
-            OutcomeType::Shape { shape, color, size }
+    }
+
+    #[test]
+    fn unversioned_body_gains_version_byte() {
+        let pot = JackpotPot {
+            version: JackpotPot::VERSION,
+            market: Pubkey::new_unique(),
+            current_pot: 7,
+            last_winner: None,
+            total_contributed: 9,
+        };
+        let mut current = Vec::new();
+        pot.try_serialize(&mut current).unwrap();
+        // Drop the version byte to get the v0 encoding, padded like an old account.
+        let mut legacy = current.clone();
+        legacy.remove(8);
+        legacy.resize(LEGACY_JACKPOT_POT_SPACE, 0);
+
+        let upgraded = upgrade_layout::<JackpotPot>(&legacy, 0, &Pubkey::default()).unwrap();
+        let mut rewritten = Vec::new();
+        upgraded.try_serialize(&mut rewritten).unwrap();
+        assert_eq!(rewritten, current);
+    }
+
+    #[test]
+    fn market_name_fits_derived_space() {
+        let market = Market {
+            name: "x".repeat(MAX_MARKET_NAME_LEN),
+            ..market(Pubkey::new_unique())
+        };
+        let mut data = Vec::new();
+        market.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + Market::INIT_SPACE);
+    }
+
+    fn market(mint: Pubkey) -> Market {
+        Market {
+            version: Market::VERSION,
+            admin: Pubkey::new_unique(),
+            name: String::new(),
+            is_active: true,
+            last_round: 0,
+            house_edge_bps: 0,
+            mint,
+            market_type: MarketType::PickRange,
+            index: 0,
+            vault_bump: 0,
+            balance_vault_bump: 0,
+            betting_mode: 0,
         }
-        MarketType::Jackpot => {
-            let v = (u8_from(rnd, 7) % 100) as u16;
-            OutcomeType::Numeric { value: v }
+    }
+
+    #[test]
+    fn v0_streak_gains_rent_payer() {
+        let user = Pubkey::new_unique();
+        let streak = Streak {
+            version: Streak::VERSION,
+            user,
+            market: Pubkey::new_unique(),
+            target: 3,
+            current_streak: 1,
+            status: StreakStatus::Failed as u8,
+            last_round: Pubkey::new_unique(),
+            rent_payer: user,
+        };
+        let mut current = Vec::new();
+        streak.try_serialize(&mut current).unwrap();
+        // A v0 streak is the same encoding without the version byte and payer.
+        let mut v0 = current[..8 + Streak::INIT_SPACE - 32].to_vec();
+        v0.remove(8);
+        assert_eq!(v0.len(), LEGACY_STREAK_SPACE);
+
+        let upgraded = upgrade_layout::<Streak>(&v0, 0, &Pubkey::default()).unwrap();
+        let mut rewritten = Vec::new();
+        upgraded.try_serialize(&mut rewritten).unwrap();
+        assert_eq!(rewritten, current);
+    }
+
+    fn settled_round(outcome: OutcomeType) -> Round {
+        Round {
+            version: Round::VERSION,
+            market: Pubkey::new_unique(),
+            number: 4,
+            status: RoundStatus::Settled as u8,
+            inputs_hash: [0; 32],
+            outcome,
+            unsettled_bets: 0,
+            opened_at: 0,
+            lock_scheduled_at: 0,
+            locked_at: 0,
+            commitment_hash: None,
+            revealed_at: 1,
+            player_entropy: [0; 32],
+            entropy_contributions: 0,
+            vrf_seed: [0; 32],
+            vrf_requested_at: 0,
+            vrf_fulfilled: true,
+            delegation_status: 0,
+            settlement_mode: 0,
+            balance_staked: 0,
+            balance_credited: 0,
+            balances_synced: false,
+            open_liability: 0,
+            params: ParamValues {
+                house_edge_bps: 0,
+                min_stake: 0,
+                max_stake: 0,
+                max_predicting_duration: 0,
+                min_lock_duration: 0,
+                dynamic_odds: DynamicOdds::default(),
+                cash_out_fee_bps: 0,
+            },
+            params_revision: 0,
+            open_bets: 0,
+            rent_payer: Pubkey::new_unique(),
+            bump: 0,
+            betting_mode: 0,
+            pools: Vec::new(),
+            exposures: Vec::new(),
         }
-        MarketType::EntropyBattle => {
-            let tee = (u16_from(rnd, 8) % 512) + 1;
-            let chain = (u16_from(rnd, 10) % 512) + 1;
-            let sensor = (u16_from(rnd, 12) % 512) + 1;
-            let winner = determine_entropy_winner(tee, chain, sensor);
-            OutcomeType::Entropy {
-                tee_score: tee,
-                chain_score: chain,
-                sensor_score: sensor,
-                winner,
+    }
+
+    #[test]
+    fn migrated_round_cannot_be_closed() {
+        let mut round = settled_round(OutcomeType::Numeric { value: 7 });
+        round.vrf_fulfilled = false;
+        round.rent_payer = Pubkey::default();
+        let mut data = Vec::new();
+        round.try_serialize(&mut data).unwrap();
+        // Fields after `revealed_at` are zero, like the padding of a v0 round.
+        let mut v0 = data.clone();
+        v0.remove(8);
+        v0.resize(LEGACY_ROUND_SPACE, 0);
+
+        let upgraded = upgrade_layout::<Round>(&v0, 0, &Pubkey::default()).unwrap();
+        assert_eq!(upgraded.version, Round::VERSION);
+        assert_eq!(upgraded.open_bets, ROUND_BETS_UNTRACKED);
+        assert_eq!(upgraded.rent_payer, Pubkey::default());
+        assert_eq!(upgraded.params_revision, 0);
+        assert!(check_stake(&upgraded.params, 1).is_ok());
+        assert_eq!(
+            upgraded.params.max_predicting_duration,
+            MAX_PREDICTING_DURATION
+        );
+        assert_eq!(upgraded.params.min_lock_duration, MIN_LOCK_DURATION);
+        assert_eq!(upgraded.params.cash_out_fee_bps, 0);
+        assert_eq!(upgraded.lock_scheduled_at, 0);
+
+        // A v0 round still taking bets stops taking them: its edge is unknown.
+        round.status = RoundStatus::Predicting as u8;
+        round.opened_at = 100;
+        let mut data = Vec::new();
+        round.try_serialize(&mut data).unwrap();
+        let mut v0 = data.clone();
+        v0.remove(8);
+        v0.resize(LEGACY_ROUND_SPACE, 0);
+        let upgraded = upgrade_layout::<Round>(&v0, 0, &Pubkey::default()).unwrap();
+        assert_eq!(upgraded.lock_scheduled_at, 100);
+        assert_eq!(
+            check_betting_open(&upgraded, 100),
+            err(ErrorCode::BettingClosed)
+        );
+    }
+
+    fn er_ledger() -> MarketLedger {
+        MarketLedger {
+            version: MarketLedger::VERSION,
+            market: Pubkey::new_unique(),
+            total_credited: 0,
+            settled_bets: 0,
+            delegation_status: DelegationStatus::Delegated as u8,
+        }
+    }
+
+    #[test]
+    fn er_payouts_net_into_one_balance_sync() {
+        let mut round = settled_round(OutcomeType::Numeric { value: 42 });
+        round.status = RoundStatus::Locked as u8;
+        round.settlement_mode = SettlementMode::Rollup as u8;
+        let mut bets: Vec<Bet> = [
+            (sel(SelectionKind::Single, 42, 0, 0), 10, 50_000),
+            (sel(SelectionKind::Range, 1, 50, 0), 20, 19_600),
+            (sel(SelectionKind::Single, 7, 0, 0), 30, 50_000),
+        ]
+        .iter()
+        .map(|&(selection, stake, odds_bps)| {
+            let mut bet = pool_bet(selection, stake);
+            let (key, user) = (Pubkey::new_unique(), bet.user);
+            record_bet(
+                &mut bet, &mut round, key, user, user, 0, selection, stake, odds_bps, 0, false,
+                None,
+            )
+            .unwrap();
+            bet
+        })
+        .collect();
+        let mut ledger = er_ledger();
+
+        let payouts: Vec<u64> = bets
+            .iter_mut()
+            .map(|bet| credit_er_bet(&mut round, bet, &mut ledger).unwrap())
+            .collect();
+        assert_eq!(payouts, vec![50, 39, 0]);
+        assert!(bets[0].won && !bets[0].paid_out);
+        assert!(!bets[2].won && bets[2].paid_out);
+        assert_eq!(ledger.total_credited, 89);
+        assert_eq!(ledger.settled_bets, 3);
+        // No token moved: the winnings are one net balance credit on the round.
+        assert_eq!(round.unsettled_bets, 0);
+        assert_eq!(round.open_liability, 0);
+        assert_eq!(round.balance_credited, 89);
+        assert_eq!(unsynced_credits(&round), 89);
+
+        round.status = RoundStatus::Settled as u8;
+        assert!(!round_is_closed_out(&round));
+        round.balances_synced = true;
+        assert!(round_is_closed_out(&round));
+        assert_eq!(unsynced_credits(&round), 0);
+    }
+
+    #[test]
+    fn er_settlement_rejects_settled_and_balance_bets() {
+        let mut round = settled_round(OutcomeType::Numeric { value: 42 });
+        let mut ledger = er_ledger();
+        let mut bet = pool_bet(sel(SelectionKind::Single, 42, 0, 0), 10);
+        bet.funded_from_balance = true;
+        assert_eq!(
+            credit_er_bet(&mut round, &mut bet, &mut ledger),
+            Err(ErrorCode::BalanceFundedBet.into())
+        );
+        bet.funded_from_balance = false;
+        credit_er_bet(&mut round, &mut bet, &mut ledger).unwrap();
+        assert_eq!(
+            credit_er_bet(&mut round, &mut bet, &mut ledger),
+            Err(ErrorCode::AlreadySettled.into())
+        );
+        assert_eq!(ledger.settled_bets, 1);
+    }
+
+    #[test]
+    fn balance_bets_wait_for_the_round_sync() {
+        let mut round = settled_round(OutcomeType::Numeric { value: 42 });
+        round.unsettled_bets = 2;
+        let mut bet = pool_bet(sel(SelectionKind::Single, 42, 0, 0), 10);
+        bet.odds_bps = 50_000;
+        assert_eq!(
+            credit_balance_bet(&mut round, &mut bet),
+            Err(ErrorCode::VaultFundedBet.into())
+        );
+        bet.funded_from_balance = true;
+        assert_eq!(credit_balance_bet(&mut round, &mut bet), Ok(50));
+        // Nothing reaches the balance until `collect_payout` after the sync.
+        assert!(bet.settled && !bet.paid_out);
+        assert_eq!(round.balance_credited, 50);
+        assert_eq!(round.unsettled_bets, 1);
+        assert_eq!(
+            credit_balance_bet(&mut round, &mut bet),
+            Err(ErrorCode::AlreadySettled.into())
+        );
+
+        let mut lost = pool_bet(sel(SelectionKind::Single, 7, 0, 0), 10);
+        lost.funded_from_balance = true;
+        assert_eq!(credit_balance_bet(&mut round, &mut lost), Ok(0));
+        assert!(lost.paid_out);
+    }
+
+    fn pool_round(edge_bps: u16, pools: &[(Selection, u64)]) -> Round {
+        let mut round = settled_round(OutcomeType::Numeric { value: 42 });
+        round.betting_mode = BettingMode::PariMutuel as u8;
+        round.params.house_edge_bps = edge_bps;
+        for (selection, stake) in pools {
+            add_to_pool(&mut round, *selection, *stake).unwrap();
+        }
+        round
+    }
+
+    fn pool_bet(selection: Selection, stake: u64) -> Bet {
+        Bet {
+            version: Bet::VERSION,
+            user: Pubkey::new_unique(),
+            round: Pubkey::new_unique(),
+            stake,
+            selection,
+            odds_bps: ODDS_ONE,
+            settled: false,
+            won: false,
+            payout: 0,
+            placed_at: 0,
+            paid_out: false,
+            funded_from_balance: false,
+            rent_payer: Pubkey::new_unique(),
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn pari_mutuel_winners_split_losing_pools_after_rake() {
+        let exact = sel(SelectionKind::Single, 42, 0, 0);
+        let range = sel(SelectionKind::Range, 1, 50, 0);
+        let miss = sel(SelectionKind::Single, 7, 0, 0);
+        // 400 on winning selections, 600 lost, 5% rake on the losing side.
+        let round = pool_round(500, &[(exact, 100), (range, 300), (miss, 600)]);
+
+        assert_eq!(bet_payout(&pool_bet(exact, 100), &round), Ok((true, 100 + 142)));
+        assert_eq!(bet_payout(&pool_bet(range, 300), &round), Ok((true, 300 + 427)));
+        assert_eq!(bet_payout(&pool_bet(miss, 600), &round), Ok((false, 0)));
+    }
+
+    #[test]
+    fn pari_mutuel_payouts_never_exceed_pool() {
+        let stakes = [1u64, 7, 333, 1_000_000, 9_999_999_999];
+        for edge in [0u16, 250, MAX_HOUSE_EDGE_BPS] {
+            for value in 1..=100u16 {
+                let mut pools = Vec::new();
+                for (i, stake) in stakes.iter().enumerate() {
+                    let a = (value + i as u16 * 17) % 100 + 1;
+                    pools.push((sel(SelectionKind::Single, a, 0, 0), *stake));
+                }
+                let round = pool_round(edge, &pools);
+                let total: u64 = stakes.iter().sum();
+                let paid: u64 = pools
+                    .iter()
+                    .map(|(s, stake)| bet_payout(&pool_bet(*s, *stake), &round).unwrap().1)
+                    .sum();
+                assert!(paid <= total, "edge {edge} value {value}");
+                for (s, stake) in &pools {
+                    let (won, payout) = bet_payout(&pool_bet(*s, *stake), &round).unwrap();
+                    assert!(!won || payout >= *stake, "winners keep their stake");
+                }
             }
         }
-        MarketType::StreakMeter => {
-            let v = (u8_from(rnd, 14) % 100) as u16;
-            OutcomeType::Numeric { value: v }
+    }
+
+    #[test]
+    fn pool_buckets_merge_and_cap() {
+        let mut round = pool_round(0, &[]);
+        let single = sel(SelectionKind::Single, 5, 0, 0);
+        add_to_pool(&mut round, single, 10).unwrap();
+        add_to_pool(&mut round, single, 15).unwrap();
+        assert_eq!(round.pools.len(), 1);
+        assert_eq!(round.pools[0].staked, 25);
+
+        for a in 6..(6 + MAX_POOL_BUCKETS as u16 - 1) {
+            add_to_pool(&mut round, sel(SelectionKind::Single, a, 0, 0), 1).unwrap();
         }
-        MarketType::CommunitySeed => {
-            let final_byte = u8_from(rnd, 15);
-            let mut sh = [0u8; 32];
-            sh.copy_from_slice(rnd);
-            OutcomeType::Community {
-                final_byte,
-                seed_hash: sh,
+        assert_eq!(
+            add_to_pool(&mut round, sel(SelectionKind::Single, 99, 0, 0), 1),
+            err(ErrorCode::PoolFull)
+        );
+        let mut data = Vec::new();
+        round.try_serialize(&mut data).unwrap();
+        assert!(data.len() <= 8 + Round::INIT_SPACE);
+    }
+
+    #[test]
+    fn pool_bets_release_what_they_paid() {
+        let winner = sel(SelectionKind::Single, 42, 0, 0);
+        let loser = sel(SelectionKind::Single, 1, 0, 0);
+        let round = pool_round(0, &[(winner, 50), (loser, 50)]);
+        let mut bet = pool_bet(winner, 50);
+        bet.payout = bet_payout(&bet, &round).unwrap().1;
+        assert_eq!(settled_liability(&round, &bet), Ok(100));
+        bet.funded_from_balance = true;
+        assert_eq!(settled_liability(&round, &bet), Ok(0));
+        assert_eq!(settled_liability(&round, &pool_bet(loser, 50)), Ok(0));
+    }
+
+    #[test]
+    fn dynamic_odds_shade_the_popular_side() {
+        let even = sel(SelectionKind::Parity, 0, 0, 0);
+        let odd = sel(SelectionKind::Parity, 1, 0, 0);
+        let config = DynamicOdds {
+            min_bps: 8_000,
+            max_bps: 11_000,
+            depth: 1_000,
+        };
+        let base = quote_odds(&even, MarketType::EvenOdd, 200).unwrap();
+        let mut round = settled_round(OutcomeType::Pending);
+        round.betting_mode = BettingMode::DynamicOdds as u8;
+        assert_eq!(dynamic_odds(base, &even, &round, &config), Ok(base));
+
+        // 3000 on Even against 1000 on Odd.
+        add_exposure(&mut round, even, 3_000, compute_payout(3_000, base).unwrap()).unwrap();
+        add_exposure(&mut round, odd, 1_000, compute_payout(1_000, base).unwrap()).unwrap();
+        let even_odds = dynamic_odds(base, &even, &round, &config).unwrap();
+        let odd_odds = dynamic_odds(base, &odd, &round, &config).unwrap();
+        assert!(even_odds < base && odd_odds > base);
+        assert!(even_odds >= base * 8 / 10 && odd_odds <= base * 11 / 10);
+
+        // A heavily one-sided book pins both sides to the bounds.
+        let payout = compute_payout(1_000_000, base).unwrap();
+        add_exposure(&mut round, even, 1_000_000, payout).unwrap();
+        assert_eq!(dynamic_odds(base, &even, &round, &config), Ok(base * 8 / 10));
+        assert_eq!(dynamic_odds(base, &odd, &round, &config), Ok(base * 11 / 10));
+    }
+
+    #[test]
+    fn dynamic_odds_never_allow_a_risk_free_pair() {
+        let even = sel(SelectionKind::Parity, 0, 0, 0);
+        let odd = sel(SelectionKind::Parity, 1, 0, 0);
+        let market = Market {
+            market_type: MarketType::EvenOdd,
+            ..market(Pubkey::new_unique())
+        };
+        for depth in [1, 1_000, 1_000_000] {
+            for stake in [1_000, 1_000_000, 1_000_000_000] {
+                let mut round = settled_round(OutcomeType::Pending);
+                round.status = RoundStatus::Predicting as u8;
+                round.betting_mode = BettingMode::DynamicOdds as u8;
+                round.params.house_edge_bps = 200;
+                round.params.dynamic_odds = DynamicOdds {
+                    min_bps: 5_000,
+                    max_bps: MAX_DYNAMIC_ODDS_BPS,
+                    depth,
+                };
+                let first = quote_round_odds(&even, &market, &round).unwrap();
+                let payout = compute_payout(stake, first).unwrap();
+                add_exposure(&mut round, even, stake, payout).unwrap();
+                let second = quote_round_odds(&odd, &market, &round).unwrap();
+                // Backing both sides locks in a profit only when
+                // (o1 - 1)(o2 - 1) >= 1, whatever the stakes.
+                let edge = (first - ODDS_ONE) as u64 * (second - ODDS_ONE) as u64;
+                assert!(edge < ODDS_ONE as u64 * ODDS_ONE as u64);
             }
         }
     }
-}
 
-fn is_prime_u16(n: u16) -> bool {
-    if n < 2 {
-        return false;
+    #[test]
+    fn dynamic_odds_bounds_are_validated() {
+        let ok = DynamicOdds {
+            min_bps: 9_000,
+            max_bps: 10_500,
+            depth: 1,
+        };
+        assert!(ok.validate().is_ok());
+        for bad in [
+            DynamicOdds { min_bps: 0, ..ok },
+            DynamicOdds { min_bps: 10_001, ..ok },
+            DynamicOdds { max_bps: 9_999, ..ok },
+            DynamicOdds { max_bps: MAX_DYNAMIC_ODDS_BPS + 1, ..ok },
+            DynamicOdds { depth: 0, ..ok },
+        ] {
+            assert_eq!(bad.validate(), err(ErrorCode::ParamOutOfBounds));
+        }
+
+        // Bounds travel through the params timelock; unset bounds are allowed
+        // until a market switches to dynamic odds.
+        let current = ParamValues::with_house_edge(200);
+        assert!(current.validate().is_ok());
+        let change = ParamChange {
+            dynamic_odds: Some(ok),
+            ..ParamChange::default()
+        };
+        assert!(change.apply_to(&current).dynamic_odds == ok);
+        let bad = ParamChange {
+            dynamic_odds: Some(DynamicOdds { depth: 0, ..ok }),
+            ..ParamChange::default()
+        };
+        assert_eq!(
+            bad.apply_to(&current).validate(),
+            err(ErrorCode::ParamOutOfBounds)
+        );
+    }
+
+    fn parlay(legs: &[(Pubkey, Selection, u32)]) -> Parlay {
+        let legs: Vec<ParlayLeg> = legs
+            .iter()
+            .map(|(round, selection, odds_bps)| ParlayLeg {
+                round: *round,
+                selection: *selection,
+                odds_bps: *odds_bps,
+                status: LegStatus::Pending as u8,
+            })
+            .collect();
+        Parlay {
+            version: Parlay::VERSION,
+            user: Pubkey::new_unique(),
+            market: Pubkey::new_unique(),
+            id: 0,
+            stake: 1_000,
+            odds_bps: parlay_odds(&legs, 200).unwrap(),
+            house_edge_bps: 200,
+            legs,
+            status: ParlayStatus::Open as u8,
+            payout: 0,
+            paid_out: false,
+            placed_at: 0,
+            rent_payer: Pubkey::new_unique(),
+            bump: 0,
+        }
+    }
+
+    fn cancelled_round() -> Round {
+        let mut round = settled_round(OutcomeType::Pending);
+        round.status = RoundStatus::Cancelled as u8;
+        round.revealed_at = 0;
+        round
+    }
+
+    #[test]
+    fn cancel_round_requires_an_unknown_outcome() {
+        let mut round = settled_round(OutcomeType::Pending);
+        round.status = RoundStatus::Locked as u8;
+        round.revealed_at = 0;
+        round.vrf_fulfilled = false;
+        assert!(check_cancellable(&round, 100).is_ok());
+
+        round.vrf_requested_at = 100;
+        assert_eq!(
+            check_cancellable(&round, 100 + VRF_RETRY_TIMEOUT - 1),
+            err(ErrorCode::VrfRequestPending)
+        );
+        assert!(check_cancellable(&round, 100 + VRF_RETRY_TIMEOUT).is_ok());
+
+        round.vrf_fulfilled = true;
+        assert_eq!(
+            check_cancellable(&round, 1_000),
+            err(ErrorCode::VrfAlreadyFulfilled)
+        );
+
+        round.vrf_requested_at = 0;
+        round.vrf_fulfilled = false;
+        round.commitment_hash = Some([7; 32]);
+        assert_eq!(
+            check_cancellable(&round, 1_000),
+            err(ErrorCode::InvalidState)
+        );
+    }
+
+    #[test]
+    fn parlay_legs_must_be_open_fixed_odds_rounds_in_one_mint() {
+        let mint = Pubkey::new_unique();
+        let leg_market = market(mint);
+        let mut round = settled_round(OutcomeType::Pending);
+        round.status = RoundStatus::Predicting as u8;
+        assert!(check_parlay_leg(&leg_market, &round, &mint, 0).is_ok());
+
+        let inactive = Market {
+            is_active: false,
+            ..market(mint)
+        };
+        assert_eq!(
+            check_parlay_leg(&inactive, &round, &mint, 0),
+            err(ErrorCode::MarketInactive)
+        );
+        assert_eq!(
+            check_parlay_leg(&market(Pubkey::new_unique()), &round, &mint, 0),
+            err(ErrorCode::InvalidParlayLegs)
+        );
+        for mode in [BettingMode::PariMutuel, BettingMode::DynamicOdds] {
+            round.betting_mode = mode as u8;
+            assert_eq!(
+                check_parlay_leg(&leg_market, &round, &mint, 0),
+                err(ErrorCode::BettingModeNotSupported)
+            );
+        }
+    }
+
+    #[test]
+    fn parlay_odds_apply_the_house_edge_once() {
+        let even = sel(SelectionKind::Parity, 0, 0, 0);
+        let mut p = parlay(&[
+            (Pubkey::new_unique(), even, 20_000),
+            (Pubkey::new_unique(), even, 30_000),
+        ]);
+        assert_eq!(p.odds_bps, 58_823);
+        let separately = compute_payout(20_000, 29_411).unwrap() as u32;
+        assert!(p.odds_bps > separately);
+
+        p.legs[1].status = LegStatus::Void as u8;
+        assert_eq!(parlay_odds(&p.legs, 200), Ok(19_607));
+        p.legs[0].odds_bps = u32::MAX;
+        p.legs[1].status = LegStatus::Pending as u8;
+        assert_eq!(
+            parlay_odds(&p.legs, 200),
+            Err(ErrorCode::ParlayOddsTooHigh.into())
+        );
     }
-    if n == 2 {
-        return true;
+
+    #[test]
+    fn parlay_is_lost_on_its_first_losing_leg() {
+        let [a, b, c] = [(); 3].map(|_| Pubkey::new_unique());
+        let hit = sel(SelectionKind::Single, 7, 0, 0);
+        let miss = sel(SelectionKind::Single, 8, 0, 0);
+        let mut p = parlay(&[(a, hit, 20_000), (b, miss, 20_000), (c, hit, 20_000)]);
+        let round = settled_round(OutcomeType::Numeric { value: 7 });
+
+        apply_leg_result(&mut p, &a, &round).unwrap();
+        assert_eq!(p.status, ParlayStatus::Open as u8);
+        assert_eq!(
+            apply_leg_result(&mut p, &a, &round),
+            err(ErrorCode::LegNotFound)
+        );
+        apply_leg_result(&mut p, &b, &round).unwrap();
+        assert_eq!(p.status, ParlayStatus::Lost as u8);
+        assert!(p.paid_out);
+        assert_eq!(p.legs[2].status, LegStatus::Pending as u8);
+        // The remaining leg still resolves, so its round can be closed.
+        apply_leg_result(&mut p, &c, &round).unwrap();
+        assert_eq!(p.status, ParlayStatus::Lost as u8);
+        assert_eq!(p.payout, 0);
+        assert_eq!(p.legs[2].status, LegStatus::Won as u8);
+        assert_eq!(
+            apply_leg_result(&mut p, &c, &round),
+            err(ErrorCode::LegNotFound)
+        );
     }
-    if n % 2 == 0 {
-        return false;
+
+    #[test]
+    fn parlay_voids_cancelled_legs() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let hit = sel(SelectionKind::Single, 7, 0, 0);
+        let mut p = parlay(&[(a, hit, 20_000), (b, hit, 30_000)]);
+        apply_leg_result(&mut p, &a, &cancelled_round()).unwrap();
+        assert_eq!(p.status, ParlayStatus::Open as u8);
+        let mut unrevealed = settled_round(OutcomeType::Pending);
+        unrevealed.revealed_at = 0;
+        assert_eq!(
+            apply_leg_result(&mut p, &b, &unrevealed),
+            err(ErrorCode::OutcomeNotRevealed)
+        );
+        let revealed = settled_round(OutcomeType::Numeric { value: 7 });
+        apply_leg_result(&mut p, &b, &revealed).unwrap();
+        assert_eq!(p.status, ParlayStatus::Won as u8);
+        assert_eq!(p.payout, compute_payout(1_000, 29_411).unwrap());
+
+        let mut q = parlay(&[(a, hit, 20_000), (b, hit, 30_000)]);
+        apply_leg_result(&mut q, &a, &cancelled_round()).unwrap();
+        apply_leg_result(&mut q, &b, &cancelled_round()).unwrap();
+        assert_eq!(q.status, ParlayStatus::Refunded as u8);
+        assert_eq!(q.payout, q.stake);
     }
-    let mut i = 3u16;
-    while (i as u32) * (i as u32) <= (n as u32) {
-        if n % i == 0 {
-            return false;
+
+    #[test]
+    fn cancelled_rounds_refund_their_bets() {
+        let mut round = cancelled_round();
+        round.unsettled_bets = 1;
+        let mut bet = pool_bet(sel(SelectionKind::Single, 7, 0, 0), 500);
+        bet.odds_bps = 90_000;
+        assert_eq!(require_settleable(&round), Ok(()));
+        assert_eq!(bet_payout(&bet, &round), Ok((false, 500)));
+        assert!(!round_is_closed_out(&round));
+        round.unsettled_bets = 0;
+        assert!(round_is_closed_out(&round));
+    }
+
+    #[test]
+    fn cash_out_unwinds_the_bet_from_its_round() {
+        let even = sel(SelectionKind::Parity, 0, 0, 0);
+        let book = |round: &mut Round, stake: u64, odds_bps: u32| -> Bet {
+            let mut bet = pool_bet(even, stake);
+            let user = bet.user;
+            let key = Pubkey::new_unique();
+            record_bet(
+                &mut bet, round, key, user, user, 0, even, stake, odds_bps, 0, false, None,
+            )
+            .unwrap();
+            bet
+        };
+        for mode in [
+            BettingMode::FixedOdds,
+            BettingMode::PariMutuel,
+            BettingMode::DynamicOdds,
+        ] {
+            let odds_bps = match mode {
+                BettingMode::PariMutuel => ODDS_ONE,
+                _ => 19_600,
+            };
+            let mut expected = settled_round(OutcomeType::Pending);
+            expected.betting_mode = mode as u8;
+            let mut round = expected.clone();
+            book(&mut expected, 300, odds_bps);
+            let kept = book(&mut round, 300, odds_bps);
+            let cashed = book(&mut round, 500, odds_bps);
+
+            unrecord_bet(&cashed, &mut round).unwrap();
+            assert_eq!(round.unsettled_bets, 1);
+            assert_eq!(round.open_bets, 1);
+            assert_eq!(round.open_liability, expected.open_liability);
+            assert!(round.pools == expected.pools);
+            assert!(round.exposures == expected.exposures);
+            unrecord_bet(&kept, &mut round).unwrap();
+            assert_eq!(round.open_liability, 0);
+            assert!(round.pools.is_empty());
         }
-        i += 2;
     }
-    true
-}
-fn is_square_u16(n: u16) -> bool {
-    let r = (n as f64).sqrt() as u16;
-    r * r == n
-}
-fn is_pal_u16(n: u16) -> bool {
-    let s = itoa::Buffer::new().format(n).to_string();
-    s.chars().rev().collect::<String>() == s
-}
-fn is_fib_u16(n: u16) -> bool {
-    // quick check from precomputed small fibs up to 987
-    matches!(
-        n,
-        0 | 1 | 2 | 3 | 5 | 8 | 13 | 21 | 34 | 55 | 89 | 144 | 233 | 377 | 610 | 987
-    )
-}
 
-fn compute_streak_odds(target: u16) -> Result<u16> {
-    let base_odds = 200u16;
-    let multiplier = match target {
-        2 => 3,
-        3 => 7,
-        4 => 12,
-        5 => 20,
-        6 => 32,
-        7 => 50,
-        8 => 75,
-        9 => 100,
-        10 => 150,
-        _ => 2,
-    };
-    Ok(base_odds * multiplier / 10)
-}
+    #[test]
+    fn cash_out_needs_a_fee_and_fixed_prices() {
+        let mut round = settled_round(OutcomeType::Pending);
+        round.status = RoundStatus::Predicting as u8;
+        assert_eq!(check_cash_out(&round, 0), err(ErrorCode::CashOutDisabled));
+
+        round.params.cash_out_fee_bps = DEFAULT_CASH_OUT_FEE_BPS;
+        assert!(check_cash_out(&round, 0).is_ok());
+        round.betting_mode = BettingMode::PariMutuel as u8;
+        assert!(check_cash_out(&round, 0).is_ok());
+        round.betting_mode = BettingMode::DynamicOdds as u8;
+        assert_eq!(
+            check_cash_out(&round, 0),
+            err(ErrorCode::BettingModeNotSupported)
+        );
 
-/// Compute odds as a percent multiplier (e.g. 150 => 1.5x)
-/// using real bookmaking economics:
-/// - For mutually exclusive equal bins (N), M = N / (1 + edge)
-/// - For general probability p, M = (1 / p) / (1 + edge)
-/// Where edge = market.house_edge_bps / 10_000. Returned value is M * 100.
-fn compute_odds_bps(sel: &Selection, market: &Market) -> Result<u16> {
-    let market_type = market.market_type;
-    let edge_bps: u64 = (market.house_edge_bps as u64).min(10_000);
-    let denom: u64 = 10_000 + edge_bps; // 1 + edge in bps
-
-    // helper: from N equal outcomes -> odds_pct
-    let from_equal_bins = |n: u64| -> u16 {
-        let m_x100 = n.saturating_mul(1_000_000) / denom; // (N * 10000 * 100) / denom
-        m_x100.min(u16::MAX as u64) as u16
-    };
+        let mut params = ParamValues::with_house_edge(200);
+        assert!(params.cash_out_fee_bps > 0);
+        params.cash_out_fee_bps = MAX_CASH_OUT_FEE_BPS + 1;
+        assert_eq!(params.validate(), err(ErrorCode::ParamOutOfBounds));
+    }
 
-    // helper: from probability p = num/den -> odds_pct
-    let from_probability = |num: u64, den: u64| -> u16 {
-        if num == 0 || den == 0 {
-            return 0;
-        }
-        // M*100 = (1/p) * 100 / (1 + edge) = (den/num) * 100 * (10000/denom)
-        let m_x100 = den
-            .saturating_mul(100)
-            .saturating_mul(10_000)
-            .saturating_div(num)
-            .saturating_div(denom);
-        m_x100.min(u16::MAX as u64) as u16
-    };
+    #[test]
+    fn cash_out_fee_is_capped() {
+        assert_eq!(cash_out_fee(1_000, 0), Ok(0));
+        assert_eq!(cash_out_fee(1_000, 250), Ok(25));
+        assert_eq!(cash_out_fee(1_000, u16::MAX), Ok(500));
+        let max_fee = cash_out_fee(u64::MAX, MAX_CASH_OUT_FEE_BPS);
+        assert_eq!(max_fee, Ok(u64::MAX / 2));
+    }
 
-    match market_type {
-        MarketType::EvenOdd => {
-            // 2 bins
-            Ok(from_equal_bins(2))
-        }
-        MarketType::ModuloThree => Ok(from_equal_bins(3)),
-        MarketType::LastDigit => Ok(from_equal_bins(10)),
-        MarketType::Jackpot => Ok(from_equal_bins(100)),
-        MarketType::EntropyBattle => Ok(from_equal_bins(3)),
-        MarketType::PickRange => {
-            match sel.kind {
-                x if x == SelectionKind::Range as u8 => {
-                    let width: u64 = if sel.b >= sel.a {
-                        (sel.b - sel.a + 1) as u64
-                    } else {
-                        0
-                    };
-                    if width == 0 {
-                        return Ok(0);
-                    }
-                    if 100 % width == 0 {
-                        let n = 100u64 / width; // equal partitions
-                        Ok(from_equal_bins(n))
-                    } else {
-                        // generic probability p = width/100
-                        Ok(from_probability(width, 100))
-                    }
+    const ROULETTE_BETS: [(SelectionKind, usize, usize); 9] = [
+        // (kind, valid selections, pockets each covers)
+        (SelectionKind::RouletteStraight, 37, 1),
+        (SelectionKind::RouletteSplit, 60, 2),
+        (SelectionKind::RouletteStreet, 12, 3),
+        (SelectionKind::RouletteCorner, 22, 4),
+        (SelectionKind::RouletteDozen, 3, 12),
+        (SelectionKind::RouletteColumn, 3, 12),
+        (SelectionKind::RouletteColor, 2, 18),
+        (SelectionKind::RouletteHighLow, 2, 18),
+        (SelectionKind::RouletteParity, 2, 18),
+    ];
+
+    /// Every selection `validate_selection` accepts for `kind`.
+    fn roulette_selections(kind: SelectionKind) -> Vec<Selection> {
+        let mut valid = Vec::new();
+        for a in 0..=40u16 {
+            for b in 0..=40u16 {
+                let s = sel(kind, a, b, 0);
+                if validate_selection(&s, MarketType::Roulette).is_ok() {
+                    valid.push(s);
                 }
-                x if x == SelectionKind::Single as u8 => Ok(from_equal_bins(100)),
-                _ => Ok(from_equal_bins(2)),
-            }
-        }
-        MarketType::ShapeColor => {
-            // Total combos = 4 * 6 * 3 = 72
-            let shapes = if sel.a == 255 { 4 } else { 1 };
-            let colors = if sel.b == 255 { 6 } else { 1 };
-            let sizes = if sel.c == 255 { 3 } else { 1 };
-            let matched: u64 = (shapes * colors * sizes) as u64;
-            let total: u64 = 72;
-            Ok(from_probability(matched, total))
-        }
-        MarketType::PatternOfDay => {
-            // Precomputed precedence-adjusted counts for 0..=999 as in derive_outcome_from_randomness
-            // [Prime, Fib, Square, EndsWith7, Palindrome, Even, Odd]
-            let counts: [u64; 7] = [168, 10, 29, 52, 73, 437, 231];
-            let pid = sel.a as usize;
-            let total: u64 = 1000;
-            let p_num = if pid < counts.len() {
-                counts[pid]
-            } else {
-                counts[6]
-            };
-            Ok(from_probability(p_num, total))
-        }
-        MarketType::CommunitySeed => {
-            // Selection fields: a = chosen byte, b = tolerance (0..8)
-            let t: u8 = (sel.b as u8).min(8);
-            // p = sum_{k=0..t} C(8,k) / 256
-            let mut num: u64 = 0;
-            for k in 0..=t {
-                num += n_choose_k(8, k as u32) as u64;
             }
-            Ok(from_probability(num, 256))
-        }
-        MarketType::StreakMeter => {
-            // Not bet-based here; provide a conservative default (2 bins)
-            Ok(from_equal_bins(2))
         }
+        valid
     }
-}
 
-fn n_choose_k(n: u32, k: u32) -> u32 {
-    if k > n {
-        return 0;
+    fn covered(s: &Selection) -> Vec<u16> {
+        (0..=36)
+            .filter(|v| evaluate_winner(s, &OutcomeType::Numeric { value: *v }).unwrap())
+            .collect()
     }
-    if k == 0 || k == n {
-        return 1;
+
+    /// Table position of pocket `n` (1-36) as (row, column).
+    fn cell(n: u16) -> (u16, u16) {
+        ((n - 1) / 3, (n - 1) % 3)
     }
-    let mut k = k.min(n - k);
-    let mut numer: u64 = 1;
-    let mut denom: u64 = 1;
-    for i in 0..k {
-        numer = numer.saturating_mul((n - i) as u64);
-        denom = denom.saturating_mul((i + 1) as u64);
+
+    #[test]
+    fn roulette_accepts_exactly_the_table_bets() {
+        for (kind, count, pockets) in ROULETTE_BETS {
+            let valid = roulette_selections(kind);
+            assert_eq!(valid.len(), count, "kind {}", kind as u8);
+            for s in &valid {
+                assert_eq!(covered(s).len(), pockets, "{} {} {}", s.kind, s.a, s.b);
+                assert_eq!(
+                    validate_selection(&sel(kind, s.a, s.b, 1), MarketType::Roulette),
+                    err(ErrorCode::SelectionOutOfRange)
+                );
+            }
+        }
     }
-    (numer / denom) as u32
-}
 
-fn evaluate_winner(sel: &Selection, outcome: &OutcomeType) -> Result<bool> {
-    match outcome {
-        OutcomeType::Numeric { value } => {
-            let v = *value;
-            let won = match sel.kind {
-                x if x == SelectionKind::Single as u8 => v == sel.a,
-                x if x == SelectionKind::Range as u8 => v >= sel.a && v <= sel.b,
-                x if x == SelectionKind::Parity as u8 => {
-                    let even = (v % 2) == 0;
-                    let want_even = sel.a == 0;
-                    even == want_even
-                }
-                x if x == SelectionKind::Digit as u8 => (v % 10) == (sel.a % 10),
-                x if x == SelectionKind::Modulo as u8 => (v % 3) == (sel.a % 3),
-                _ => false,
+    #[test]
+    fn roulette_inside_bets_cover_neighbouring_pockets() {
+        for s in roulette_selections(SelectionKind::RouletteSplit) {
+            let (a, b) = (s.a, s.b);
+            let adjacent = if a == 0 {
+                (1..=3).contains(&b)
+            } else {
+                let ((ra, ca), (rb, cb)) = (cell(a), cell(b));
+                (ra == rb && cb == ca + 1) || (ca == cb && rb == ra + 1)
             };
-            Ok(won)
+            assert!(adjacent, "split {a}/{b}");
         }
-        OutcomeType::Shape { shape, color, size } => {
-            if sel.kind != SelectionKind::Shape as u8 {
-                return Ok(false);
-            }
-            let matches = (sel.a == 255 || sel.a == *shape as u16)
-                && (sel.b == 255 || sel.b == *color as u16)
-                && (sel.c == 255 || sel.c == *size as u16);
-            Ok(matches)
+        for s in roulette_selections(SelectionKind::RouletteStreet) {
+            let rows: Vec<u16> = covered(&s).iter().map(|v| cell(*v).0).collect();
+            assert_eq!(rows, [cell(s.a).0; 3]);
         }
-        OutcomeType::Pattern {
-            pattern_id,
-            matched_value,
-        } => {
-            if sel.kind != SelectionKind::Pattern as u8 {
-                return Ok(false);
-            }
-            Ok(sel.a == *pattern_id as u16 && *matched_value > 0)
+        for s in roulette_selections(SelectionKind::RouletteCorner) {
+            let cells: Vec<(u16, u16)> = covered(&s).iter().map(|v| cell(*v)).collect();
+            let (r, c) = cell(s.a);
+            assert_eq!(cells, [(r, c), (r, c + 1), (r + 1, c), (r + 1, c + 1)]);
         }
-        OutcomeType::Entropy { winner, .. } => {
-            if sel.kind != SelectionKind::Entropy as u8 {
-                return Ok(false);
+    }
+
+    #[test]
+    fn roulette_outside_bets_split_one_to_thirty_six() {
+        // Red is odd in 1-10 and 19-28, even in 11-18 and 29-36.
+        let red = |n: u16| ((1..=10).contains(&n) || (19..=28).contains(&n)) == (n % 2 == 1);
+        for n in 1..=36u16 {
+            let (row, column) = cell(n);
+            let expected = [
+                (SelectionKind::RouletteDozen, row / 4),
+                (SelectionKind::RouletteColumn, column),
+                (SelectionKind::RouletteColor, if red(n) { 0 } else { 1 }),
+                (SelectionKind::RouletteHighLow, if n > 18 { 1 } else { 0 }),
+                (SelectionKind::RouletteParity, n % 2),
+            ];
+            for (kind, winner) in expected {
+                for s in roulette_selections(kind) {
+                    let won = covered(&s).contains(&n);
+                    assert_eq!(won, s.a == winner, "{n} on {}", kind as u8);
+                }
             }
-            Ok(sel.a == *winner as u16)
         }
-        OutcomeType::Community { final_byte, .. } => {
-            if sel.kind != SelectionKind::Community as u8 {
-                return Ok(false);
+        // Zero only pays straight-up and on the three zero splits.
+        let zero = OutcomeType::Numeric { value: 0 };
+        let mut zero_winners = 0;
+        for (kind, ..) in ROULETTE_BETS {
+            for s in roulette_selections(kind) {
+                if evaluate_winner(&s, &zero).unwrap() {
+                    zero_winners += 1;
+                }
             }
-            let distance = calculate_hamming_distance(sel.a as u8, *final_byte);
-            let tolerance = sel.b as u8;
-            Ok(distance <= tolerance)
         }
-        OutcomeType::Pending => Ok(false),
+        assert_eq!(zero_winners, 4);
     }
-}
 
-fn is_prime(n: u16) -> bool {
-    if n < 2 {
-        return false;
-    }
-    if n == 2 {
-        return true;
-    }
-    if n % 2 == 0 {
-        return false;
-    }
-    let sqrt = (n as f64).sqrt() as u16;
-    for i in (3..=sqrt).step_by(2) {
-        if n % i == 0 {
-            return false;
-        }
+    #[test]
+    fn roulette_odds_follow_pocket_count() {
+        let quote = |kind, a, b, edge| {
+            compute_odds_bps(&sel(kind, a, b, 0), MarketType::Roulette, edge).unwrap()
+        };
+        assert_eq!(quote(SelectionKind::RouletteStraight, 17, 0, 0), 370_000);
+        assert_eq!(quote(SelectionKind::RouletteSplit, 0, 2, 0), 185_000);
+        assert_eq!(quote(SelectionKind::RouletteStreet, 4, 0, 0), 123_333);
+        assert_eq!(quote(SelectionKind::RouletteCorner, 1, 0, 0), 92_500);
+        assert_eq!(quote(SelectionKind::RouletteDozen, 2, 0, 0), 30_833);
+        assert_eq!(quote(SelectionKind::RouletteColor, 1, 0, 0), 20_555);
+        // A 2.7% edge prices a straight-up bet like a casino's 35 to 1.
+        assert_eq!(quote(SelectionKind::RouletteStraight, 17, 0, 270), 360_272);
     }
-    true
-}
 
-fn is_fibonacci(n: u16) -> bool {
-    let fibs: [u16; 17] = [
-        0, 1, 1, 2, 3, 5, 8, 13, 21, 34, 55, 89, 144, 233, 377, 610, 987,
-    ];
-    fibs.contains(&n)
-}
-
-fn is_perfect_square(n: u16) -> bool {
-    let sqrt = (n as f64).sqrt() as u16;
-    sqrt * sqrt == n
-}
-
-fn ends_with_seven(n: u16) -> bool {
-    n % 10 == 7
-}
-
-fn is_palindrome(n: u16) -> bool {
-    let s = n.to_string();
-    s.chars().eq(s.chars().rev())
-}
-
-pub fn matches_pattern(value: u16, pattern_type: PatternType) -> bool {
-    match pattern_type {
-        PatternType::Prime => is_prime(value),
-        PatternType::Fibonacci => is_fibonacci(value),
-        PatternType::PerfectSquare => is_perfect_square(value),
-        PatternType::EndsWithSeven => ends_with_seven(value),
-        PatternType::Palindrome => is_palindrome(value),
-        PatternType::Even => value % 2 == 0,
-        PatternType::Odd => value % 2 == 1,
+    #[test]
+    fn roulette_outcomes_land_on_the_wheel() {
+        let mut hits = [0u32; 37];
+        for x in 0..=u16::MAX {
+            let mut rnd = [0u8; 32];
+            rnd[16..18].copy_from_slice(&x.to_le_bytes());
+            match derive_outcome_from_randomness(MarketType::Roulette, &rnd) {
+                OutcomeType::Numeric { value } => hits[value as usize] += 1,
+                _ => panic!("expected a pocket"),
+            }
+        }
+        assert!(hits.iter().all(|h| *h == 1771 || *h == 1772));
     }
 }
@@ -16,6 +16,7 @@ pub enum MarketType {
     EntropyBattle,
     StreakMeter,
     CommunitySeed,
+    Roulette,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,6 +86,7 @@ impl TeeEngine {
             MarketType::CommunitySeed => {
                 self.generate_community_outcome(params.community_seeds.unwrap_or_default())
             },
+            MarketType::Roulette => self.generate_roulette_outcome(&mut rng),
         };
 
         self.create_attestation(round_id, market_type, outcome)
@@ -145,6 +147,11 @@ impl TeeEngine {
         OutcomeType::Numeric { value }
     }
 
+    fn generate_roulette_outcome(&self, rng: &mut TeeRng) -> OutcomeType {
+        let value = (rng.next_u32() % 37) as u16; // European wheel: 0-36
+        OutcomeType::Numeric { value }
+    }
+
     fn generate_entropy_outcome(&self, rng: &mut TeeRng, chain_hash: Option<[u8; 32]>) -> OutcomeType {
         let tee_bytes = self.generate_entropy_bytes(rng, 32);
         let tee_score = Self::calculate_entropy_score(&tee_bytes);
@@ -443,6 +450,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_roulette_generation() {
+        let mut engine = TeeEngine::new();
+        for i in 0..100 {
+            let outcome = engine.generate_outcome(
+                format!("roulette-{}", i),
+                MarketType::Roulette,
+                OutcomeParams::default()
+            ).unwrap();
+
+            match outcome.outcome {
+                OutcomeType::Numeric { value } => assert!(value <= 36),
+                _ => panic!("Expected Numeric outcome"),
+            }
+        }
+    }
+
     #[test]
     fn test_streak_tracking() {
         let mut engine = TeeEngine::new();
@@ -14,10 +14,13 @@ export class BetsController {
       throw new Error('User not authenticated');
     }
 
-    const { roundId, selection, stake } = request.body as {
+    const { roundId, selection, stake, playerSeed, sessionSigner, minOddsBps } = request.body as {
       roundId: string;
       selection: any;
       stake: number;
+      playerSeed?: string;
+      sessionSigner?: string;
+      minOddsBps?: number;
     };
 
     const result = await betsService.createBetTransaction(
@@ -25,7 +28,10 @@ export class BetsController {
       request.user.walletAddress,
       roundId,
       selection,
-      stake
+      stake,
+      playerSeed,
+      sessionSigner,
+      minOddsBps
     );
 
     return success(reply, result, 'Bet transaction created');
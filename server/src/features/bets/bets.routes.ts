@@ -22,6 +22,9 @@ export async function betsRoutes(fastify: FastifyInstance) {
             roundId: { type: 'string', minLength: 1 },
             selection: { type: 'object' },
             stake: { type: 'number', minimum: 0, maximum: 100000000000 },
+            playerSeed: { type: 'string', pattern: '^[0-9a-fA-F]{64}$' },
+            sessionSigner: { type: 'string', minLength: 32, maxLength: 44 },
+            minOddsBps: { type: 'integer', minimum: 0, maximum: 4294967295 },
           },
         },
         response: {
@@ -20,9 +20,15 @@ export class BetsService {
     userWalletAddress: string,
     roundId: string,
     selection: any,
-    stake: number
+    stake: number,
+    playerSeedHex?: string,
+    sessionSignerAddress?: string,
+    minOddsBps?: number
   ) {
     if (stake <= 0) throw new ValidationError('Stake must be positive');
+    if (playerSeedHex !== undefined && !/^[0-9a-fA-F]{64}$/.test(playerSeedHex)) {
+      throw new ValidationError('playerSeed must be 32 bytes of hex');
+    }
 
     const round = await Round.findById(roundId)
       .populate({ path: 'marketId', model: 'Market' })
@@ -51,6 +57,14 @@ export class BetsService {
     }
     if (!marketConfig.mintAddress) throw new ValidationError('Missing mintAddress in market config');
     const mint = new PublicKey(marketConfig.mintAddress);
+    let sessionSigner: PublicKey | null = null;
+    if (sessionSignerAddress) {
+      try {
+        sessionSigner = new PublicKey(sessionSignerAddress);
+      } catch (e) {
+        throw new ValidationError('Invalid sessionSigner public key');
+      }
+    }
 
     const selectionEncoded = this.encodeSelection(selection, ((round.marketId as any).type) as MarketType);
 
@@ -72,7 +86,13 @@ export class BetsService {
       selectionEncoded,
       stake,
       mint,
-      { useER: false }
+      {
+        useER: false,
+        playerSeed: playerSeedHex ? Buffer.from(playerSeedHex, 'hex') : null,
+        sessionSigner,
+        minOddsBps,
+        maxStake: stake,
+      }
     );
 
     const serializedTransaction = transaction.serialize({ requireAllSignatures: false, verifySignatures: false });
@@ -82,7 +102,9 @@ export class BetsService {
     return {
       transaction: serializedTransaction.toString('base64'),
       betPda: betPda.toString(),
-      message: 'Sign this transaction in your wallet to place bet',
+      message: sessionSigner
+        ? 'Sign this transaction with your session key to place bet'
+        : 'Sign this transaction in your wallet to place bet',
       vaultPda: vaultPda ? vaultPda.toString() : undefined,
       needsVaultAta,
       mint: mint.toString(),
@@ -344,6 +366,7 @@ export class BetsService {
       case MarketType.PICK_RANGE:
         if (selection.type === 'range') {
           if (selection.min < 1 || selection.max > 100 || selection.min > selection.max) throw new ValidationError('Invalid range selection');
+          if (selection.min === 1 && selection.max === 100) throw new ValidationError('Range covers every outcome');
         } else if (selection.type === 'single') {
           if (selection.value < 1 || selection.value > 100) throw new ValidationError('Invalid single number selection');
         }
@@ -363,18 +386,20 @@ export class BetsService {
         break;
       }
       case MarketType.SHAPE_COLOR: {
-        const validByte = (n: any) => n === undefined || (Number.isInteger(n) && n >= 0 && n <= 255);
-        if (!validByte(selection.shape) || !validByte(selection.color) || !validByte(selection.size)) {
+        const validField = (n: any, max: number) => n === undefined || (Number.isInteger(n) && n >= 0 && n <= max);
+        if (!validField(selection.shape, 3) || !validField(selection.color, 5) || !validField(selection.size, 2)) {
           throw new ValidationError('Invalid shape selection');
         }
+        if (selection.shape === undefined && selection.color === undefined && selection.size === undefined) {
+          throw new ValidationError('Shape selection covers every outcome');
+        }
         break;
       }
       case MarketType.ENTROPY_BATTLE:
         if (!['tee', 'chain', 'sensor'].includes(selection.source)) throw new ValidationError('Invalid entropy source selection');
         break;
       case MarketType.STREAK_METER:
-        if (selection.target < 2 || selection.target > config.MAX_STREAK_TARGET) throw new ValidationError('Invalid streak target');
-        break;
+        throw new ValidationError('Streak meter markets do not take bets; use the streaks endpoints');
       case MarketType.COMMUNITY_SEED:
         if (selection.byte < 0 || selection.byte > 255) throw new ValidationError('Invalid community seed byte');
         break;
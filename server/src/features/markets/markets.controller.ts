@@ -42,4 +42,10 @@ export class MarketsController {
     const result = await marketsService.updateHouseEdgeBpsByType(type, houseEdgeBps, request.user!);
     return success(reply, result);
   });
+
+  applyPendingParams = asyncHandler(async (request: FastifyRequest, reply: FastifyReply) => {
+    const { marketId } = request.params as { marketId: string };
+    const result = await marketsService.applyPendingParams(marketId);
+    return success(reply, result);
+  });
 }
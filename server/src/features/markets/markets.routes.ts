@@ -100,8 +100,8 @@ export async function marketsRoutes(fastify: FastifyInstance) {
       preHandler: [requireAuth],
       schema: {
         tags: ['Markets'],
-        summary: 'Update house edge (bps)',
-        description: 'Admin: update on-chain market house_edge_bps and mirror to DB config',
+        summary: 'Propose house edge (bps)',
+        description: 'Admin: queue a timelocked house_edge_bps change on-chain and record it as pending in DB config',
         params: {
           type: 'object',
           required: ['marketId'],
@@ -111,7 +111,7 @@ export async function marketsRoutes(fastify: FastifyInstance) {
           type: 'object',
           required: ['houseEdgeBps'],
           properties: {
-            houseEdgeBps: { type: 'number', minimum: 0, maximum: 10000 },
+            houseEdgeBps: { type: 'number', minimum: 0, maximum: 7500 },
           },
         },
       },
@@ -125,17 +125,34 @@ export async function marketsRoutes(fastify: FastifyInstance) {
       preHandler: [requireAuth],
       schema: {
         tags: ['Markets'],
-        summary: 'Bulk update house edge (bps) by type',
+        summary: 'Bulk propose house edge (bps) by type',
         body: {
           type: 'object',
           required: ['type', 'houseEdgeBps'],
           properties: {
             type: { type: 'string' },
-            houseEdgeBps: { type: 'number', minimum: 0, maximum: 10000 },
+            houseEdgeBps: { type: 'number', minimum: 0, maximum: 7500 },
           },
         },
       },
     },
     marketsController.updateHouseEdgeByType
   );
+
+  fastify.post(
+    '/:marketId/params/execute',
+    {
+      schema: {
+        tags: ['Markets'],
+        summary: 'Apply pending market parameters',
+        description: 'Execute a matured parameter change on-chain and mirror it to DB config',
+        params: {
+          type: 'object',
+          required: ['marketId'],
+          properties: { marketId: { type: 'string', minLength: 1 } },
+        },
+      },
+    },
+    marketsController.applyPendingParams
+  );
 }
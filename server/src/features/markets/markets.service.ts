@@ -7,6 +7,8 @@ import { getMarketConfig } from '@/utils/market-config';
 import { PublicKey } from '@solana/web3.js';
 
 const tossrProgram = new TossrProgramService();
+const PARAM_CHANGE_DELAY_MS = 24 * 60 * 60 * 1000;
+const MAX_HOUSE_EDGE_BPS = 7500;
 
 export class MarketsService {
   async getAllMarkets() {
@@ -134,8 +136,8 @@ export class MarketsService {
   }
 
   async updateHouseEdgeBps(marketId: string, houseEdgeBps: number, user: { id: string; walletAddress: string }) {
-    if (houseEdgeBps < 0 || houseEdgeBps > 10000) {
-      throw new ValidationError('houseEdgeBps must be between 0 and 10000');
+    if (houseEdgeBps < 0 || houseEdgeBps > MAX_HOUSE_EDGE_BPS) {
+      throw new ValidationError(`houseEdgeBps must be between 0 and ${MAX_HOUSE_EDGE_BPS}`);
     }
 
     const market = await Market.findById(marketId).lean();
@@ -151,17 +153,41 @@ export class MarketsService {
     const cfg = getMarketConfig(market.config as unknown);
     const marketPubkey = new PublicKey(cfg.solanaAddress);
 
-    const signature = await tossrProgram.setHouseEdgeBps(marketPubkey, houseEdgeBps, adminKeypair);
+    const signature = await tossrProgram.proposeHouseEdgeBps(marketPubkey, houseEdgeBps, adminKeypair);
 
-    const newConfig = { ...cfg, houseEdgeBps } as any;
+    const effectiveAt = new Date(Date.now() + PARAM_CHANGE_DELAY_MS);
+    const newConfig = { ...cfg, pendingHouseEdgeBps: houseEdgeBps, houseEdgeEffectiveAt: effectiveAt } as any;
     await Market.updateOne({ _id: marketId }, { $set: { config: newConfig } });
 
-    return { marketId, houseEdgeBps, tx: signature };
+    return { marketId, pendingHouseEdgeBps: houseEdgeBps, effectiveAt, tx: signature };
+  }
+
+  async applyPendingParams(marketId: string) {
+    const market = await Market.findById(marketId).lean();
+    if (!market) {
+      throw new NotFoundError('Market');
+    }
+
+    const cfg = getMarketConfig(market.config as unknown);
+    if (cfg.pendingHouseEdgeBps === undefined) {
+      throw new ValidationError('No pending parameter change');
+    }
+    if (cfg.houseEdgeEffectiveAt && new Date(cfg.houseEdgeEffectiveAt).getTime() > Date.now()) {
+      throw new ValidationError('Parameter change timelock has not elapsed');
+    }
+
+    const signature = await tossrProgram.executeMarketParams(new PublicKey(cfg.solanaAddress), getAdminKeypair());
+
+    const { pendingHouseEdgeBps, houseEdgeEffectiveAt: _effectiveAt, ...rest } = cfg;
+    const newConfig = { ...rest, houseEdgeBps: pendingHouseEdgeBps };
+    await Market.updateOne({ _id: marketId }, { $set: { config: newConfig } });
+
+    return { marketId, houseEdgeBps: pendingHouseEdgeBps, tx: signature };
   }
 
   async updateHouseEdgeBpsByType(type: string, houseEdgeBps: number, user: { id: string; walletAddress: string }) {
-    if (houseEdgeBps < 0 || houseEdgeBps > 10000) {
-      throw new ValidationError('houseEdgeBps must be between 0 and 10000');
+    if (houseEdgeBps < 0 || houseEdgeBps > MAX_HOUSE_EDGE_BPS) {
+      throw new ValidationError(`houseEdgeBps must be between 0 and ${MAX_HOUSE_EDGE_BPS}`);
     }
 
     const adminKeypair = getAdminKeypair();
@@ -180,8 +206,9 @@ export class MarketsService {
     for (const market of markets) {
       const cfg = getMarketConfig(market.config as unknown);
       const marketPubkey = new PublicKey(cfg.solanaAddress);
-      const sig = await tossrProgram.setHouseEdgeBps(marketPubkey, houseEdgeBps, adminKeypair);
-      const newConfig = { ...cfg, houseEdgeBps } as any;
+      const sig = await tossrProgram.proposeHouseEdgeBps(marketPubkey, houseEdgeBps, adminKeypair);
+      const effectiveAt = new Date(Date.now() + PARAM_CHANGE_DELAY_MS);
+      const newConfig = { ...cfg, pendingHouseEdgeBps: houseEdgeBps, houseEdgeEffectiveAt: effectiveAt } as any;
       await Market.updateOne({ _id: market._id }, { $set: { config: newConfig } });
       signatures.push(sig);
     }
@@ -9,7 +9,7 @@ import { logger } from '@/utils/logger';
 import { Connection, PublicKey, Transaction } from '@solana/web3.js';
 import { getMarketConfig } from '@/utils/market-config';
 import { roundLifecycleQueue, betSettlementQueue } from '@/jobs/queues';
-import { fetchRoundStateRaw } from '@/solana/round-reader';
+import { fetchRoundStateRaw, isVrfRetryAllowed } from '@/solana/round-reader';
 import {
   getAssociatedTokenAddress,
   createAssociatedTokenAccountInstruction,
@@ -319,6 +319,21 @@ export class RoundsService {
     logger.info({ roundId, delegateTxHash }, 'Round delegated to ER');
   }
 
+  /**
+   * Reads `delegation_status` from the on-chain Round. Falls back to the
+   * recorded delegate/undelegate tx hashes if the account cannot be decoded.
+   */
+  private async isRoundDelegated(round: any, marketPubkey: PublicKey): Promise<boolean> {
+    try {
+      const roundPda = await tossrProgram.getRoundPda(marketPubkey, round.roundNumber);
+      const state = await fetchRoundStateRaw(new Connection(config.SOLANA_RPC_URL), roundPda);
+      if (state && state.delegated !== null) return state.delegated;
+    } catch (error: any) {
+      logger.warn({ roundNumber: round.roundNumber, error: String(error?.message || error) }, 'Failed to read delegation status on-chain');
+    }
+    return Boolean(round.delegateTxHash && !round.undelegateTxHash);
+  }
+
   async lockRound(roundId: string) {
     const round = await Round.findById(roundId).populate({ path: 'marketId', model: 'Market' }).lean();
 
@@ -357,7 +372,7 @@ export class RoundsService {
     const marketConfig = getMarketConfig((round as any).marketId.config as unknown);
     const marketPubkey = new PublicKey(marketConfig.solanaAddress);
 
-    const isDelegated = Boolean((round as any).delegateTxHash && !(round as any).undelegateTxHash);
+    const isDelegated = await this.isRoundDelegated(round, marketPubkey);
 
     let lockTxHash: string;
     try {
@@ -409,30 +424,35 @@ export class RoundsService {
     const marketPubkey = new PublicKey(marketConfig.solanaAddress);
 
     const oracleQueue = new PublicKey(config.VRF_ORACLE_QUEUE);
-    const clientSeed = round.roundNumber % 256;
-
-    try {
-      await tossrProgram.requestRandomnessER(
-        marketPubkey,
-        round.roundNumber,
-        clientSeed,
-        adminKeypair,
-        oracleQueue,
-        { useER: false }
-      );
-      logger.info({ roundId, roundNumber: round.roundNumber }, 'VRF randomness requested');
-    } catch (error: any) {
-      const msg = String(error?.message || 'unknown error');
-      if (!msg.includes('already')) {
-        logger.error({ roundId, roundNumber: round.roundNumber, error: msg }, 'Failed to request VRF randomness');
-        throw error;
-      }
-    }
 
     const roundPda = await tossrProgram.getRoundPda(marketPubkey, round.roundNumber);
     const baseConnection = new Connection(config.SOLANA_RPC_URL);
     const erConnection = new Connection(config.EPHEMERAL_RPC_URL);
+
+    const onChainState = await fetchRoundStateRaw(baseConnection, roundPda);
+    if (isVrfRetryAllowed(onChainState?.vrf ?? null)) {
+      try {
+        await tossrProgram.requestRandomnessER(
+          marketPubkey,
+          round.roundNumber,
+          adminKeypair,
+          oracleQueue,
+          { useER: false }
+        );
+        logger.info({ roundId, roundNumber: round.roundNumber }, 'VRF randomness requested');
+      } catch (error: any) {
+        const msg = String(error?.message || 'unknown error');
+        if (!msg.includes('already')) {
+          logger.error({ roundId, roundNumber: round.roundNumber, error: msg }, 'Failed to request VRF randomness');
+          throw error;
+        }
+      }
+    } else {
+      logger.info({ roundId, roundNumber: round.roundNumber, vrf: onChainState?.vrf }, 'VRF request already on-chain; waiting for callback');
+    }
     const isZeroHex = (value: string | null) => !value || /^0+$/.test(value);
+    const hasRandomness = (state: Awaited<ReturnType<typeof fetchRoundStateRaw>>) =>
+      Boolean(state && (state.vrf ? state.vrf.fulfilled : !isZeroHex(state.inputsHash)));
 
     let randomnessHex: string | null = null;
     for (let attempt = 0; attempt < 30; attempt++) {
@@ -440,8 +460,8 @@ export class RoundsService {
         fetchRoundStateRaw(erConnection, roundPda),
         fetchRoundStateRaw(baseConnection, roundPda),
       ]);
-      const state = erState && !isZeroHex(erState.inputsHash) ? erState : baseState;
-      if (state && state.inputsHash && !isZeroHex(state.inputsHash)) {
+      const state = hasRandomness(erState) ? erState : baseState;
+      if (state && state.inputsHash && hasRandomness(state)) {
         randomnessHex = state.inputsHash;
         break;
       }
@@ -489,7 +509,7 @@ export class RoundsService {
     const commitmentHash = Buffer.from(attestation.commitment_hash, 'hex');
     const attestationSig = Buffer.from(attestation.signature, 'hex');
 
-    const isDelegated = Boolean((round as any).delegateTxHash && !(round as any).undelegateTxHash);
+    const isDelegated = await this.isRoundDelegated(round, marketPubkey);
     if (isDelegated) {
       await Round.updateOne({ _id: roundId }, { $set: { commitTxHash: 'er-skip' } });
       await roundLifecycleQueue.add('reveal-outcome', { roundId }, { jobId: `reveal-${roundId}`, delay: config.LOCK_DURATION_SECONDS * 1000 });
@@ -568,7 +588,7 @@ export class RoundsService {
     const marketConfig = getMarketConfig((round as any).marketId.config as unknown);
     const marketPubkey = new PublicKey(marketConfig.solanaAddress);
 
-    const isDelegated = Boolean((round as any).delegateTxHash && !(round as any).undelegateTxHash);
+    const isDelegated = await this.isRoundDelegated(round, marketPubkey);
     let revealTxHash: string;
 
     if (outcome.Numeric) {
@@ -614,7 +634,7 @@ export class RoundsService {
           fetchRoundStateRaw(erConnection, roundPda),
           fetchRoundStateRaw(baseConnection, roundPda),
         ]);
-        state = (Boolean((round as any).delegateTxHash && !(round as any).undelegateTxHash)
+        state = (isDelegated
           ? (erState || baseState)
           : (baseState || erState));
         if (state && state.outcome) break;
@@ -646,7 +666,6 @@ export class RoundsService {
 
         if (!outcomeMatches) throw new Error('Reveal verification failed: outcome mismatch');
         // Only require inputsHash on base layer; ER will be committed shortly
-        const isDelegated = Boolean((round as any).delegateTxHash && !(round as any).undelegateTxHash);
         if (!isDelegated && !inputsMatch) throw new Error('Reveal verification failed: inputsHash mismatch');
         logger.info({ roundId }, 'Reveal verification passed');
       }
@@ -3,6 +3,7 @@ import { RoundStatus } from '@/shared/types';
 import { config } from '@/config/env';
 import { logger } from '@/utils/logger';
 import { roundLifecycleQueue } from '../queues';
+import { VRF_RETRY_TIMEOUT_SECONDS } from '@/solana/round-reader';
 
 const ACTIVE_JOB_STATES = new Set(['active', 'waiting', 'delayed']);
 
@@ -34,8 +35,11 @@ export async function recoverStalledRounds() {
     const predictingGraceMs = (config.ROUND_DURATION_SECONDS + config.LOCK_DURATION_SECONDS + 60) * 1000;
     const queuedGraceMs = (config.ROUND_RELEASE_INTERVAL_SECONDS + config.ROUND_QUEUE_BUFFER_SECONDS + 60) * 1000;
 
+    const lockedGraceMs = (config.LOCK_DURATION_SECONDS + VRF_RETRY_TIMEOUT_SECONDS + 60) * 1000;
+
     const predictingCutoff = new Date(now - predictingGraceMs);
     const queuedCutoff = new Date(now - queuedGraceMs);
+    const lockedCutoff = new Date(now - lockedGraceMs);
 
     const stalePredicting = await Round.find({
       status: RoundStatus.PREDICTING,
@@ -53,6 +57,25 @@ export async function recoverStalledRounds() {
       }
     }
 
+    // Locked rounds without an attestation are stuck waiting on VRF. The lock job
+    // re-reads the on-chain VRF request and only re-requests once it has timed out.
+    const staleLocked = await Round.find({
+      status: RoundStatus.LOCKED,
+      lockedAt: { $lte: lockedCutoff },
+      attestation: { $exists: false },
+    }).select('_id lockedAt marketId roundNumber').lean();
+
+    for (const round of staleLocked) {
+      const roundId = String((round as any)._id);
+      const requeued = await requeueLockJob(roundId);
+      if (requeued) {
+        logger.warn(
+          { roundId, lockedAt: round.lockedAt, marketId: round.marketId, roundNumber: round.roundNumber },
+          'Recovered locked round awaiting VRF by re-queuing lock job'
+        );
+      }
+    }
+
     const staleQueued = await Round.find({
       status: RoundStatus.QUEUED,
       queuedAt: { $lte: queuedCutoff },
@@ -10,6 +10,8 @@ import { getAdminKeypair } from '@/config/admin-keypair';
 import { PublicKey } from '@solana/web3.js';
 
 const tossrProgram = new TossrProgramService();
+// (bet, user_token) pairs per settle_bets_batch; keeps the transaction under the size limit
+const SETTLE_BATCH_SIZE = 8;
 
 async function processBetSettlementJob(job: Job<SettleBetsJobData>) {
   const { roundId } = job.data;
@@ -58,24 +60,28 @@ async function processBetSettlementJob(job: Job<SettleBetsJobData>) {
     }
   }
 
-  for (const bet of pendingBets as any[]) {
-    const won = checkBetWon(bet.selection as any, outcome, (round as any).marketId.type);
-    const payout = won ? Number(bet.stake) * Number(bet.odds) : 0;
-
-    // Settle on-chain; program computes win + payout and transfers from vault
+  // Settle on-chain in batches; program computes win + payout and transfers from vault
+  const userKeys = (pendingBets as any[]).map((bet) => new PublicKey(bet.userId.walletAddress));
+  for (let i = 0; i < userKeys.length; i += SETTLE_BATCH_SIZE) {
+    const chunk = userKeys.slice(i, i + SETTLE_BATCH_SIZE);
     try {
-      const userPk = new PublicKey(bet.userId.walletAddress);
-      await tossrProgram.settleBet(
-        marketPubkey,
-        round.roundNumber,
-        userPk,
-        mint,
-        adminKeypair
-      );
+      await tossrProgram.settleBetsBatch(marketPubkey, round.roundNumber, chunk, mint, adminKeypair);
     } catch (e) {
-      logger.error({ betId: bet._id, err: e }, 'On-chain settle failed');
-      // Continue with DB update; system remains eventually consistent
+      logger.warn({ roundId, err: e, size: chunk.length }, 'Batch settle failed; settling bets one by one');
+      for (const userPk of chunk) {
+        try {
+          await tossrProgram.settleBet(marketPubkey, round.roundNumber, userPk, mint, adminKeypair);
+        } catch (err) {
+          logger.error({ roundId, user: userPk.toBase58(), err }, 'On-chain settle failed');
+          // Continue with DB update; system remains eventually consistent
+        }
+      }
     }
+  }
+
+  for (const bet of pendingBets as any[]) {
+    const won = checkBetWon(bet.selection as any, outcome, (round as any).marketId.type);
+    const payout = won ? Number(bet.stake) * Number(bet.odds) : 0;
 
     await Bet.updateOne({ _id: bet._id }, { $set: { status: won ? BetStatus.WON : BetStatus.LOST, payout } });
 
@@ -47,7 +47,7 @@ async function main() {
   const mintPk = new PublicKey(mint)
 
   const { signature, marketPda } = skipOnchain
-    ? { signature: 'skipped', marketPda: await svc.getMarketPdaByIndex(admin.publicKey, index) }
+    ? { signature: 'skipped', marketPda: await svc.getMarketPdaByIndex(index) }
     : await svc.initializeMarket(admin, name, bps, typeCode, index, mintPk)
 
   // Persist in DB (create or update by name)
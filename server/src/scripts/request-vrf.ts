@@ -35,13 +35,11 @@ async function main() {
   const marketCfg = getMarketConfig(round.marketId.config)
   const marketPk = new PublicKey(marketCfg.solanaAddress)
   const isDelegated = Boolean(round.delegateTxHash && !round.undelegateTxHash)
-  const clientSeed = round.roundNumber % 256
 
   const tossr = new TossrProgramService()
   const sig = await tossr.requestRandomnessER(
     marketPk,
     round.roundNumber,
-    clientSeed,
     admin,
     new PublicKey(config.VRF_ORACLE_QUEUE),
     { useER: isDelegated }
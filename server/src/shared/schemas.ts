@@ -93,6 +93,9 @@ export const placeBetSchema = z.object({
     100_000_000_000, // 100 SOL max
     'Maximum stake exceeded'
   ),
+  playerSeed: z.string().regex(/^[0-9a-fA-F]{64}$/, 'playerSeed must be 32 bytes of hex').optional(),
+  sessionSigner: z.string().min(32).max(44).optional(),
+  minOddsBps: z.number().int().min(0).max(4294967295).optional(),
 });
 
 // Pagination
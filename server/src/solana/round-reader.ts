@@ -7,12 +7,30 @@ export type Outcome =
   | { Entropy: { tee_score: number; chain_score: number; sensor_score: number; winner: number } }
   | { Community: { final_byte: number; seed_hash: string } };
 
+export type VrfRequestState = {
+  seed: string;
+  requestedAt: number;
+  fulfilled: boolean;
+};
+
 export type RoundState = {
   commitmentHash: string | null;
   inputsHash: string | null;
   outcome: Outcome | null;
+  playerEntropy: string | null;
+  vrf: VrfRequestState | null;
+  delegated: boolean | null;
 };
 
+// Must match VRF_RETRY_TIMEOUT in the tossr-engine program.
+export const VRF_RETRY_TIMEOUT_SECONDS = 30;
+
+export function isVrfRetryAllowed(vrf: VrfRequestState | null, nowSeconds = Math.floor(Date.now() / 1000)): boolean {
+  if (!vrf || vrf.requestedAt === 0) return true;
+  if (vrf.fulfilled) return false;
+  return nowSeconds >= vrf.requestedAt + VRF_RETRY_TIMEOUT_SECONDS;
+}
+
 function bytesToHex(bytes: number[] | Uint8Array | null | undefined): string | null {
   if (!bytes) return null;
   const arr = bytes instanceof Uint8Array ? bytes : Uint8Array.from(bytes);
@@ -29,6 +47,7 @@ export async function fetchRoundStateRaw(
     const buf = info.data;
     let o = 0;
     o += 8;
+    o += 1; // version
     o += 32;
     o += 8;
     o += 1;
@@ -80,7 +99,27 @@ export async function fetchRoundStateRaw(
       commitmentHash = Buffer.from(buf.subarray(o, o + 32)).toString('hex');
       o += 32;
     }
-    return { commitmentHash, inputsHash, outcome };
+    o += 8;
+    let playerEntropy: string | null = null;
+    let vrf: VrfRequestState | null = null;
+    if (buf.length >= o + 32 + 4 + 32 + 8 + 1) {
+      playerEntropy = Buffer.from(buf.subarray(o, o + 32)).toString('hex');
+      o += 32;
+      o += 4;
+      const seed = Buffer.from(buf.subarray(o, o + 32)).toString('hex');
+      o += 32;
+      const requestedAt = Number(buf.readBigInt64LE(o));
+      o += 8;
+      const fulfilled = buf.readUInt8(o) === 1;
+      o += 1;
+      vrf = { seed, requestedAt, fulfilled };
+    }
+    let delegated: boolean | null = null;
+    if (buf.length >= o + 1) {
+      delegated = buf.readUInt8(o) === 1;
+      o += 1;
+    }
+    return { commitmentHash, inputsHash, outcome, playerEntropy, vrf, delegated };
   } catch {
     return null;
   }
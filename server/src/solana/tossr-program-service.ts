@@ -47,10 +47,21 @@ const [PROGRAM_IDENTITY_PDA] = PublicKey.findProgramAddressSync(
 );
 const VRF_DEFAULT_QUEUE_PK = new PublicKey('Cuj97ggrhhidhbu39TijNVqE74xvKJ69gDervRUXAxGh');
 
+const CONFIG_SEED = Buffer.from('config');
 const MARKET_SEED = Buffer.from('market');
 const ROUND_SEED = Buffer.from('round');
 const VAULT_SEED = Buffer.from('vault');
 const BET_SEED = Buffer.from('bet');
+const SESSION_SEED = Buffer.from('session');
+const PARAMS_SEED = Buffer.from('params');
+const [CONFIG_PDA] = PublicKey.findProgramAddressSync([CONFIG_SEED], TOSSR_PROGRAM_ID);
+
+export const PAUSE_FLAGS = {
+  BETTING: 1 << 0,
+  REVEAL: 1 << 1,
+  PAYOUT: 1 << 2,
+  DELEGATION: 1 << 3,
+} as const;
 
 export class TossrProgramService {
   private connection: Connection;
@@ -113,20 +124,37 @@ export class TossrProgramService {
     return url;
   }
 
-  async setHouseEdgeBps(
+  getMarketParamsPda(marketId: PublicKey): PublicKey {
+    const [pda] = PublicKey.findProgramAddressSync(
+      [PARAMS_SEED, marketId.toBuffer()],
+      TOSSR_PROGRAM_ID
+    );
+    return pda;
+  }
+
+  // Queues a house edge change; it takes effect once executeMarketParams
+  // runs after the on-chain timelock.
+  async proposeHouseEdgeBps(
     marketId: PublicKey,
     houseEdgeBps: number,
     adminKeypair: Keypair
   ): Promise<string> {
+    const edge = Buffer.alloc(2);
+    edge.writeUInt16LE(houseEdgeBps);
+    // ParamChange: Some(house_edge_bps), then None for the remaining fields
     const data = Buffer.concat([
-      DISCRIMINATORS.SET_HOUSE_EDGE_BPS,
-      Buffer.from(new Uint16Array([houseEdgeBps]).buffer),
+      DISCRIMINATORS.PROPOSE_MARKET_PARAMS,
+      Buffer.from([1]),
+      edge,
+      Buffer.from([0, 0, 0, 0]),
     ]);
 
     const ix = new TransactionInstruction({
       keys: [
-        { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: true },
-        { pubkey: marketId, isSigner: false, isWritable: true },
+        { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
+        { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
+        { pubkey: this.getMarketParamsPda(marketId), isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
       data,
@@ -139,6 +167,45 @@ export class TossrProgramService {
     return this.sendAndConfirm(this.connection, tx, [adminKeypair], 'confirmed');
   }
 
+  async executeMarketParams(
+    marketId: PublicKey,
+    payerKeypair: Keypair
+  ): Promise<string> {
+    const ix = new TransactionInstruction({
+      keys: [
+        { pubkey: marketId, isSigner: false, isWritable: true },
+        { pubkey: this.getMarketParamsPda(marketId), isSigner: false, isWritable: true },
+      ],
+      programId: TOSSR_PROGRAM_ID,
+      data: DISCRIMINATORS.EXECUTE_MARKET_PARAMS,
+    });
+
+    const tx = new Transaction().add(ix);
+    const { blockhash } = await this.connection.getLatestBlockhash();
+    tx.recentBlockhash = blockhash;
+    tx.feePayer = payerKeypair.publicKey;
+    return this.sendAndConfirm(this.connection, tx, [payerKeypair], 'confirmed');
+  }
+
+  // Replaces the program-wide pause bitmask (see PAUSE_FLAGS). Signed by the
+  // guardian or the owner.
+  async setPauseFlags(flags: number, authorityKeypair: Keypair): Promise<string> {
+    const ix = new TransactionInstruction({
+      keys: [
+        { pubkey: authorityKeypair.publicKey, isSigner: true, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: true },
+      ],
+      programId: TOSSR_PROGRAM_ID,
+      data: Buffer.concat([DISCRIMINATORS.SET_PAUSE_FLAGS, Buffer.from([flags & 0xff])]),
+    });
+
+    const tx = new Transaction().add(ix);
+    const { blockhash } = await this.connection.getLatestBlockhash();
+    tx.recentBlockhash = blockhash;
+    tx.feePayer = authorityKeypair.publicKey;
+    return this.sendAndConfirm(this.connection, tx, [authorityKeypair], 'confirmed');
+  }
+
   async placeBet(
     userPublicKey: PublicKey,
     marketId: PublicKey,
@@ -146,7 +213,13 @@ export class TossrProgramService {
     selection: { kind: number; a: number; b: number; c: number },
     stakeAmount: number,
     mint: PublicKey,
-    opts?: { useER?: boolean }
+    opts?: {
+      useER?: boolean;
+      playerSeed?: Uint8Array | null;
+      sessionSigner?: PublicKey | null;
+      minOddsBps?: number;
+      maxStake?: number | null;
+    }
   ): Promise<{ transaction: Transaction; betPda: PublicKey }> {
     const roundNumberBuffer = Buffer.alloc(8);
     roundNumberBuffer.writeBigUInt64LE(BigInt(roundNumber));
@@ -171,6 +244,17 @@ export class TossrProgramService {
       userPublicKey
     );
 
+    // With a session signer the bet is signed and funded (rent) by the session key,
+    // and the stake is drawn through the SessionToken PDA's token delegation.
+    const sessionSigner = opts?.sessionSigner ?? null;
+    const signerPublicKey = sessionSigner ?? userPublicKey;
+    const sessionPda = sessionSigner
+      ? PublicKey.findProgramAddressSync(
+          [SESSION_SEED, userPublicKey.toBuffer(), mint.toBuffer()],
+          TOSSR_PROGRAM_ID
+        )[0]
+      : TOSSR_PROGRAM_ID;
+
     const vaultTokenAccount = await getAssociatedTokenAddress(
       mint,
       vaultPda,
@@ -185,17 +269,38 @@ export class TossrProgramService {
       selBuf.writeUInt16LE(selection.a & 0xffff, 1);
       selBuf.writeUInt16LE(selection.b & 0xffff, 3);
       selBuf.writeUInt16LE(selection.c & 0xffff, 5);
+      const playerSeed = opts?.playerSeed;
+      if (playerSeed && playerSeed.length !== 32) {
+        throw new Error('playerSeed must be 32 bytes');
+      }
+      const seedBuf = playerSeed
+        ? Buffer.concat([Buffer.from([1]), Buffer.from(playerSeed)])
+        : Buffer.from([0]);
+      const minOddsBuf = Buffer.alloc(4);
+      minOddsBuf.writeUInt32LE(opts?.minOddsBps ?? 0);
+      let maxStakeBuf = Buffer.from([0]);
+      if (opts?.maxStake !== undefined && opts?.maxStake !== null) {
+        maxStakeBuf = Buffer.alloc(9);
+        maxStakeBuf.writeUInt8(1, 0);
+        maxStakeBuf.writeBigUInt64LE(BigInt(opts.maxStake), 1);
+      }
       return Buffer.concat([
         DISCRIMINATORS.PLACE_BET,
         selBuf,
         stakeBuf,
+        seedBuf,
+        minOddsBuf,
+        maxStakeBuf,
       ]);
     })();
 
     const instruction = new TransactionInstruction({
       keys: [
-        { pubkey: userPublicKey, isSigner: true, isWritable: true },
+        { pubkey: signerPublicKey, isSigner: true, isWritable: true },
+        { pubkey: userPublicKey, isSigner: false, isWritable: false },
+        { pubkey: sessionPda, isSigner: false, isWritable: Boolean(sessionSigner) },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
         { pubkey: betPda, isSigner: false, isWritable: true },
         { pubkey: userTokenAccount, isSigner: false, isWritable: true },
@@ -217,7 +322,7 @@ export class TossrProgramService {
       this.connection.getAccountInfo(vaultTokenAccount),
     ]);
 
-    if (!opts?.useER) {
+    if (!opts?.useER && !sessionSigner) {
       if (!userAtaInfo) {
         ixs.push(
           createAssociatedTokenAccountInstruction(
@@ -263,7 +368,7 @@ export class TossrProgramService {
     if (blockhashInfo.lastValidBlockHeight !== undefined) {
       (transaction as any).lastValidBlockHeight = blockhashInfo.lastValidBlockHeight;
     }
-    transaction.feePayer = userPublicKey;
+    transaction.feePayer = signerPublicKey;
 
     return { transaction, betPda };
   }
@@ -359,6 +464,17 @@ export class TossrProgramService {
       userPublicKey
     );
 
+    // With a session signer the bet is signed and funded (rent) by the session key,
+    // and the stake is drawn through the SessionToken PDA's token delegation.
+    const sessionSigner = opts?.sessionSigner ?? null;
+    const signerPublicKey = sessionSigner ?? userPublicKey;
+    const sessionPda = sessionSigner
+      ? PublicKey.findProgramAddressSync(
+          [SESSION_SEED, userPublicKey.toBuffer(), mint.toBuffer()],
+          TOSSR_PROGRAM_ID
+        )[0]
+      : TOSSR_PROGRAM_ID;
+
     const vaultTokenAccount = await getAssociatedTokenAddress(
       mint,
       vaultPda,
@@ -371,6 +487,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: true },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
         { pubkey: betPda, isSigner: false, isWritable: true },
         { pubkey: vaultPda, isSigner: false, isWritable: false },
@@ -396,6 +513,70 @@ export class TossrProgramService {
     return signature;
   }
 
+  /**
+   * Settle several vault-funded bets of one round in a single instruction.
+   * Each user's associated token account for `mint` must already exist.
+   */
+  async settleBetsBatch(
+    marketId: PublicKey,
+    roundNumber: number,
+    userPublicKeys: PublicKey[],
+    mint: PublicKey,
+    adminKeypair: Keypair
+  ): Promise<string> {
+    const roundNumberBuffer = Buffer.alloc(8);
+    roundNumberBuffer.writeBigUInt64LE(BigInt(roundNumber));
+
+    const [roundPda] = PublicKey.findProgramAddressSync(
+      [ROUND_SEED, marketId.toBuffer(), roundNumberBuffer],
+      TOSSR_PROGRAM_ID
+    );
+    const [vaultPda] = PublicKey.findProgramAddressSync(
+      [VAULT_SEED, marketId.toBuffer()],
+      TOSSR_PROGRAM_ID
+    );
+    const vaultTokenAccount = await getAssociatedTokenAddress(mint, vaultPda, true);
+
+    // Remaining accounts: (bet, user_token) pairs
+    const pairs: { pubkey: PublicKey; isSigner: boolean; isWritable: boolean }[] = [];
+    for (const userPublicKey of userPublicKeys) {
+      const [betPda] = PublicKey.findProgramAddressSync(
+        [BET_SEED, roundPda.toBuffer(), userPublicKey.toBuffer()],
+        TOSSR_PROGRAM_ID
+      );
+      const userTokenAccount = await getAssociatedTokenAddress(mint, userPublicKey);
+      pairs.push(
+        { pubkey: betPda, isSigner: false, isWritable: true },
+        { pubkey: userTokenAccount, isSigner: false, isWritable: true },
+      );
+    }
+
+    const instruction = new TransactionInstruction({
+      keys: [
+        { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
+        { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
+        { pubkey: roundPda, isSigner: false, isWritable: true },
+        { pubkey: vaultPda, isSigner: false, isWritable: false },
+        { pubkey: vaultTokenAccount, isSigner: false, isWritable: true },
+        { pubkey: mint, isSigner: false, isWritable: false },
+        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+        ...pairs,
+      ],
+      programId: TOSSR_PROGRAM_ID,
+      data: Buffer.from(DISCRIMINATORS.SETTLE_BETS_BATCH),
+    });
+
+    const transaction = new Transaction().add(instruction);
+    const { blockhash } = await this.connection.getLatestBlockhash();
+    transaction.recentBlockhash = blockhash;
+    transaction.feePayer = adminKeypair.publicKey;
+
+    const signature = await this.sendAndConfirm(this.connection, transaction, [adminKeypair], 'finalized');
+    logger.info({ signature, roundPda: roundPda.toString(), bets: userPublicKeys.length }, 'Bet batch settled on-chain');
+    return signature;
+  }
+
   async openRound(
     marketId: PublicKey,
     roundNumber: number,
@@ -415,7 +596,9 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: true },
         { pubkey: marketId, isSigner: false, isWritable: true },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
+        { pubkey: this.getMarketParamsPda(marketId), isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -496,6 +679,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: true },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -545,6 +729,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: true },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -590,6 +775,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: true },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -664,6 +850,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -711,6 +898,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -765,6 +953,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -808,6 +997,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -858,6 +1048,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -908,6 +1099,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -960,6 +1152,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -1010,6 +1203,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -1041,7 +1235,6 @@ export class TossrProgramService {
   async requestRandomnessER(
     marketId: PublicKey,
     roundNumber: number,
-    clientSeed: number,
     payer: Keypair,
     oracleQueue: PublicKey,
     opts?: { useER?: boolean },
@@ -1052,14 +1245,12 @@ export class TossrProgramService {
       [ROUND_SEED, marketId.toBuffer(), roundNumberBuffer],
       TOSSR_PROGRAM_ID
     );
-    const data = Buffer.concat([
-      DISCRIMINATORS.REQUEST_RANDOMNESS,
-      Buffer.from([clientSeed & 0xff]),
-    ]);
+    const data = Buffer.from(DISCRIMINATORS.REQUEST_RANDOMNESS);
     const ix = new TransactionInstruction({
       keys: [
         { pubkey: payer.publicKey, isSigner: true, isWritable: true },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
         { pubkey: oracleQueue, isSigner: false, isWritable: true },
         { pubkey: PROGRAM_IDENTITY_PDA, isSigner: false, isWritable: false },
@@ -1109,6 +1300,7 @@ export class TossrProgramService {
     const keys = [
       { pubkey: payer.publicKey, isSigner: true, isWritable: true },
       { pubkey: marketId, isSigner: false, isWritable: false },
+      { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
       { pubkey: roundPda, isSigner: false, isWritable: true },
       { pubkey: MAGIC_PROGRAM_PK, isSigner: false, isWritable: false },
       { pubkey: MAGIC_CONTEXT_PK, isSigner: false, isWritable: true },
@@ -1156,6 +1348,7 @@ export class TossrProgramService {
     const keys = [
       { pubkey: payer.publicKey, isSigner: true, isWritable: true },
       { pubkey: marketId, isSigner: false, isWritable: false },
+      { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
       { pubkey: roundPda, isSigner: false, isWritable: true },
       { pubkey: MAGIC_PROGRAM_PK, isSigner: false, isWritable: false },
       { pubkey: MAGIC_CONTEXT_PK, isSigner: false, isWritable: true },
@@ -1230,6 +1423,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -1284,6 +1478,7 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: false },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
       ],
       programId: TOSSR_PROGRAM_ID,
@@ -1337,6 +1532,7 @@ export class TossrProgramService {
         { pubkey: delegationMetadataPda, isSigner: false, isWritable: true },
         { pubkey: roundPda, isSigner: false, isWritable: true },
         { pubkey: marketId, isSigner: false, isWritable: false },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
         { pubkey: roundPda, isSigner: false, isWritable: true },
         { pubkey: TOSSR_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: DELEGATION_PROGRAM_PK, isSigner: false, isWritable: false },
@@ -1522,6 +1718,7 @@ export class TossrProgramService {
     const keys = [
       { pubkey: payer.publicKey, isSigner: true, isWritable: true },
       { pubkey: marketId, isSigner: false, isWritable: false },
+      { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
       { pubkey: roundPda, isSigner: false, isWritable: true },
       { pubkey: MAGIC_PROGRAM_PK, isSigner: false, isWritable: false },
       { pubkey: MAGIC_CONTEXT_PK, isSigner: false, isWritable: true },
@@ -1574,6 +1771,7 @@ export class TossrProgramService {
     const keys = [
       { pubkey: payer.publicKey, isSigner: true, isWritable: true },
       { pubkey: marketId, isSigner: false, isWritable: false },
+      { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
       { pubkey: roundPda, isSigner: false, isWritable: true },
       { pubkey: MAGIC_PROGRAM_PK, isSigner: false, isWritable: false },
       { pubkey: MAGIC_CONTEXT_PK, isSigner: false, isWritable: true },
@@ -1622,7 +1820,7 @@ export class TossrProgramService {
       if (!accountInfo) return 0;
 
       const data = accountInfo.data;
-      const unsettledBets = data.readUInt32LE(8 + 32 + 8 + 1 + 32);
+      const unsettledBets = data.readUInt32LE(8 + 1 + 32 + 8 + 1 + 32);
       return unsettledBets;
     } catch {
       return 0;
@@ -1640,9 +1838,10 @@ export class TossrProgramService {
     return roundPda;
   }
 
-  async getMarketPda(adminPublicKey: PublicKey): Promise<PublicKey> {
+  /** Markets created before the Config account were seeded by their admin key. */
+  async getLegacyMarketPda(adminPublicKey: PublicKey, index: number): Promise<PublicKey> {
     const indexBuf = Buffer.alloc(2);
-    indexBuf.writeUInt16LE(0, 0);
+    indexBuf.writeUInt16LE(index & 0xffff, 0);
     const [marketPda] = PublicKey.findProgramAddressSync(
       [MARKET_SEED, adminPublicKey.toBuffer(), indexBuf],
       TOSSR_PROGRAM_ID
@@ -1650,16 +1849,20 @@ export class TossrProgramService {
     return marketPda;
   }
 
-  async getMarketPdaByIndex(adminPublicKey: PublicKey, index: number): Promise<PublicKey> {
+  async getMarketPdaByIndex(index: number): Promise<PublicKey> {
     const indexBuf = Buffer.alloc(2);
     indexBuf.writeUInt16LE(index & 0xffff, 0);
     const [marketPda] = PublicKey.findProgramAddressSync(
-      [MARKET_SEED, adminPublicKey.toBuffer(), indexBuf],
+      [MARKET_SEED, indexBuf],
       TOSSR_PROGRAM_ID
     );
     return marketPda;
   }
 
+  getConfigPda(): PublicKey {
+    return CONFIG_PDA;
+  }
+
   async initializeMarket(
     adminKeypair: Keypair,
     name: string,
@@ -1668,7 +1871,7 @@ export class TossrProgramService {
     index: number,
     mint: PublicKey,
   ): Promise<{ signature: string; marketPda: PublicKey }> {
-    const marketPda = await this.getMarketPdaByIndex(adminKeypair.publicKey, index);
+    const marketPda = await this.getMarketPdaByIndex(index);
     const idlPath = path.resolve(__dirname, '../../../contracts/anchor/target/idl/tossr_engine.json');
     const idl = JSON.parse(fs.readFileSync(idlPath, 'utf8')) as Idl;
     const coder = new BorshCoder(idl as any);
@@ -1695,6 +1898,8 @@ export class TossrProgramService {
       keys: [
         { pubkey: adminKeypair.publicKey, isSigner: true, isWritable: true },
         { pubkey: marketPda, isSigner: false, isWritable: true },
+        { pubkey: CONFIG_PDA, isSigner: false, isWritable: false },
+        { pubkey: this.getMarketParamsPda(marketPda), isSigner: false, isWritable: true },
         { pubkey: mint, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
       ],
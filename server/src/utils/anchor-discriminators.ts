@@ -10,6 +10,7 @@ export const DISCRIMINATORS = {
   PLACE_BET: getAnchorDiscriminator('place_bet'),
   LOCK_ROUND: getAnchorDiscriminator('lock_round'),
   SETTLE_BET: getAnchorDiscriminator('settle_bet'),
+  SETTLE_BETS_BATCH: getAnchorDiscriminator('settle_bets_batch'),
   SETTLE_ROUND: getAnchorDiscriminator('settle_round'),
   COMMIT_OUTCOME_HASH: getAnchorDiscriminator('commit_outcome_hash'),
   REVEAL_OUTCOME_NUMERIC: getAnchorDiscriminator('reveal_outcome_numeric'),
@@ -26,6 +27,8 @@ export const DISCRIMINATORS = {
   DELEGATE_ROUND: getAnchorDiscriminator('delegate_round'),
   COMMIT_ROUND: getAnchorDiscriminator('commit_round'),
   COMMIT_AND_UNDELEGATE_ROUND: getAnchorDiscriminator('commit_and_undelegate_round'),
-  SET_HOUSE_EDGE_BPS: getAnchorDiscriminator('set_house_edge_bps'),
+  PROPOSE_MARKET_PARAMS: getAnchorDiscriminator('propose_market_params'),
+  EXECUTE_MARKET_PARAMS: getAnchorDiscriminator('execute_market_params'),
+  SET_PAUSE_FLAGS: getAnchorDiscriminator('set_pause_flags'),
   INITIALIZE_MARKET: getAnchorDiscriminator('initialize_market'),
 };
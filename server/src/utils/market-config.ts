@@ -5,6 +5,8 @@ export const marketConfigSchema = z.object({
   solanaAddress: z.string().min(1, 'Missing solanaAddress in market config'),
   mintAddress: z.string().optional(),
   houseEdgeBps: z.number().int().min(0).max(10000).optional(),
+  pendingHouseEdgeBps: z.number().int().min(0).max(10000).optional(),
+  houseEdgeEffectiveAt: z.coerce.date().optional(),
 });
 
 export type MarketConfig = z.infer<typeof marketConfigSchema>;